@@ -0,0 +1,652 @@
+//! Requests against the QuickBooks Online REST API that `quickbooks_ureq` does not wrap.
+//!
+//! Everything that modifies QuickBooks goes through [`QboClient::send`], which validates the
+//! [`Request`] locally and, in dry-run mode, records it instead of sending it.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{self, Read},
+    path::Path,
+};
+
+use quickbooks_ureq::AccessToken;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::QboClient,
+    commands::OutputError,
+    config::{self, Config, ConfigError},
+    entity::Entity,
+    model::DecodeError,
+};
+
+/// `minorversion` sent with every request
+pub const MINOR_VERSION: &str = "65";
+
+/// maximum number of operations QuickBooks accepts in a single `/batch` request
+pub const MAX_BATCH_SIZE: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+        }
+    }
+}
+
+/// A request against `/v3/company/<company id>/<endpoint>`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Request {
+    pub method: Method,
+    /// path relative to the company, eg. `customer` or `invoice/123/pdf`
+    pub endpoint: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+impl Request {
+    pub fn get(endpoint: impl Into<String>) -> Self {
+        Self {
+            method: Method::Get,
+            endpoint: endpoint.into(),
+            query: BTreeMap::new(),
+            body: None,
+        }
+    }
+
+    pub fn post(endpoint: impl Into<String>, body: serde_json::Value) -> Self {
+        Self {
+            method: Method::Post,
+            endpoint: endpoint.into(),
+            query: BTreeMap::new(),
+            body: Some(body),
+        }
+    }
+
+    /// `POST /<entity>`, eg. `Request::create("customer", json!({ "DisplayName": "..." }))`
+    pub fn create(entity: &str, body: serde_json::Value) -> Self {
+        Self::post(entity.to_lowercase(), body)
+    }
+
+    /// `POST /<entity>?operation=update`; `body` must contain `Id` and `SyncToken`
+    pub fn update(entity: &str, body: serde_json::Value) -> Self {
+        Self::create(entity, body).with_query("operation", "update")
+    }
+
+    /// `POST /<entity>?operation=delete`; `body` must contain `Id` and `SyncToken`
+    pub fn delete(entity: &str, body: serde_json::Value) -> Self {
+        Self::create(entity, body).with_query("operation", "delete")
+    }
+
+    /// `POST /<entity>?operation=void`; `body` must contain `Id` and `SyncToken`
+    pub fn void(entity: &str, body: serde_json::Value) -> Self {
+        Self::create(entity, body).with_query("operation", "void")
+    }
+
+    /// `GET /query?query=<query>`, eg. `Request::query("SELECT * FROM Customer")`
+    pub fn query(query: &str) -> Self {
+        Self::get("query").with_query("query", query)
+    }
+
+    /// `GET /<entity>/<id>`, eg. `Request::read("customer", "58")`
+    pub fn read(entity: &str, id: &str) -> Self {
+        Self::get(format!("{}/{id}", entity.to_lowercase()))
+    }
+
+    /// `GET /<entity>/<id>/pdf`, the PDF of a transaction
+    pub fn pdf(entity: &str, id: &str) -> Self {
+        Self::get(format!("{}/{id}/pdf", entity.to_lowercase()))
+    }
+
+    /// `GET /reports/<name>`, eg. `Request::report("ProfitAndLoss", &[("start_date", "2026-01-01")])`
+    pub fn report(name: &str, parameters: &[(&str, &str)]) -> Self {
+        parameters.iter().fold(
+            Self::get(format!("reports/{name}")),
+            |request, (key, value)| request.with_query(*key, *value),
+        )
+    }
+
+    /// `GET /cdc`, the changes to `entities` since `changed_since`
+    pub fn cdc(entities: &[Entity], changed_since: &str) -> Self {
+        let names: Vec<&str> = entities.iter().map(Entity::as_str).collect();
+
+        Self::get("cdc")
+            .with_query("entities", names.join(","))
+            .with_query("changedSince", changed_since)
+    }
+
+    pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn operation(&self) -> Option<&str> {
+        self.query.get("operation").map(String::as_str)
+    }
+
+    /// checks the request for mistakes QuickBooks would reject it for, without sending it
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.endpoint.is_empty() || self.endpoint.starts_with('/') {
+            return Err(ValidationError::InvalidEndpoint(self.endpoint.clone()));
+        }
+
+        if self.method == Method::Get {
+            return Ok(());
+        }
+
+        let body = self
+            .body
+            .as_ref()
+            .ok_or(ValidationError::MissingBody)?
+            .as_object()
+            .ok_or(ValidationError::BodyNotAnObject)?;
+
+        if body.is_empty() {
+            return Err(ValidationError::EmptyBody);
+        }
+
+        if let Some("update" | "delete" | "void") = self.operation() {
+            for field in ["Id", "SyncToken"] {
+                if let None | Some(serde_json::Value::Null) = body.get(field) {
+                    return Err(ValidationError::MissingField(field));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    InvalidEndpoint(String),
+    MissingBody,
+    BodyNotAnObject,
+    EmptyBody,
+    MissingField(&'static str),
+    NotAGetRequest,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidEndpoint(endpoint) => write!(f, "invalid endpoint: {endpoint:?}"),
+            Self::MissingBody => write!(f, "request has no body"),
+            Self::BodyNotAnObject => write!(f, "request body is not a JSON object"),
+            Self::EmptyBody => write!(f, "request body is empty"),
+            Self::MissingField(field) => write!(f, "request body is missing `{field}`"),
+            Self::NotAGetRequest => write!(f, "only GET requests can be fetched"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A request that was not sent because the client is in dry-run mode.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlannedRequest {
+    pub url: String,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Validation(ValidationError),
+    /// QuickBooks responded with an error status; the body is usually a `Fault`
+    Status(u16, serde_json::Value),
+    Transport(Box<ureq::Transport>),
+    QbUreq(Box<quickbooks_ureq::Error>),
+    /// failed to read a response body
+    Io(io::Error),
+    /// the response does not have the shape of the model it was read as
+    Decode(DecodeError),
+    /// a request of [`AsyncClient`](crate::client::AsyncClient) failed to be sent or read
+    #[cfg(feature = "async")]
+    Http(reqwest::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Validation(error) => write!(f, "invalid request: {error}"),
+            Self::Status(status, body) => write!(f, "QuickBooks responded with {status}: {body}"),
+            Self::Transport(error) => error.fmt(f),
+            Self::QbUreq(error) => error.fmt(f),
+            Self::Io(error) => write!(f, "failed to read the response: {error}"),
+            Self::Decode(error) => error.fmt(f),
+            #[cfg(feature = "async")]
+            Self::Http(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Validation(error) => Some(error),
+            Self::Status(..) => None,
+            Self::Transport(error) => Some(error),
+            Self::QbUreq(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::Decode(error) => Some(error),
+            #[cfg(feature = "async")]
+            Self::Http(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Http(error)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(error: DecodeError) -> Self {
+        Self::Decode(error)
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        Self::Validation(error)
+    }
+}
+
+impl From<quickbooks_ureq::Error> for Error {
+    fn from(error: quickbooks_ureq::Error) -> Self {
+        Self::QbUreq(Box::new(error))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Error {
+    /// `code`s of the errors in a `Fault` response, eg. `["5010"]`
+    pub fn fault_codes(&self) -> Vec<&str> {
+        match self {
+            Self::Status(_, body) => body
+                .pointer("/Fault/Error")
+                .and_then(serde_json::Value::as_array)
+                .map(|errors| {
+                    errors
+                        .iter()
+                        .filter_map(|error| error.get("code")?.as_str())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// whether QuickBooks rejected the request because the `SyncToken` it was sent with is stale
+    pub fn is_stale_object(&self) -> bool {
+        self.fault_codes().contains(&"5010")
+    }
+
+    pub(crate) fn from_ureq(error: ureq::Error) -> Self {
+        match error {
+            ureq::Error::Status(status, response) => {
+                let body = response
+                    .into_string()
+                    .map(|body| {
+                        serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body))
+                    })
+                    .unwrap_or_default();
+
+                Self::Status(status, body)
+            }
+            ureq::Error::Transport(transport) => Self::Transport(Box::new(transport)),
+        }
+    }
+}
+
+/// A client that sends [`Request`]s to QuickBooks (or the `base_url` of the config).
+///
+/// An access token QuickBooks rejects is refreshed (and written back to the config file of the
+/// profile, if the client was connected to one) and the request sent again.
+pub struct Client {
+    base_url: String,
+    company_id: String,
+    token: RefCell<AccessToken>,
+    /// the config, and the file it is written back to when the access token is refreshed
+    config: (Config, Option<String>),
+    dry_run: bool,
+    planned: RefCell<Vec<PlannedRequest>>,
+}
+
+impl Client {
+    /// exits with an example config if `config` has no access token; see [`Client::from_config`]
+    pub(crate) fn new(config: Config, dry_run: bool) -> Self {
+        let token = config::token_or_exit(&config);
+
+        Self::with_token(config, token, dry_run)
+    }
+
+    /// a client for `config`, which is not checked against QuickBooks; refreshed access tokens are
+    /// kept, but not written anywhere
+    pub fn from_config(config: Config, dry_run: bool) -> Result<Self, ConfigError> {
+        let token = config.token.clone().ok_or(ConfigError::NoToken)?;
+
+        Ok(Self::with_token(config, token, dry_run))
+    }
+
+    fn with_token(config: Config, token: AccessToken, dry_run: bool) -> Self {
+        Self {
+            base_url: config.base_url(),
+            company_id: config.company_id.clone(),
+            token: RefCell::new(token),
+            config: (config, None),
+            dry_run,
+            planned: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Reads the config of `profile` (see [`Config::read`]) and authorizes against QuickBooks,
+    /// refreshing the access token if it has expired.
+    ///
+    /// For embedding qbtools: unlike the command line, nothing is printed and the process is not
+    /// exited if the config is missing or the token cannot be refreshed.
+    pub fn connect(profile: Option<&str>, dry_run: bool) -> Result<Self, ConfigError> {
+        let mut client = Self::from_config(Config::read(profile)?, dry_run)?;
+        client.config.1 = Some(config::profile_base_path(profile));
+
+        let request = Request::get(format!("companyinfo/{}", client.company_id));
+        match client.respond_once(&request, "application/json") {
+            Ok(_) => {}
+            Err(Error::Status(401, _)) => client.refresh()?,
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(client)
+    }
+
+    /// the company (realm) requests are sent to
+    pub fn company_id(&self) -> &str {
+        &self.company_id
+    }
+
+    /// reads the config and authorizes against QuickBooks (see `config::get_authorized_config`)
+    pub(crate) fn authorized(quiet: bool, dry_run: bool) -> Result<Self, OutputError> {
+        let mut client = Self::new(config::get_authorized_config(quiet)?, dry_run);
+        client.config.1 = Some(config::base_path());
+
+        Ok(client)
+    }
+
+    /// sends `request`, asking for a response of type `accept`; a rejected access token is
+    /// refreshed, and the request sent again
+    fn respond(&self, request: &Request, accept: &str) -> Result<ureq::Response, Error> {
+        match self.respond_once(request, accept) {
+            Err(Error::Status(401, body)) => {
+                if let Err(err) = self.refresh() {
+                    log::error!("{err}");
+                    return Err(Error::Status(401, body));
+                }
+                self.respond_once(request, accept)
+            }
+            result => result,
+        }
+    }
+
+    fn respond_once(&self, request: &Request, accept: &str) -> Result<ureq::Response, Error> {
+        let access_token = self.token.borrow().access_token.clone();
+        let mut http = ureq::request(request.method.as_str(), &self.url(request))
+            .set("Authorization", &format!("Bearer {access_token}"))
+            .set("Accept", accept)
+            .query("minorversion", MINOR_VERSION);
+
+        for (key, value) in &request.query {
+            http = http.query(key, value);
+        }
+
+        match &request.body {
+            Some(body) => http.send_json(body),
+            None => http.call(),
+        }
+        .map_err(Error::from_ureq)
+    }
+
+    /// gets a new access token with the refresh token, writing it to the config file (if there is
+    /// one)
+    fn refresh(&self) -> Result<(), ConfigError> {
+        log::debug!("access token rejected, refreshing it");
+
+        let (config, base_path) = &self.config;
+        let token = self.token.borrow().clone();
+        if token.refresh_token.is_empty() {
+            return Err(ConfigError::NoToken);
+        }
+
+        let refreshed = config::make_client(&Config {
+            token: Some(token),
+            ..config.clone()
+        })
+        .refresh_access_token_with_reqwest()
+        .map_err(|error| ConfigError::Refresh(error.to_string()))?;
+        *self.token.borrow_mut() = refreshed.clone();
+
+        if let Some(base_path) = base_path {
+            let config = Config {
+                token: Some(refreshed),
+                ..config.clone()
+            };
+            config.write_to(Path::new(base_path))?;
+            // the command line would authorize with the token that was just replaced
+            config::forget_authorization();
+        }
+
+        Ok(())
+    }
+}
+
+impl QboClient for Client {
+    fn url(&self, request: &Request) -> String {
+        format!(
+            "{}/v3/company/{}/{}",
+            self.base_url, self.company_id, request.endpoint
+        )
+    }
+
+    fn call(&self, request: &Request) -> Result<serde_json::Value, Error> {
+        let response = self.respond(request, "application/json")?;
+
+        Ok(response.into_json()?)
+    }
+
+    fn call_bytes(&self, request: &Request) -> Result<Vec<u8>, Error> {
+        let response = self.respond(request, "application/pdf")?;
+
+        let mut bytes = Vec::new();
+        response.into_reader().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn plan(&self, request: PlannedRequest) {
+        self.planned.borrow_mut().push(request);
+    }
+
+    fn planned_requests(&self) -> Vec<PlannedRequest> {
+        self.planned.borrow().clone()
+    }
+}
+
+/// A single operation of a `/batch` request.
+///
+/// Deserializes from eg. `{ "operation": "update", "entity": "Customer", "body": { ... } }` or
+/// `{ "operation": "query", "query": "SELECT * FROM Item" }`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Create {
+        entity: Entity,
+        body: serde_json::Value,
+    },
+    Update {
+        entity: Entity,
+        body: serde_json::Value,
+    },
+    Delete {
+        entity: Entity,
+        body: serde_json::Value,
+    },
+    Query {
+        query: String,
+    },
+}
+
+impl BatchOperation {
+    /// the equivalent single request, used for validation
+    fn request(&self) -> Option<Request> {
+        match self {
+            Self::Create { entity, body } => Some(Request::create(entity.as_str(), body.clone())),
+            Self::Update { entity, body } => Some(Request::update(entity.as_str(), body.clone())),
+            Self::Delete { entity, body } => Some(Request::delete(entity.as_str(), body.clone())),
+            Self::Query { .. } => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match self {
+            Self::Query { query } if query.trim().is_empty() => Err(ValidationError::EmptyBody),
+            Self::Query { .. } => Ok(()),
+            _ => self.request().map_or(Ok(()), |request| request.validate()),
+        }
+    }
+
+    /// `BatchItemRequest` with the operation's index as its `bId`
+    pub(crate) fn to_item(&self, index: usize) -> serde_json::Value {
+        let mut item = serde_json::Map::new();
+        item.insert("bId".to_string(), index.to_string().into());
+
+        match self {
+            Self::Create { entity, body }
+            | Self::Update { entity, body }
+            | Self::Delete { entity, body } => {
+                let operation = match self {
+                    Self::Create { .. } => "create",
+                    Self::Update { .. } => "update",
+                    _ => "delete",
+                };
+
+                item.insert("operation".to_string(), operation.into());
+                item.insert(entity.as_str().to_string(), body.clone());
+            }
+            Self::Query { query } => {
+                item.insert("Query".to_string(), query.clone().into());
+            }
+        }
+
+        serde_json::Value::Object(item)
+    }
+}
+
+/// A `BatchItemResponse`, matched up with the operation it belongs to.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BatchItemResult {
+    /// index of the operation in the list passed to [`QboClient::batch`]
+    pub index: usize,
+    pub operation: BatchOperation,
+    /// the affected entity, or the `QueryResponse` of a query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<serde_json::Value>,
+}
+
+impl BatchItemResult {
+    pub(crate) fn from_item(
+        mut item: serde_json::Value,
+        operations: &[BatchOperation],
+    ) -> Option<Self> {
+        let object = item.as_object_mut()?;
+
+        let index = object
+            .remove("bId")
+            .and_then(|b_id| b_id.as_str()?.parse::<usize>().ok())
+            .filter(|index| *index < operations.len());
+        let Some(index) = index else {
+            log::error!("batch response item with unknown bId: {item}");
+            return None;
+        };
+
+        let fault = object.remove("Fault");
+        let result = match fault {
+            Some(_) => None,
+            None => Some(take_entity(&mut item)),
+        };
+
+        Some(Self {
+            index,
+            operation: operations[index].clone(),
+            result,
+            fault,
+        })
+    }
+}
+
+/// takes the single entity out of a read/create/update response (`{ "Customer": {...}, "time": "..." }`)
+pub fn take_entity(response: &mut serde_json::Value) -> serde_json::Value {
+    match response.as_object_mut() {
+        Some(object) => match object.keys().find(|key| key.as_str() != "time").cloned() {
+            Some(key) => object.remove(&key).unwrap_or_default(),
+            None => serde_json::Value::Null,
+        },
+        None => response.take(),
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, Options};
+
+    #[test]
+    fn refreshes_a_rejected_access_token_and_sends_the_request_again() {
+        let server = MockServer::start(
+            "127.0.0.1:0",
+            Options {
+                token_lifetime: Some(1),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+        let config: Config = serde_json::from_value(server.config()).unwrap();
+        let access_token = config.token.clone().unwrap().access_token;
+
+        // every request after the first is rejected once, and refreshes the access token
+        let client = Client::from_config(config, false).unwrap();
+        for id in ["1", "2", "3"] {
+            assert_eq!(client.read("customer", id).unwrap()["Id"], id);
+        }
+        assert!(!client.pdf("invoice", "101").unwrap().is_empty());
+
+        assert_ne!(client.token.borrow().access_token, access_token);
+        assert_eq!(
+            server.config()["access_token"],
+            client.token.borrow().access_token
+        );
+    }
+}
@@ -1,8 +1,23 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use clap::{Parser, Subcommand};
 
+#[cfg(feature = "cmd-completions")]
+use crate::commands::completions;
+#[cfg(any(feature = "cmd-remove", feature = "cmd-update"))]
+use crate::entity::Entity;
+use crate::{filter::Filter, query::Operator};
+
 #[derive(Debug, Parser, PartialEq)]
+#[cfg_attr(
+    feature = "interactive",
+    command(after_help = "Run `qbtools -i` to start an interactive session.")
+)]
 pub struct Args {
     #[clap(subcommand)]
     pub command: Command,
@@ -16,41 +31,222 @@ pub struct Args {
         help = "print additional information (useful for debugging)"
     )]
     pub verbose: bool,
+
+    #[clap(
+        long,
+        help = "validate and print requests that would modify QuickBooks as JSON instead of sending them"
+    )]
+    pub dry_run: bool,
+
+    #[clap(
+        long,
+        help = "read the config from qb-api-cfg-<PROFILE>.* instead of qb-api-cfg.*"
+    )]
+    pub profile: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "read and write the <Entity>.json files in DIR instead of QuickBooks"
+    )]
+    pub mirror: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with = "replay",
+        help = "save every request and response to DIR/cassette.jsonl, with tokens and personal details redacted"
+    )]
+    pub record: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "answer requests with the responses recorded by --record in DIR instead of sending them"
+    )]
+    pub replay: Option<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, Subcommand)]
 pub enum Command {
     #[cfg(feature = "cmd-export")]
     Export {
-        #[arg(short, long, default_value = "json")]
-        format: Option<OutputFormat>,
-        #[arg(short, long)]
-        output_path: Option<PathBuf>,
-        #[arg(long)]
-        pretty: bool,
+        #[command(flatten)]
+        output: OutputArgs,
 
         #[clap(subcommand)]
         command: ExportCommands,
     },
     #[cfg(feature = "cmd-get")]
     Get {
-        #[arg(short, long, default_value = "json")]
-        format: Option<OutputFormat>,
+        #[command(flatten)]
+        output: OutputArgs,
+
         #[arg(long)]
+        #[cfg_attr(feature = "cmd-completions", arg(add = completions::ids()))]
         id: String,
-        #[arg(short, long)]
-        output_path: Option<PathBuf>,
 
         #[clap(subcommand)]
         command: GetCommands,
     },
+    /// sparse-update a single entity
+    #[cfg(feature = "cmd-update")]
+    Update {
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[cfg_attr(feature = "cmd-completions", arg(add = completions::entities()))]
+        entity: Entity,
+        #[arg(long)]
+        #[cfg_attr(feature = "cmd-completions", arg(add = completions::ids()))]
+        id: String,
+        #[arg(
+            long,
+            value_name = "FIELD.PATH=VALUE",
+            required_unless_present = "patch",
+            help = "set a field to the string VALUE, or to a JSON value with FIELD.PATH:=VALUE (eg. Active:=false)"
+        )]
+        set: Vec<SetField>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "JSON/TOML/YAML object of fields to update"
+        )]
+        patch: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "re-read the entity and reapply the update if it was modified in the meantime"
+        )]
+        retry_on_conflict: bool,
+    },
+    /// deactivate, delete or void entities, backing each one up first
+    #[cfg(feature = "cmd-remove")]
+    Remove {
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[cfg_attr(feature = "cmd-completions", arg(add = completions::entities()))]
+        entity: Entity,
+        #[arg(long = "id", required = true)]
+        #[cfg_attr(feature = "cmd-completions", arg(add = completions::ids()))]
+        ids: Vec<String>,
+        #[arg(
+            long,
+            help = "void transactions that support it (eg. invoices) instead of deleting them"
+        )]
+        void: bool,
+        #[arg(short, long, help = "do not ask for confirmation")]
+        yes: bool,
+        #[arg(long, default_value = "qbtools-backups")]
+        backup_dir: PathBuf,
+    },
+    /// run create/update/delete/query operations through the batch endpoint
+    #[cfg(feature = "cmd-batch")]
+    Batch {
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[arg(long, value_name = "FILE", help = "JSON/TOML/YAML list of operations")]
+        input: PathBuf,
+    },
+    /// export a financial report
+    #[cfg(feature = "cmd-report")]
+    Report {
+        #[command(flatten)]
+        output: OutputArgs,
+
+        report: ReportName,
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        from: Option<String>,
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        to: Option<String>,
+        #[arg(long, value_name = "cash|accrual")]
+        accounting_method: Option<AccountingMethod>,
+        #[arg(
+            long,
+            value_name = "COLUMN",
+            help = "eg. Total, Month, Quarter, Year, Customers, Classes"
+        )]
+        summarize_by: Option<String>,
+        #[arg(
+            long,
+            help = "output one row per line of the report, even for formats that can nest"
+        )]
+        flat: bool,
+    },
+    /// run a query, fetching every page of results
+    #[cfg(feature = "cmd-query")]
+    Query {
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[arg(
+            help = "eg. \"SELECT Id, DisplayName FROM Customer WHERE Balance > '0' ORDERBY DisplayName\""
+        )]
+        query: String,
+        #[arg(
+            long,
+            default_value_t = crate::export::MAX_PAGE_SIZE,
+            value_parser = clap::value_parser!(u32).range(1..=i64::from(crate::export::MAX_PAGE_SIZE)),
+            help = "records fetched per request"
+        )]
+        page_size: u32,
+    },
+    /// download the PDFs of invoices, estimates and sales receipts
+    #[cfg(feature = "cmd-pdf")]
+    Pdf {
+        #[clap(subcommand)]
+        command: PdfCommands,
+    },
+    /// browse customers, items, invoices and vendors full-screen
+    #[cfg(feature = "tui")]
+    Tui,
+    /// serve a mock QuickBooks API locally, printing a config that uses it
+    #[cfg(feature = "mock-server")]
+    MockServer {
+        #[arg(long, default_value_t = 8080, help = "0 picks a free port")]
+        port: u16,
+        #[arg(
+            long,
+            help = "directory of <Entity>.json files [default: built-in fixtures]"
+        )]
+        fixtures: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "requests an access token is valid for before it has to be refreshed"
+        )]
+        token_lifetime: Option<u32>,
+        #[arg(long, help = "requests answered per minute before the rest get a 429")]
+        throttle: Option<u32>,
+    },
+    /// print a script that completes qbtools commands in SHELL
+    #[cfg(feature = "cmd-completions")]
+    #[command(
+        after_help = "eg. `source <(qbtools completions bash)` in ~/.bashrc; with --mirror, IDs are completed from the mirror"
+    )]
+    Completions {
+        #[arg(value_parser = ["bash", "elvish", "fish", "powershell", "zsh"])]
+        shell: String,
+    },
+    /// write a man page for qbtools and each of its subcommands
+    #[cfg(feature = "cmd-man")]
+    Man {
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// receive webhook notifications of changes from QuickBooks
+    #[cfg(feature = "cmd-webhook")]
+    Webhook {
+        #[clap(subcommand)]
+        command: WebhookCommands,
+    },
 }
 
 #[cfg(feature = "cmd-export")]
 #[derive(Debug, PartialEq, Subcommand)]
 pub enum ExportCommands {
     Customers(ExportCustomerArgs),
-    Items,
+    Items(ExportItemArgs),
 }
 
 #[cfg(feature = "cmd-export")]
@@ -60,36 +256,336 @@ pub enum GetCommands {
     Item,
 }
 
+#[cfg(feature = "cmd-pdf")]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum PdfCommands {
+    Invoice(PdfArgs),
+    Invoices(PdfBulkArgs),
+    Estimate(PdfArgs),
+    Estimates(PdfBulkArgs),
+    SalesReceipt(PdfArgs),
+    SalesReceipts(PdfBulkArgs),
+}
+
+#[cfg(feature = "cmd-pdf")]
+#[derive(clap::Args, Debug, PartialEq)]
+pub struct PdfArgs {
+    #[arg(long)]
+    pub id: String,
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "[default: named with --name]"
+    )]
+    pub output_path: Option<PathBuf>,
+    #[command(flatten)]
+    pub file: PdfFileArgs,
+}
+
+#[cfg(feature = "cmd-pdf")]
+#[derive(clap::Args, Debug, PartialEq)]
+pub struct PdfBulkArgs {
+    #[arg(long, help = "eg. \"TxnDate >= '2026-01-01'\"")]
+    pub r#where: Option<String>,
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub out_dir: PathBuf,
+    #[command(flatten)]
+    pub file: PdfFileArgs,
+}
+
+#[cfg(feature = "cmd-pdf")]
+#[derive(clap::Args, Debug, PartialEq)]
+pub struct PdfFileArgs {
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        default_value = "{DocNumber}-{CustomerRef.name}.pdf",
+        help = "file name, with every {Field.Path} replaced by that field of the transaction"
+    )]
+    pub name: String,
+    #[arg(
+        long,
+        help = "overwrite files that already exist instead of skipping them"
+    )]
+    pub force: bool,
+}
+
+#[cfg(feature = "cmd-webhook")]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum WebhookCommands {
+    /// serve the webhook URL, printing every changed entity as a line of JSON (or writing it to
+    /// --mirror)
+    Serve {
+        #[arg(
+            long,
+            default_value = "127.0.0.1",
+            help = "0.0.0.0 for every interface"
+        )]
+        bind: String,
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        #[arg(
+            long,
+            help = "the verifier token of the webhook, from the Intuit developer portal"
+        )]
+        verifier_token: String,
+        #[arg(
+            long,
+            value_name = "COMMAND",
+            help = "run COMMAND with the shell for every changed entity, with its line of JSON on stdin"
+        )]
+        exec: Option<String>,
+    },
+    /// POST a notification signed with the verifier token, eg. to try out `webhook serve`
+    Send {
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        url: String,
+        #[arg(long)]
+        verifier_token: String,
+        #[arg(value_name = "FILE", help = "JSON payload with eventNotifications")]
+        payload: PathBuf,
+    },
+}
+
 #[derive(clap::Args, Debug, PartialEq)]
 pub struct ExportCustomerArgs {
     #[arg(long)]
     pub r#where: Option<String>,
+    #[command(flatten)]
+    pub query: ExportQueryArgs,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(clap::Args, Debug, PartialEq)]
+pub struct ExportItemArgs {
+    #[command(flatten)]
+    pub query: ExportQueryArgs,
+}
+
+// options of every export subcommand, which become part of the query that is sent
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct ExportQueryArgs {
+    #[command(flatten)]
+    pub conditions: WhereArgs,
+    #[arg(
+        long,
+        num_args = 1..=2,
+        value_names = ["FIELD", "asc|desc"],
+        help = "sort by FIELD (ascending unless followed by desc); can be given more than once"
+    )]
+    pub order_by: Vec<String>,
+    #[arg(
+        long,
+        value_name = "DATETIME",
+        help = "only entities modified at or after DATETIME (YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS, with an optional offset)"
+    )]
+    pub updated_since: Option<String>,
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["FROM", "TO"],
+        help = "only entities created between FROM and TO, inclusive"
+    )]
+    pub created_between: Vec<String>,
+    #[arg(
+        long,
+        help = "also export inactive entities, which QuickBooks leaves out by default"
+    )]
+    pub include_inactive: bool,
+}
+
+// conditions given as `--where-field`, `--where-op` and `--where-value` (each the same number of
+// times), which are validated and escaped, then combined with `AND`
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct WhereArgs {
+    #[arg(long = "where-field", value_name = "FIELD")]
+    pub fields: Vec<String>,
+    #[arg(long = "where-op", value_name = "eq|lt|le|gt|ge|like|in")]
+    pub operators: Vec<Operator>,
+    #[arg(
+        long = "where-value",
+        value_name = "VALUE",
+        help = "quoted and escaped for the query; a comma-separated list for `in`"
+    )]
+    pub values: Vec<String>,
+}
+
+// where and how a command writes its output (not a doc comment, which clap would use as the
+// description of every command it is flattened into)
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct OutputArgs {
+    #[arg(short, long, help = "[default: table on a terminal, json otherwise]")]
+    pub format: Option<OutputFormat>,
+    #[arg(short, long)]
+    pub output_path: Option<PathBuf>,
+    #[arg(long)]
+    pub pretty: bool,
+    #[command(flatten)]
+    pub select: SelectArgs,
+}
+
+impl OutputArgs {
+    /// `--format`, or the default for `--output-path`
+    pub fn format(&self) -> OutputFormat {
+        self.format
+            .clone()
+            .unwrap_or_else(|| OutputFormat::default_for(&self.output_path))
+    }
+}
+
+// `--fields` and `--filter`, applied to the output of a command whatever its format, and options of
+// particular formats (not a doc comment, which clap would use as the description of every command
+// it is flattened into)
+#[derive(clap::Args, Clone, Debug, Default, PartialEq)]
+pub struct SelectArgs {
+    #[arg(
+        long,
+        value_name = "FIELD.PATH,...",
+        value_delimiter = ',',
+        help = "only output these fields, eg. Id,DisplayName,PrimaryEmailAddr.Address"
+    )]
+    pub fields: Vec<String>,
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "only output records matching EXPRESSION, eg. \"Balance > 0 && DisplayName =~ /^a/i\""
+    )]
+    pub filter: Option<Filter>,
+    #[arg(
+        long,
+        help = "show every column of table output, without truncating long text"
+    )]
+    pub wide: bool,
+    #[cfg(feature = "template")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "render the output through a Handlebars template, with the records as `records`"
+    )]
+    pub template: Option<PathBuf>,
+    #[cfg(feature = "template")]
+    #[arg(
+        long,
+        requires = "template",
+        help = "render the template once per record, with the record as its context"
+    )]
+    pub each: bool,
+}
+
+impl SelectArgs {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.filter.is_none()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum OutputFormat {
+    #[default]
     Json,
+    Csv,
+    Tsv,
+    Table,
     #[cfg(feature = "toml")]
     Toml,
     #[cfg(feature = "yaml")]
     Yaml,
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+    #[cfg(feature = "parquet")]
+    Parquet,
+    #[cfg(feature = "arrow")]
+    Arrow,
+    #[cfg(feature = "template")]
+    Template,
 }
 
 impl OutputFormat {
+    #[cfg(feature = "interactive")]
+    pub const ALL: &'static [Self] = &[
+        Self::Json,
+        Self::Csv,
+        Self::Tsv,
+        Self::Table,
+        #[cfg(feature = "toml")]
+        Self::Toml,
+        #[cfg(feature = "yaml")]
+        Self::Yaml,
+        #[cfg(feature = "xlsx")]
+        Self::Xlsx,
+        #[cfg(feature = "parquet")]
+        Self::Parquet,
+        #[cfg(feature = "arrow")]
+        Self::Arrow,
+        // not the template format, which needs a --template to go with it
+    ];
+
     pub const fn as_str(&self) -> &str {
         match self {
             Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Table => "table",
             #[cfg(feature = "toml")]
             Self::Toml => "toml",
             #[cfg(feature = "yaml")]
             Self::Yaml => "yaml",
+            #[cfg(feature = "xlsx")]
+            Self::Xlsx => "xlsx",
+            #[cfg(feature = "parquet")]
+            Self::Parquet => "parquet",
+            #[cfg(feature = "arrow")]
+            Self::Arrow => "arrow",
+            #[cfg(feature = "template")]
+            Self::Template => "template",
+        }
+    }
+
+    /// whether the format is made of rows and columns, so nested values have to be flattened
+    #[allow(clippy::match_like_matches_macro)]
+    pub const fn is_tabular(&self) -> bool {
+        match self {
+            Self::Csv | Self::Tsv | Self::Table => true,
+            #[cfg(feature = "xlsx")]
+            Self::Xlsx => true,
+            _ => false,
+        }
+    }
+
+    /// whether records are written under the name of their entity, as a sheet, a table with the
+    /// columns of that entity or an array of tables
+    #[allow(clippy::match_like_matches_macro)]
+    pub const fn has_sections(&self) -> bool {
+        match self {
+            Self::Table => true,
+            #[cfg(feature = "toml")]
+            Self::Toml => true,
+            #[cfg(feature = "xlsx")]
+            Self::Xlsx => true,
+            _ => false,
+        }
+    }
+
+    /// `table` if `output_path` is None and stdout is a terminal, otherwise `json`
+    pub fn default_for(output_path: &Option<PathBuf>) -> Self {
+        if output_path.is_none() && io::stdout().is_terminal() {
+            Self::Table
+        } else {
+            Self::Json
         }
     }
-}
 
-impl Default for OutputFormat {
-    fn default() -> Self {
-        Self::Json
+    /// whether the format is not text, so it should not be written to a terminal
+    #[allow(clippy::match_like_matches_macro)]
+    pub const fn is_binary(&self) -> bool {
+        match self {
+            #[cfg(feature = "xlsx")]
+            Self::Xlsx => true,
+            #[cfg(feature = "parquet")]
+            Self::Parquet => true,
+            #[cfg(feature = "arrow")]
+            Self::Arrow => true,
+            _ => false,
+        }
     }
 }
 
@@ -100,17 +596,186 @@ impl FromStr for OutputFormat {
     fn from_str(format: &str) -> Result<Self, Self::Err> {
         match format {
             "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            "table" => Ok(Self::Table),
             #[cfg(feature = "toml")]
             "toml" => Ok(Self::Toml),
             #[cfg(feature = "yaml")]
             "yaml" => Ok(Self::Yaml),
+            #[cfg(feature = "xlsx")]
+            "xlsx" => Ok(Self::Xlsx),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(Self::Parquet),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(Self::Arrow),
+            #[cfg(feature = "template")]
+            "template" => Ok(Self::Template),
             _ => Err("Could not parse output format"),
         }
     }
 }
 
-impl ToString for OutputFormat {
-    fn to_string(&self) -> String {
-        self.as_str().to_string()
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `Field.Path=value`, eg. `PrimaryEmailAddr.Address=someone@example.com`, or `Field.Path:=json`,
+/// eg. `Active:=false`; a value after `=` is always a string, since eg. `DocNumber=1037` is one
+#[cfg(feature = "cmd-update")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetField {
+    pub path: Vec<String>,
+    pub value: serde_json::Value,
+}
+
+#[cfg(feature = "cmd-update")]
+impl FromStr for SetField {
+    type Err = &'static str;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        let (path, value) = field
+            .split_once('=')
+            .ok_or("expected FIELD.PATH=VALUE or FIELD.PATH:=JSON")?;
+
+        let (path, value) = match path.strip_suffix(':') {
+            Some(path) => (
+                path,
+                serde_json::from_str(value).map_err(|_| "the value after := must be JSON")?,
+            ),
+            None => (path, serde_json::Value::String(value.to_string())),
+        };
+
+        let path: Vec<String> = path.split('.').map(str::to_string).collect();
+        if path.iter().any(String::is_empty) {
+            return Err("field path must not have empty components");
+        }
+
+        Ok(Self { path, value })
+    }
+}
+
+#[cfg(feature = "cmd-report")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportName {
+    ProfitAndLoss,
+    BalanceSheet,
+    AgedReceivables,
+    AgedPayables,
+    TrialBalance,
+    GeneralLedger,
+    CashFlow,
+}
+
+#[cfg(feature = "cmd-report")]
+impl ReportName {
+    pub const ALL: [Self; 7] = [
+        Self::ProfitAndLoss,
+        Self::BalanceSheet,
+        Self::AgedReceivables,
+        Self::AgedPayables,
+        Self::TrialBalance,
+        Self::GeneralLedger,
+        Self::CashFlow,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ProfitAndLoss => "ProfitAndLoss",
+            Self::BalanceSheet => "BalanceSheet",
+            Self::AgedReceivables => "AgedReceivables",
+            Self::AgedPayables => "AgedPayables",
+            Self::TrialBalance => "TrialBalance",
+            Self::GeneralLedger => "GeneralLedger",
+            Self::CashFlow => "CashFlow",
+        }
+    }
+
+    /// aging reports are as of a single date instead of covering a period
+    pub const fn is_aging(&self) -> bool {
+        matches!(self, Self::AgedReceivables | Self::AgedPayables)
+    }
+}
+
+#[cfg(feature = "cmd-report")]
+impl FromStr for ReportName {
+    type Err = &'static str;
+
+    fn from_str(report: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(report))
+            .ok_or("Could not parse report name")
+    }
+}
+
+#[cfg(feature = "cmd-report")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccountingMethod {
+    Cash,
+    Accrual,
+}
+
+#[cfg(feature = "cmd-report")]
+impl AccountingMethod {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cash => "Cash",
+            Self::Accrual => "Accrual",
+        }
+    }
+}
+
+#[cfg(feature = "cmd-report")]
+impl FromStr for AccountingMethod {
+    type Err = &'static str;
+
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method.to_lowercase().as_str() {
+            "cash" => Ok(Self::Cash),
+            "accrual" => Ok(Self::Accrual),
+            _ => Err("Could not parse accounting method"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "cmd-update"))]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn set_field_values_are_strings() {
+        let field: SetField = "PrimaryPhone.FreeFormNumber=5551234".parse().unwrap();
+        assert_eq!(field.path, ["PrimaryPhone", "FreeFormNumber"]);
+        assert_eq!(field.value, json!("5551234"));
+
+        let field: SetField = "DocNumber=1037".parse().unwrap();
+        assert_eq!(field.value, json!("1037"));
+
+        let field: SetField = "Notes=a=b".parse().unwrap();
+        assert_eq!(field.value, json!("a=b"));
+    }
+
+    #[test]
+    fn set_field_json_values() {
+        let field: SetField = "Active:=false".parse().unwrap();
+        assert_eq!(field.path, ["Active"]);
+        assert_eq!(field.value, json!(false));
+
+        let field: SetField = r#"BillAddr:={"City":"Tucson"}"#.parse().unwrap();
+        assert_eq!(field.value, json!({ "City": "Tucson" }));
+
+        assert!("Active:=nope".parse::<SetField>().is_err());
+    }
+
+    #[test]
+    fn set_field_rejects_bad_paths() {
+        assert!("Active".parse::<SetField>().is_err());
+        assert!("PrimaryEmailAddr..Address=x".parse::<SetField>().is_err());
+        assert!("=x".parse::<SetField>().is_err());
     }
 }
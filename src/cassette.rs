@@ -0,0 +1,617 @@
+//! Recording the traffic between qbtools and QuickBooks, and replaying it without the network.
+//!
+//! `--record <dir>` sends every request through a local proxy that forwards it to QuickBooks and
+//! appends it, with its response, to `<dir>/cassette.jsonl`. `--replay <dir>` answers requests from
+//! that file instead, so a cassette attached to a bug report reproduces what qbtools saw.
+//!
+//! Nothing secret is written: headers (and so the access token) are left out, the company id is
+//! replaced, and names, addresses, email addresses, phone numbers and notes are redacted as
+//! `redacted-<hash>`, in requests and responses. The same text is always redacted the same way, so
+//! references between entities still line up and a replayed request matches the recorded one.
+//! Binary bodies, such as the PDF of an invoice, cannot be redacted and are not recorded either: a
+//! placeholder with their size is, and is what a replay answers with.
+//!
+//! Requests are matched on their method, path, query and body. Identical requests get their
+//! responses in the order they were recorded, so a replay does not depend on timing.
+//! Token refreshes go to Intuit's OAuth server, not the API, and are neither recorded nor replayed.
+
+use crate::{
+    config::{self, Config},
+    local_server::{self, Request, Response},
+    query::is_date_time,
+};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    thread,
+};
+
+const CASSETTE_FILE: &str = "cassette.jsonl";
+
+/// replaces the company id in paths
+const COMPANY_ID: &str = "COMPANY_ID";
+
+/// fields whose text is redacted, along with everything nested in them
+const SENSITIVE_FIELDS: &[&str] = &[
+    "access_token",
+    "AcctNum",
+    "AlternatePhone",
+    "BillAddr",
+    "BillEmail",
+    "BillEmailBcc",
+    "BillEmailCc",
+    "BirthDate",
+    "CompanyAddr",
+    "CompanyName",
+    "CustomerCommunicationAddr",
+    "CustomerCommunicationEmailAddr",
+    "CustomerMemo",
+    "DisplayName",
+    "Email",
+    "FamilyName",
+    "Fax",
+    "FullyQualifiedName",
+    "GivenName",
+    "LegalAddr",
+    "LegalName",
+    "MiddleName",
+    "Mobile",
+    "Notes",
+    "OtherAddr",
+    "PrimaryAddr",
+    "PrimaryEmailAddr",
+    "PrimaryPhone",
+    "PrintOnCheckName",
+    "PrivateNote",
+    "refresh_token",
+    "ShipAddr",
+    "SSN",
+    "TaxIdentifier",
+    "WebAddr",
+];
+
+/// the directory being recorded to or replayed from, once started
+static STARTED: OnceCell<PathBuf> = OnceCell::new();
+
+/// A request and the response QuickBooks gave, as written to a cassette.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub query: Vec<(String, String)>,
+    /// parsed if it is JSON, text if it is text, and a placeholder otherwise
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub content_type: String,
+    /// parsed if it is JSON, text if it is text, and a placeholder otherwise
+    #[serde(default)]
+    pub body: Value,
+}
+
+/// Starts recording every request made from now on to `directory`, which is created if needed.
+///
+/// Requests still go to the `base_url` of the config (or QuickBooks); a cassette already in
+/// `directory` is overwritten. Fails if the config of the active profile cannot be read.
+pub fn record(directory: &Path) -> io::Result<()> {
+    if already_started(directory) {
+        return Ok(());
+    }
+
+    let upstream = Config::read(config::profile().as_deref())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
+        .upstream_url()
+        .to_string();
+
+    fs::create_dir_all(directory)?;
+    let mut cassette = File::create(directory.join(CASSETTE_FILE))?;
+
+    start(directory, move |request| {
+        let response = forward(&upstream, request);
+
+        let interaction = Interaction {
+            request: redact_request(request),
+            response: redact_response(&response),
+        };
+        let written = serde_json::to_string(&interaction)
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(cassette, "{line}"));
+        if let Err(err) = written {
+            log::error!(
+                "failed to record {} {}: {err}",
+                request.method,
+                request.path
+            );
+        }
+
+        response
+    })
+}
+
+/// Starts answering every request made from now on with the responses recorded in `directory`.
+///
+/// A request that is not in the cassette (or has been answered as many times as it was recorded)
+/// gets a `404` with a `Fault` body.
+pub fn replay(directory: &Path) -> io::Result<()> {
+    if already_started(directory) {
+        return Ok(());
+    }
+
+    let interactions = read_cassette(&directory.join(CASSETTE_FILE))?;
+    let mut replayed = vec![false; interactions.len()];
+
+    start(directory, move |request| {
+        answer(&interactions, &mut replayed, request)
+    })
+}
+
+/// the response recorded for `request` that has not been `replayed` yet, marking it as replayed
+fn answer(interactions: &[Interaction], replayed: &mut [bool], request: &Request) -> Response {
+    let recorded = redact_request(request);
+    let next = (0..interactions.len())
+        .find(|index| !replayed[*index] && interactions[*index].request == recorded);
+
+    match next {
+        Some(index) => {
+            replayed[index] = true;
+            to_response(&interactions[index].response)
+        }
+        None => {
+            log::error!(
+                "replay: {} {} is not in the cassette",
+                request.method,
+                request.path
+            );
+            Response::json(
+                404,
+                &json!({
+                    "Fault": {
+                        "Error": [{
+                            "Message": "not in the cassette",
+                            "Detail": format!("{} {} was not recorded (or not this many times)", request.method, request.path),
+                            "code": "0",
+                        }],
+                        "type": "SERVICE",
+                    }
+                }),
+            )
+        }
+    }
+}
+
+/// reads the interactions of a cassette, in the order they were recorded
+pub fn read_cassette(path: &Path) -> io::Result<Vec<Interaction>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// `true` if recording or replaying has started (in an interactive session, by an earlier command)
+fn already_started(directory: &Path) -> bool {
+    match STARTED.get() {
+        Some(started) => {
+            if started != directory {
+                log::warn!(
+                    "already recording or replaying {}, ignoring {}",
+                    started.display(),
+                    directory.display()
+                );
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// serves `handle` on a free local port, and sends every request there
+fn start<F>(directory: &Path, handle: F) -> io::Result<()>
+where
+    F: FnMut(&Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+
+    thread::spawn(move || local_server::serve(listener, "cassette", handle));
+
+    STARTED.get_or_init(|| directory.to_path_buf());
+    config::redirect(Some(base_url));
+    config::forget_authorization();
+
+    Ok(())
+}
+
+fn forward(upstream: &str, request: &Request) -> Response {
+    let mut outgoing = ureq::request(&request.method, &format!("{upstream}{}", request.path));
+    for (key, value) in &request.query {
+        outgoing = outgoing.query(key, value);
+    }
+    for header in ["accept", "authorization", "content-type"] {
+        if let Some(value) = request.header(header) {
+            outgoing = outgoing.set(header, value);
+        }
+    }
+
+    let result = if request.body.is_empty() {
+        outgoing.call()
+    } else {
+        outgoing.send_bytes(&request.body)
+    };
+
+    // a failed connection is recorded too, so that it is replayed
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(transport)) => {
+            return Response {
+                status: 502,
+                content_type: "text/plain".to_string(),
+                body: transport.to_string().into_bytes(),
+            }
+        }
+    };
+
+    let status = response.status();
+    let content_type = response.content_type().to_string();
+    let mut body = Vec::new();
+    if let Err(err) = response.into_reader().read_to_end(&mut body) {
+        log::warn!("failed to read the response to {}: {err}", request.path);
+    }
+
+    Response {
+        status,
+        content_type,
+        body,
+    }
+}
+
+fn redact_request(request: &Request) -> RecordedRequest {
+    // `/v3/company/<id>/...`
+    let path = match request.path.strip_prefix("/v3/company/") {
+        Some(rest) => match rest.split_once('/') {
+            Some((_, endpoint)) => format!("/v3/company/{COMPANY_ID}/{endpoint}"),
+            None => format!("/v3/company/{COMPANY_ID}"),
+        },
+        None => request.path.clone(),
+    };
+
+    let query = request
+        .query
+        .iter()
+        .map(|(key, value)| match key.as_str() {
+            "query" => (key.clone(), redact_literals(value)),
+            _ => (key.clone(), value.clone()),
+        })
+        .collect();
+
+    RecordedRequest {
+        method: request.method.clone(),
+        path,
+        query,
+        body: redacted_body(
+            &request.body,
+            request.header("content-type").unwrap_or_default(),
+        ),
+    }
+}
+
+fn redact_response(response: &Response) -> RecordedResponse {
+    RecordedResponse {
+        status: response.status,
+        content_type: response.content_type.clone(),
+        body: redacted_body(&response.body, &response.content_type),
+    }
+}
+
+fn to_response(recorded: &RecordedResponse) -> Response {
+    let body = match &recorded.body {
+        Value::Null => Vec::new(),
+        Value::String(text) if !recorded.content_type.contains("json") => text.clone().into_bytes(),
+        body => body.to_string().into_bytes(),
+    };
+
+    Response {
+        status: recorded.status,
+        content_type: recorded.content_type.clone(),
+        body,
+    }
+}
+
+/// `body` as it is recorded: JSON redacted, text as it is, and anything else replaced with a
+/// placeholder, as it could be neither redacted nor written as text
+fn redacted_body(body: &[u8], content_type: &str) -> Value {
+    if body.is_empty() {
+        return Value::Null;
+    }
+
+    if let Ok(mut value) = serde_json::from_slice(body) {
+        redact(&mut value);
+        return value;
+    }
+
+    let is_text = content_type.is_empty()
+        || content_type.starts_with("text/")
+        || content_type.contains("json")
+        || content_type.contains("xml");
+    match std::str::from_utf8(body) {
+        Ok(text) if is_text => Value::String(text.to_string()),
+        _ => Value::String(format!(
+            "<{} bytes of {content_type}, not recorded>",
+            body.len()
+        )),
+    }
+}
+
+/// redacts sensitive fields, and the names in references (`CustomerRef.name`)
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if SENSITIVE_FIELDS.contains(&key.as_str()) {
+                    redact_all(value);
+                } else if key.ends_with("Ref") {
+                    if let Some(name) = value.get_mut("name") {
+                        redact_all(name);
+                    }
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(elements) => elements.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn redact_all(value: &mut Value) {
+    match value {
+        Value::String(text) if !text.is_empty() => *text = pseudonym(text),
+        Value::Object(object) => object.values_mut().for_each(redact_all),
+        Value::Array(elements) => elements.iter_mut().for_each(redact_all),
+        _ => {}
+    }
+}
+
+/// the string literals of a query redacted, except for dates and numbers, which are rarely personal
+/// and often what a bug is about
+fn redact_literals(query: &str) -> String {
+    let mut redacted = String::new();
+    let mut chars = query.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            redacted.push(c);
+            continue;
+        }
+
+        let mut literal = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => literal.extend(chars.next()),
+                '\'' => break,
+                c => literal.push(c),
+            }
+        }
+
+        let keep = literal.is_empty() || is_date_time(&literal) || literal.parse::<f64>().is_ok();
+        if keep {
+            redacted.push('\'');
+            redacted.push_str(&literal.replace('\\', "\\\\").replace('\'', "\\'"));
+            redacted.push('\'');
+        } else {
+            redacted.push_str(&format!("'{}'", pseudonym(&literal)));
+        }
+    }
+
+    redacted
+}
+
+/// the same text for the same input, on every platform and Rust version (64-bit FNV-1a)
+fn pseudonym(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    format!("redacted-{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str, query: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: query
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            headers: vec![("authorization".to_string(), "Bearer secret".to_string())],
+            body: Vec::new(),
+        }
+    }
+
+    fn interaction(path: &str, body: Value) -> Interaction {
+        Interaction {
+            request: redact_request(&request(path, &[])),
+            response: redact_response(&Response::json(200, &body)),
+        }
+    }
+
+    #[test]
+    fn pseudonyms_are_stable_and_distinct() {
+        assert_eq!(pseudonym("Amy"), pseudonym("Amy"));
+        assert_ne!(pseudonym("Amy"), pseudonym("amy"));
+        assert_eq!(pseudonym(""), "redacted-cbf29ce484222325");
+        assert_eq!(pseudonym("a"), "redacted-af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn redacts_nested_fields_and_reference_names() {
+        let mut invoice = json!({
+            "Id": "130",
+            "TotalAmt": 150,
+            "BillAddr": { "Id": "5", "Line1": "4581 Finch St.", "Lat": "", "Extra": ["a", 1] },
+            "CustomerRef": { "value": "1", "name": "Amy's Bird Sanctuary" },
+            "Line": [{ "SalesItemLineDetail": { "ItemRef": { "value": "3", "name": "Rocks" } } }],
+            "CustomerMemo": { "value": "Thank you, Amy" },
+        });
+        redact(&mut invoice);
+
+        assert_eq!(
+            invoice,
+            json!({
+                "Id": "130",
+                "TotalAmt": 150,
+                "BillAddr": {
+                    "Id": pseudonym("5"),
+                    "Line1": pseudonym("4581 Finch St."),
+                    "Lat": "",
+                    "Extra": [pseudonym("a"), 1],
+                },
+                "CustomerRef": { "value": "1", "name": pseudonym("Amy's Bird Sanctuary") },
+                "Line": [{
+                    "SalesItemLineDetail": { "ItemRef": { "value": "3", "name": pseudonym("Rocks") } },
+                }],
+                "CustomerMemo": { "value": pseudonym("Thank you, Amy") },
+            })
+        );
+    }
+
+    #[test]
+    fn redacts_query_literals() {
+        let query = r"SELECT * FROM Customer WHERE DisplayName = 'Amy\'s' AND Balance > '0'";
+        assert_eq!(
+            redact_literals(query),
+            format!(
+                "SELECT * FROM Customer WHERE DisplayName = '{}' AND Balance > '0'",
+                pseudonym("Amy's")
+            )
+        );
+
+        // dates, numbers and empty strings are kept, escaped as they were
+        let query =
+            r"WHERE MetaData.CreateTime >= '2026-01-01' AND Balance = '-1.5' AND Notes = ''";
+        assert_eq!(redact_literals(query), query);
+        assert_eq!(
+            redact_literals(r"WHERE Notes = 'a \\ b'"),
+            format!("WHERE Notes = '{}'", pseudonym(r"a \ b"))
+        );
+    }
+
+    #[test]
+    fn redacts_requests() {
+        let recorded = redact_request(&request(
+            "/v3/company/9130/query",
+            &[
+                ("query", "SELECT * FROM Customer WHERE DisplayName = 'Amy'"),
+                ("minorversion", "75"),
+            ],
+        ));
+
+        assert_eq!(recorded.path, "/v3/company/COMPANY_ID/query");
+        assert_eq!(
+            recorded.query,
+            [
+                (
+                    "query".to_string(),
+                    format!(
+                        "SELECT * FROM Customer WHERE DisplayName = '{}'",
+                        pseudonym("Amy")
+                    )
+                ),
+                ("minorversion".to_string(), "75".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_identical_requests_in_recorded_order() {
+        let interactions = [
+            interaction("/v3/company/1/customer/1", json!({ "SyncToken": "0" })),
+            interaction("/v3/company/1/customer/2", json!({ "SyncToken": "5" })),
+            interaction("/v3/company/1/customer/1", json!({ "SyncToken": "1" })),
+        ];
+        let mut replayed = vec![false; interactions.len()];
+        let mut answer = |path: &str| {
+            let response = answer(&interactions, &mut replayed, &request(path, &[]));
+            let body: Value = serde_json::from_slice(&response.body).unwrap();
+            (response.status, body)
+        };
+
+        // another company id still matches, as it is replaced when recording
+        assert_eq!(
+            answer("/v3/company/2/customer/1"),
+            (200, json!({ "SyncToken": "0" }))
+        );
+        assert_eq!(
+            answer("/v3/company/1/customer/1"),
+            (200, json!({ "SyncToken": "1" }))
+        );
+
+        let (status, body) = answer("/v3/company/1/customer/1");
+        assert_eq!(status, 404);
+        assert_eq!(body["Fault"]["Error"][0]["Message"], "not in the cassette");
+    }
+
+    #[test]
+    fn records_and_replays_a_pdf_as_a_placeholder() {
+        let pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n(Amy's Bird Sanctuary) Tj\n%%EOF\n".to_vec();
+        let interaction = Interaction {
+            request: redact_request(&request("/v3/company/1/invoice/130/pdf", &[])),
+            response: redact_response(&Response {
+                status: 200,
+                content_type: "application/pdf".to_string(),
+                body: pdf.clone(),
+            }),
+        };
+
+        let line = serde_json::to_string(&interaction).unwrap();
+        assert!(!line.contains("Amy"));
+        let placeholder = format!("<{} bytes of application/pdf, not recorded>", pdf.len());
+        assert_eq!(interaction.response.body, placeholder.as_str());
+
+        // the cassette reads back, and answers with the placeholder
+        let interactions = [serde_json::from_str::<Interaction>(&line).unwrap()];
+        let response = answer(
+            &interactions,
+            &mut [false],
+            &request("/v3/company/1/invoice/130/pdf", &[]),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "application/pdf");
+        assert_eq!(response.body, placeholder.into_bytes());
+    }
+
+    #[test]
+    fn records_text_bodies_as_they_are() {
+        let response = Response {
+            status: 502,
+            content_type: "text/plain".to_string(),
+            body: b"connection refused".to_vec(),
+        };
+        let recorded = redact_response(&response);
+
+        assert_eq!(recorded.body, "connection refused");
+        assert_eq!(to_response(&recorded).body, response.body);
+    }
+}
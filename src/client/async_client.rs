@@ -0,0 +1,464 @@
+//! QuickBooks from async code, without blocking the runtime (behind the `async` feature).
+//!
+//! [`AsyncClient`] has the methods of [`QboClient`](super::QboClient) as `async` functions, and
+//! exports are [`Stream`]s:
+//!
+//! ```ignore
+//! use futures_util::TryStreamExt;
+//! use qbtools::{client::AsyncClient, entity::Entity, ExportOptions};
+//!
+//! let client = AsyncClient::connect(Some("accounting"), false).await?;
+//! let customers: Vec<_> = client.export(Entity::Customer, ExportOptions::new()).try_collect().await?;
+//! ```
+//!
+//! Requests are sent with `reqwest`, so they need a tokio runtime. An access token QuickBooks
+//! rejects is refreshed (and written back to the config file of the profile) and the request sent
+//! again, so a long-running service does not have to connect again every hour.
+
+use super::{
+    batch_chunks, batch_request, batch_results, entity_of, prepare_fetch, prepare_send,
+    take_response, Prepared,
+};
+use crate::{
+    api::{BatchItemResult, BatchOperation, Error, Method, PlannedRequest, Request, MINOR_VERSION},
+    config::{self, Config, ConfigError},
+    entity::Entity,
+    export::{self, ExportOptions, Pages},
+    model::{self, Model},
+};
+
+use futures_util::stream::{self, Stream, StreamExt};
+use quickbooks_ureq::AccessToken;
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::{path::Path, sync::Mutex};
+
+/// where Intuit hands out new access tokens
+const TOKEN_URL: &str = "https://oauth.platform.intuit.com/oauth2/v1/tokens/bearer";
+
+/// A client that sends [`Request`]s to QuickBooks (or the `base_url` of the config) without
+/// blocking (see the module docs).
+#[derive(Debug)]
+pub struct AsyncClient {
+    http: reqwest::Client,
+    base_url: String,
+    company_id: String,
+    token: Mutex<AccessToken>,
+    /// held while the access token is refreshed, so requests rejected at the same time refresh it
+    /// once
+    refreshing: tokio::sync::Mutex<()>,
+    token_url: String,
+    /// the config, and the file it is written back to when the access token is refreshed
+    config: Option<(Config, String)>,
+    dry_run: bool,
+    planned: Mutex<Vec<PlannedRequest>>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    token_type: String,
+}
+
+impl AsyncClient {
+    /// Reads the config of `profile` (see [`Config::read`]) and authorizes against QuickBooks,
+    /// refreshing the access token if it has expired, like
+    /// [`api::Client::connect`](crate::api::Client::connect).
+    pub async fn connect(profile: Option<&str>, dry_run: bool) -> Result<Self, ConfigError> {
+        let config = Config::read(profile)?;
+        let mut client = Self::from_config(config.clone(), dry_run)?;
+        client.config = Some((config, config::profile_base_path(profile)));
+
+        let request = Request::get(format!("companyinfo/{}", client.company_id));
+        let token = client.access_token();
+        match client
+            .respond_once(&request, "application/json", &token)
+            .await
+        {
+            Ok(_) => {}
+            Err(Error::Status(401, _)) => client.refresh(&token).await?,
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(client)
+    }
+
+    /// a client for `config`, which is not checked against QuickBooks; refreshed access tokens are
+    /// kept, but not written anywhere
+    pub fn from_config(config: Config, dry_run: bool) -> Result<Self, ConfigError> {
+        let token = config.token.clone().ok_or(ConfigError::NoToken)?;
+        let token_url = match &config.base_url {
+            Some(base_url) => format!("{base_url}/oauth2/v1/tokens/bearer"),
+            None => TOKEN_URL.to_string(),
+        };
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url: config.base_url(),
+            company_id: config.company_id,
+            token: Mutex::new(token),
+            refreshing: tokio::sync::Mutex::new(()),
+            token_url,
+            config: None,
+            dry_run,
+            planned: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// where `request` is sent, for logs and dry runs
+    pub fn url(&self, request: &Request) -> String {
+        format!(
+            "{}/v3/company/{}/{}",
+            self.base_url, self.company_id, request.endpoint
+        )
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// requests that were not sent because of dry-run mode
+    pub fn planned_requests(&self) -> Vec<PlannedRequest> {
+        self.planned
+            .lock()
+            .expect("planned requests lock to not be poisoned")
+            .clone()
+    }
+
+    /// sends `request` as it is, returning the response body; a rejected access token is
+    /// refreshed, and the request sent again
+    pub async fn call(&self, request: &Request) -> Result<Value, Error> {
+        Ok(self
+            .respond(request, "application/json")
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Validates and sends `request`, returning the response body.
+    ///
+    /// Returns `Ok(None)` without sending anything in dry-run mode; the request is kept and can be
+    /// retrieved with [`AsyncClient::planned_requests`].
+    pub async fn send(&self, request: Request) -> Result<Option<Value>, Error> {
+        let url = self.url(&request);
+
+        match prepare_send(request, url, self.dry_run)? {
+            Prepared::Send(request) => self.call(&request).await.map(Some),
+            Prepared::Planned(planned) => {
+                self.planned
+                    .lock()
+                    .expect("planned requests lock to not be poisoned")
+                    .push(planned);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Sends a `GET` request, even in dry-run mode, returning the response body.
+    pub async fn fetch(&self, request: Request) -> Result<Value, Error> {
+        prepare_fetch(&request, &self.url(&request))?;
+        self.call(&request).await
+    }
+
+    /// runs a query, returning the `QueryResponse`
+    pub async fn query(&self, query: &str) -> Result<Value, Error> {
+        let mut response = self.fetch(Request::query(query)).await?;
+        take_response(&mut response, "QueryResponse")
+    }
+
+    /// reads a single entity, eg. `client.read("customer", "58")`, returning the entity itself
+    pub async fn read(&self, entity: &str, id: &str) -> Result<Value, Error> {
+        Ok(entity_of(self.fetch(Request::read(entity, id)).await?))
+    }
+
+    /// reads a single entity as its model, eg. `client.read_as::<Customer>("58")`
+    pub async fn read_as<T: Model>(&self, id: &str) -> Result<T, Error> {
+        let entity = self.read(T::ENTITY.as_str(), id).await?;
+        Ok(model::from_value(entity)?)
+    }
+
+    /// the PDF of a transaction, eg. `client.pdf("invoice", "130")`, even in dry-run mode
+    pub async fn pdf(&self, entity: &str, id: &str) -> Result<Vec<u8>, Error> {
+        let request = Request::pdf(entity, id);
+        prepare_fetch(&request, &self.url(&request))?;
+
+        let response = self.respond(&request, "application/pdf").await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// creates an entity, returning it as QuickBooks saved it (or None in dry-run mode)
+    pub async fn create(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self
+            .send(Request::create(entity, body))
+            .await?
+            .map(entity_of))
+    }
+
+    /// updates an entity; `body` must contain `Id` and `SyncToken`, and `"sparse": true` to leave
+    /// out fields that do not change
+    pub async fn update(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self
+            .send(Request::update(entity, body))
+            .await?
+            .map(entity_of))
+    }
+
+    /// deletes an entity; `body` must contain `Id` and `SyncToken`
+    pub async fn delete(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self
+            .send(Request::delete(entity, body))
+            .await?
+            .map(entity_of))
+    }
+
+    /// voids a transaction; `body` must contain `Id` and `SyncToken`
+    pub async fn void(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self.send(Request::void(entity, body)).await?.map(entity_of))
+    }
+
+    /// Sends `operations` through `/batch`, [`MAX_BATCH_SIZE`](crate::api::MAX_BATCH_SIZE) at a
+    /// time, like [`QboClient::batch`](super::QboClient::batch).
+    pub async fn batch(
+        &self,
+        operations: &[BatchOperation],
+    ) -> Result<Vec<BatchItemResult>, Error> {
+        let mut results = Vec::with_capacity(operations.len());
+
+        for (offset, chunk) in batch_chunks(operations)? {
+            if let Some(response) = self.send(batch_request(offset, chunk)).await? {
+                results.extend(batch_results(&response, offset, chunk, operations));
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// fetches a report, eg. `client.report("ProfitAndLoss", &[("start_date", "2026-01-01")])`
+    pub async fn report(&self, name: &str, parameters: &[(&str, &str)]) -> Result<Value, Error> {
+        self.fetch(Request::report(name, parameters)).await
+    }
+
+    /// fetches every entity of `entities` changed since `changed_since`, returning the
+    /// `CDCResponse`
+    pub async fn cdc(&self, entities: &[Entity], changed_since: &str) -> Result<Value, Error> {
+        let mut response = self.fetch(Request::cdc(entities, changed_since)).await?;
+        take_response(&mut response, "CDCResponse")
+    }
+
+    /// Every entity of `entity` matching `options`, fetched a page at a time as the stream is
+    /// polled, like [`export::export`].
+    pub fn export(
+        &self,
+        entity: Entity,
+        options: ExportOptions,
+    ) -> impl Stream<Item = Result<Value, export::Error>> + '_ {
+        let pages = Pages::new(entity, &options);
+
+        stream::unfold(Some(pages), move |pages| async move {
+            let mut pages = match pages? {
+                Ok(pages) => pages,
+                Err(error) => return Some((Err(error.into()), None)),
+            };
+
+            if let Some(query) = pages.next_query() {
+                match self.query(&query).await {
+                    Ok(mut response) => pages.add(&mut response),
+                    Err(error) => return Some((Err(error.into()), None)),
+                }
+            }
+
+            let record = pages.pop()?;
+            Some((Ok(record), Some(Ok(pages))))
+        })
+    }
+
+    /// [`AsyncClient::export`], with every entity as its model
+    pub fn export_as<T: Model>(
+        &self,
+        options: ExportOptions,
+    ) -> impl Stream<Item = Result<T, export::Error>> + '_ {
+        self.export(T::ENTITY, options)
+            .map(|record| Ok(model::from_value(record?)?))
+    }
+
+    /// sends `request`, asking for a response of type `accept`; a rejected access token is
+    /// refreshed, and the request sent again
+    async fn respond(&self, request: &Request, accept: &str) -> Result<reqwest::Response, Error> {
+        let token = self.access_token();
+
+        match self.respond_once(request, accept, &token).await {
+            Err(Error::Status(401, body)) => {
+                if let Err(err) = self.refresh(&token).await {
+                    log::error!("{err}");
+                    return Err(Error::Status(401, body));
+                }
+                self.respond_once(request, accept, &self.access_token())
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// sends `request` with `token`, asking for a response of type `accept`
+    async fn respond_once(
+        &self,
+        request: &Request,
+        accept: &str,
+        token: &AccessToken,
+    ) -> Result<reqwest::Response, Error> {
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+        };
+
+        let mut http = self
+            .http
+            .request(method, self.url(request))
+            .bearer_auth(&token.access_token)
+            .header(reqwest::header::ACCEPT, accept)
+            .query(&[("minorversion", MINOR_VERSION)])
+            .query(&request.query);
+        if let Some(body) = &request.body {
+            http = http.json(body);
+        }
+
+        let response = http.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await?;
+        let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+        Err(Error::Status(status.as_u16(), body))
+    }
+
+    fn access_token(&self) -> AccessToken {
+        self.token
+            .lock()
+            .expect("access token lock to not be poisoned")
+            .clone()
+    }
+
+    /// Gets a new access token with the refresh token, writing it to the config file (if there is
+    /// one).
+    ///
+    /// Does nothing if `rejected` was already replaced, by a request rejected at the same time.
+    async fn refresh(&self, rejected: &AccessToken) -> Result<(), ConfigError> {
+        let _refreshing = self.refreshing.lock().await;
+
+        let token = self.access_token();
+        if token != *rejected {
+            return Ok(());
+        }
+        log::debug!("access token rejected, refreshing it");
+
+        if token.refresh_token.is_empty() {
+            return Err(ConfigError::NoToken);
+        }
+
+        let refresh = |error: reqwest::Error| ConfigError::Refresh(error.to_string());
+        let response: TokenResponse = self
+            .http
+            .post(&self.token_url)
+            .basic_auth(config::CLIENT_ID, Some(config::CLIENT_SECRET))
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &token.refresh_token),
+            ])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(refresh)?
+            .json()
+            .await
+            .map_err(refresh)?;
+
+        let refreshed = AccessToken {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            token_type: response.token_type,
+        };
+        *self
+            .token
+            .lock()
+            .expect("access token lock to not be poisoned") = refreshed.clone();
+
+        if let Some((config, base_path)) = &self.config {
+            let config = Config {
+                token: Some(refreshed),
+                ..config.clone()
+            };
+            let (file, out) = config.serialize_for(Path::new(base_path))?;
+            tokio::fs::write(file, out)
+                .await
+                .map_err(ConfigError::Write)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockServer, Options};
+
+    use futures_util::future;
+
+    fn start(token_lifetime: u32) -> (MockServer, AsyncClient) {
+        let server = MockServer::start(
+            "127.0.0.1:0",
+            Options {
+                token_lifetime: Some(token_lifetime),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+        let config: Config = serde_json::from_value(server.config()).unwrap();
+        let client = AsyncClient::from_config(config, false).unwrap();
+
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn refreshes_a_rejected_access_token_and_sends_the_request_again() {
+        let (server, client) = start(1);
+        let access_token = client.access_token().access_token;
+
+        // every request after the first is rejected once, and refreshes the access token
+        for id in ["1", "2", "3"] {
+            assert_eq!(client.read("customer", id).await.unwrap()["Id"], id);
+        }
+        assert!(!client.pdf("invoice", "101").await.unwrap().is_empty());
+
+        assert_ne!(client.access_token().access_token, access_token);
+        assert_eq!(
+            server.config()["access_token"],
+            client.access_token().access_token
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshes_the_access_token_once_for_requests_rejected_together() {
+        let (server, client) = start(3);
+        for id in ["1", "2", "3"] {
+            client.read("customer", id).await.unwrap();
+        }
+
+        // all three are sent with the expired token before any of them is answered
+        let reads = ["1", "2", "3"].map(|id| client.read("customer", id));
+        for (read, id) in future::join_all(reads)
+            .await
+            .into_iter()
+            .zip(["1", "2", "3"])
+        {
+            assert_eq!(read.unwrap()["Id"], id);
+        }
+
+        assert_eq!(server.config()["access_token"], "mock-access-token-1");
+    }
+}
@@ -0,0 +1,998 @@
+//! A QuickBooks company kept in memory, for tests and as the store behind the mock server and the
+//! local mirror.
+//!
+//! Requests are answered like QuickBooks answers them:
+//! - `query` (`WHERE`, `ORDERBY`, `STARTPOSITION`, `MAXRESULTS` and `COUNT(*)`), leaving out
+//!   inactive entities unless the query mentions `Active`
+//! - reading, creating, updating (sparse or not, checking the `SyncToken`), deleting and voiding
+//! - `batch`, `cdc` (by `MetaData.LastUpdatedTime`) and `reports/<name>` (empty, unless one was
+//!   given)
+//! - `<entity>/<id>/pdf`, with a one-page PDF standing in for the one QuickBooks renders
+//!
+//! Errors are [`Error::Status`] with the `Fault` bodies QuickBooks uses, eg. `610` for an entity
+//! that does not exist and `5010` for a stale `SyncToken`.
+
+use super::QboClient;
+use crate::{
+    api::{Error, Method, PlannedRequest, Request},
+    entity::Entity,
+    filter::Filter,
+};
+
+use regex_lite::Regex;
+use serde_json::{json, Map, Value};
+
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// `MAXRESULTS` when a query does not have one
+const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// entities by their name, eg. `Customer`
+pub type Entities = BTreeMap<String, Vec<Value>>;
+
+/// A [`QboClient`] that keeps entities in memory.
+///
+/// ```ignore
+/// use qbtools::client::{MemoryClient, QboClient};
+///
+/// let client = MemoryClient::default();
+/// client.create("customer", json!({ "DisplayName": "Amy's Bird Sanctuary" }))?;
+///
+/// // { "startPosition": 1, "maxResults": 1, "Customer": [{ "Id": "1", ... }] }
+/// let customers = client.query("SELECT * FROM Customer")?;
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryClient {
+    store: RefCell<Store>,
+    dry_run: bool,
+    planned: RefCell<Vec<PlannedRequest>>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Store {
+    entities: Entities,
+    reports: BTreeMap<String, Value>,
+}
+
+impl MemoryClient {
+    pub fn new(entities: Entities) -> Self {
+        let entities = entities
+            .into_iter()
+            .map(|(name, records)| (canonical_name(&name), records))
+            .collect();
+
+        Self {
+            store: RefCell::new(Store {
+                entities,
+                reports: BTreeMap::new(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Reads the `<Entity>.json` files in `directory` (each an array of entities, eg.
+    /// `Customer.json`) and the reports in `reports/<name>.json`.
+    pub fn from_directory(directory: &Path) -> io::Result<Self> {
+        let mut entities = Entities::new();
+        for (name, value) in json_files(directory)? {
+            let records = match value {
+                Value::Array(records) => records,
+                record => vec![record],
+            };
+            entities.insert(name, records);
+        }
+
+        let mut reports = BTreeMap::new();
+        let reports_directory = directory.join("reports");
+        if reports_directory.is_dir() {
+            reports.extend(json_files(&reports_directory)?);
+        }
+
+        Ok(Self::new(entities).with_reports(reports))
+    }
+
+    /// what `reports/<name>` answers with, by name
+    pub fn with_reports(self, reports: BTreeMap<String, Value>) -> Self {
+        self.store.borrow_mut().reports.extend(reports);
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// every stored entity of `entity`, eg. `client.entities("customer")`
+    pub fn entities(&self, entity: &str) -> Vec<Value> {
+        self.store
+            .borrow()
+            .entities
+            .get(&canonical_name(entity))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// stores `record` as it is, replacing the entity of `entity` with the same `Id` (if any), eg.
+    /// to keep a copy of one read from QuickBooks
+    pub fn put(&self, entity: &str, record: Value) {
+        let mut store = self.store.borrow_mut();
+        let records = store.entities.entry(canonical_name(entity)).or_default();
+
+        match records
+            .iter_mut()
+            .find(|stored| stored["Id"] == record["Id"])
+        {
+            Some(stored) => *stored = record,
+            None => records.push(record),
+        }
+    }
+
+    /// removes the entity of `entity` with `id`, returning whether there was one
+    pub fn remove(&self, entity: &str, id: &str) -> bool {
+        let mut store = self.store.borrow_mut();
+        let Some(records) = store.entities.get_mut(&canonical_name(entity)) else {
+            return false;
+        };
+
+        let count = records.len();
+        records.retain(|stored| stored["Id"] != id);
+        records.len() != count
+    }
+
+    /// writes every entity to `<Entity>.json` in `directory`
+    pub fn write_to_directory(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+
+        for (name, records) in &self.store.borrow().entities {
+            let json = serde_json::to_string_pretty(records)?;
+            fs::write(directory.join(format!("{name}.json")), json)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl QboClient for MemoryClient {
+    fn url(&self, request: &Request) -> String {
+        format!("memory:{}", request.endpoint)
+    }
+
+    fn call(&self, request: &Request) -> Result<Value, Error> {
+        self.store.borrow_mut().answer(request)
+    }
+
+    fn call_bytes(&self, request: &Request) -> Result<Vec<u8>, Error> {
+        let segments: Vec<&str> = request.endpoint.split('/').collect();
+
+        match (request.method, segments.as_slice()) {
+            (Method::Get, [entity, id, "pdf"]) => {
+                let record = read(&self.store.borrow(), entity, id)?;
+                Ok(placeholder_pdf(entity, &record))
+            }
+            _ => Err(fault(
+                404,
+                "SERVICE",
+                "610",
+                "Not Found",
+                &format!("{} {}", request.method.as_str(), request.endpoint),
+            )),
+        }
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn plan(&self, request: PlannedRequest) {
+        self.planned.borrow_mut().push(request);
+    }
+
+    fn planned_requests(&self) -> Vec<PlannedRequest> {
+        self.planned.borrow().clone()
+    }
+}
+
+impl Store {
+    fn answer(&mut self, request: &Request) -> Result<Value, Error> {
+        let segments: Vec<&str> = request.endpoint.split('/').collect();
+        let body = request.body.clone().unwrap_or_default();
+        let parameter = |name: &str| request.query.get(name).map(String::as_str);
+
+        let mut response = match (request.method, segments.as_slice()) {
+            (Method::Get, ["query"]) => match parameter("query") {
+                Some(query) => json!({ "QueryResponse": run_query(self, query)? }),
+                None => return Err(validation_fault("4000", "Error parsing query", "no query")),
+            },
+            (Method::Get, ["cdc"]) => {
+                let entities = parameter("entities").unwrap_or_default();
+                let changed_since = parameter("changedSince").unwrap_or_default();
+                json!({ "CDCResponse": [cdc(self, entities, changed_since)] })
+            }
+            (Method::Get, ["reports", name]) => report(self, name),
+            (Method::Get, [entity, id]) => {
+                json!({ canonical_name(entity): read(self, entity, id)? })
+            }
+            (Method::Post, ["batch"]) => batch(self, &body),
+            (Method::Post, [entity]) => {
+                let record = write(self, entity, parameter("operation"), body)?;
+                json!({ canonical_name(entity): record })
+            }
+            _ => {
+                return Err(fault(
+                    404,
+                    "SERVICE",
+                    "610",
+                    "Not Found",
+                    &format!("{} {}", request.method.as_str(), request.endpoint),
+                ))
+            }
+        };
+
+        response["time"] = now().into();
+        Ok(response)
+    }
+}
+
+/// an error response with a `Fault` with a single error, like QuickBooks sends
+pub(crate) fn fault(
+    status: u16,
+    fault_type: &str,
+    code: &str,
+    message: &str,
+    detail: &str,
+) -> Error {
+    Error::Status(
+        status,
+        json!({
+            "Fault": {
+                "Error": [{ "Message": message, "Detail": detail, "code": code }],
+                "type": fault_type,
+            },
+            "time": now(),
+        }),
+    )
+}
+
+fn validation_fault(code: &str, message: &str, detail: &str) -> Error {
+    fault(400, "ValidationFault", code, message, detail)
+}
+
+/// the `Fault` of an error, for a `BatchItemResponse`
+fn fault_of(error: Error) -> Value {
+    match error {
+        Error::Status(_, mut body) => body["Fault"].take(),
+        error => json!({ "Error": [{ "Message": format!("{error:?}") }] }),
+    }
+}
+
+/// `Customer` for `customer` and `CUSTOMER`; names that are not entities are kept as they are
+fn canonical_name(name: &str) -> String {
+    match name.parse::<Entity>() {
+        Ok(entity) => entity.as_str().to_string(),
+        Err(_) if name.eq_ignore_ascii_case("companyinfo") => "CompanyInfo".to_string(),
+        Err(_) => name.to_string(),
+    }
+}
+
+/// the JSON files in `directory`, by the name of the file without `.json`
+fn json_files(directory: &Path) -> io::Result<Vec<(String, Value)>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        let value = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        files.push((stem.to_string(), value));
+    }
+
+    Ok(files)
+}
+
+/// `{ "QueryResponse": [{ "Customer": [...] }, ...] }` with the entities changed since
+/// `changed_since`, compared as text (so it should have the offset the entities are stored with)
+fn cdc(store: &Store, entities: &str, changed_since: &str) -> Value {
+    let responses: Vec<Value> = entities
+        .split(',')
+        .map(canonical_name)
+        .map(|name| {
+            let changed: Vec<&Value> = store
+                .entities
+                .get(&name)
+                .map(|records| {
+                    records
+                        .iter()
+                        .filter(|record| {
+                            record["MetaData"]["LastUpdatedTime"]
+                                .as_str()
+                                .is_some_and(|updated| updated >= changed_since)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            json!({ name: changed })
+        })
+        .collect();
+
+    json!({ "QueryResponse": responses })
+}
+
+fn read(store: &Store, entity: &str, id: &str) -> Result<Value, Error> {
+    let name = canonical_name(entity);
+
+    store
+        .entities
+        .get(&name)
+        // there is only one `CompanyInfo`, read with the company id rather than its own
+        .and_then(|records| match name.as_str() {
+            "CompanyInfo" => records.first(),
+            _ => records.iter().find(|record| record["Id"] == id),
+        })
+        .cloned()
+        .ok_or_else(|| not_found(entity, id))
+}
+
+/// a PDF with a single page saying eg. `Invoice 1037`
+fn placeholder_pdf(entity: &str, record: &Value) -> Vec<u8> {
+    let number = record["DocNumber"].as_str().or(record["Id"].as_str());
+    let title: String = format!("{} {}", canonical_name(entity), number.unwrap_or_default())
+        .chars()
+        .filter(|c| (c.is_ascii_graphic() || *c == ' ') && !matches!(c, '(' | ')' | '\\'))
+        .collect();
+    let content = format!("BT /F1 24 Tf 72 720 Td ({title}) Tj ET");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+         /Resources << /Font << /F1 5 0 R >> >> >>"
+            .to_string(),
+        format!(
+            "<< /Length {} >>\nstream\n{content}\nendstream",
+            content.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{object}\nendobj\n", index + 1));
+    }
+
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    ));
+
+    pdf.into_bytes()
+}
+
+fn not_found(entity: &str, id: &str) -> Error {
+    validation_fault(
+        "610",
+        "Object Not Found",
+        &format!("Object Not Found : Something you're trying to use has been made inactive. Check the fields with accounts, customers, items, vendors or employees. ({} {id})", canonical_name(entity)),
+    )
+}
+
+fn write(
+    store: &mut Store,
+    entity: &str,
+    operation: Option<&str>,
+    mut body: Value,
+) -> Result<Value, Error> {
+    let name = canonical_name(entity);
+    if name.parse::<Entity>().is_err() {
+        return Err(validation_fault(
+            "2010",
+            "Request has invalid or unsupported property",
+            &format!("unsupported entity {entity:?}"),
+        ));
+    }
+    if !body.is_object() {
+        return Err(validation_fault(
+            "2020",
+            "Required param missing, need to supply the required value for the API",
+            "the body is not an object",
+        ));
+    }
+
+    let records = store.entities.entry(name.clone()).or_default();
+
+    if operation.is_none() || operation == Some("create") {
+        let id = records
+            .iter()
+            .filter_map(|record| record["Id"].as_str()?.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        body["Id"] = id.to_string().into();
+        body["SyncToken"] = "0".into();
+        body["MetaData"] = json!({ "CreateTime": now(), "LastUpdatedTime": now() });
+        if name
+            .parse::<Entity>()
+            .is_ok_and(|entity| !entity.is_transaction())
+            && body.get("Active").is_none()
+        {
+            body["Active"] = true.into();
+        }
+
+        records.push(body.clone());
+        return Ok(body);
+    }
+
+    let id = body["Id"].as_str().unwrap_or_default().to_string();
+    let Some(index) = records
+        .iter()
+        .position(|record| record["Id"] == id.as_str())
+    else {
+        return Err(not_found(entity, &id));
+    };
+
+    if body["SyncToken"] != records[index]["SyncToken"] {
+        return Err(validation_fault(
+            "5010",
+            "Stale Object Error",
+            &format!(
+                "Stale Object Error : You and someone else were working on this at the same time. They finished before you did, so your work was not saved. (SyncToken {} is not the latest)",
+                body["SyncToken"]
+            ),
+        ));
+    }
+
+    let sync_token = records[index]["SyncToken"]
+        .as_str()
+        .and_then(|token| token.parse::<u64>().ok())
+        .unwrap_or(0)
+        + 1;
+
+    match operation {
+        Some("update") => {
+            let mut record = if body["sparse"] == true {
+                records[index].clone()
+            } else {
+                json!({ "Id": id, "MetaData": records[index]["MetaData"] })
+            };
+            merge(&mut record, body);
+            record["SyncToken"] = sync_token.to_string().into();
+            record["MetaData"]["LastUpdatedTime"] = now().into();
+            record.as_object_mut().map(|object| object.remove("sparse"));
+
+            records[index] = record.clone();
+            Ok(record)
+        }
+        Some("delete") => {
+            records.remove(index);
+            Ok(json!({ "Id": id, "status": "Deleted", "domain": "QBO" }))
+        }
+        Some("void") => {
+            let record = &mut records[index];
+            record["SyncToken"] = sync_token.to_string().into();
+            record["TotalAmt"] = 0.into();
+            record["Balance"] = 0.into();
+            record["PrivateNote"] = "Voided".into();
+            record["MetaData"]["LastUpdatedTime"] = now().into();
+            Ok(record.clone())
+        }
+        Some(operation) => Err(validation_fault(
+            "2010",
+            "Request has invalid or unsupported property",
+            &format!("unsupported operation {operation:?}"),
+        )),
+        None => unreachable!("creating returned above"),
+    }
+}
+
+/// sets every field of `changes` in `record`, merging nested objects
+fn merge(record: &mut Value, changes: Value) {
+    match (record, changes) {
+        (Value::Object(record), Value::Object(changes)) => {
+            for (key, value) in changes {
+                match record.get_mut(&key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge(existing, value)
+                    }
+                    _ => {
+                        record.insert(key, value);
+                    }
+                }
+            }
+        }
+        (record, changes) => *record = changes,
+    }
+}
+
+fn batch(store: &mut Store, body: &Value) -> Value {
+    let items = body["BatchItemRequest"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+
+    let responses: Vec<Value> = items
+        .into_iter()
+        .map(|item| {
+            let b_id = item["bId"].clone();
+
+            let result = if let Some(query) = item["Query"].as_str() {
+                run_query(store, query).map(|response| ("QueryResponse".to_string(), response))
+            } else {
+                let operation = item["operation"].as_str().unwrap_or("create").to_string();
+                let entity = item
+                    .as_object()
+                    .and_then(|object| {
+                        object.keys().find(|key| {
+                            !matches!(key.as_str(), "bId" | "operation" | "optionsData")
+                        })
+                    })
+                    .cloned()
+                    .unwrap_or_default();
+                let body = item[&entity].clone();
+
+                write(store, &entity, Some(operation.as_str()), body)
+                    .map(|record| (canonical_name(&entity), record))
+            };
+
+            match result {
+                Ok((key, value)) => json!({ "bId": b_id, key: value }),
+                Err(err) => json!({ "bId": b_id, "Fault": fault_of(err) }),
+            }
+        })
+        .collect();
+
+    json!({ "BatchItemResponse": responses })
+}
+
+fn report(store: &Store, name: &str) -> Value {
+    store.reports.get(name).cloned().unwrap_or_else(|| {
+        json!({
+            "Header": { "ReportName": name, "Time": now() },
+            "Columns": { "Column": [] },
+            "Rows": {},
+        })
+    })
+}
+
+fn run_query(store: &Store, query: &str) -> Result<Value, Error> {
+    let pattern = Regex::new(
+        r"(?is)^\s*SELECT\s+(.+?)\s+FROM\s+(\w+)(?:\s+WHERE\s+(.+?))?(?:\s+ORDERBY\s+(.+?))?(?:\s+STARTPOSITION\s+(\d+))?(?:\s+MAXRESULTS\s+(\d+))?\s*;?\s*$",
+    )
+    .expect("query pattern to be valid");
+    let parse_error = |detail: String| validation_fault("4000", "Error parsing query", &detail);
+
+    let Some(captures) = pattern.captures(query) else {
+        return Err(parse_error(format!("QueryParserError: {query}")));
+    };
+    let columns = captures.get(1).map_or("*", |m| m.as_str()).trim();
+    let name = canonical_name(captures.get(2).map_or("", |m| m.as_str()));
+    let condition = captures.get(3).map(|m| m.as_str());
+
+    let mut records: Vec<Value> = store.entities.get(&name).cloned().unwrap_or_default();
+
+    // QuickBooks leaves out inactive entities unless the query is about `Active`
+    let mentions_active =
+        condition.is_some_and(|condition| condition.to_lowercase().contains("active"));
+    if !mentions_active {
+        records.retain(|record| record["Active"] != false);
+    }
+
+    if let Some(condition) = condition {
+        let filter: Filter = to_filter(condition)
+            .parse()
+            .map_err(|err| parse_error(format!("QueryParserError: {err}")))?;
+        records.retain(|record| filter.matches(record));
+    }
+
+    if columns.eq_ignore_ascii_case("count(*)") {
+        return Ok(json!({ "totalCount": records.len() }));
+    }
+
+    if let Some(order) = captures.get(4) {
+        let keys: Vec<(String, bool)> = order
+            .as_str()
+            .split(',')
+            .filter_map(|key| {
+                let mut words = key.split_whitespace();
+                let field = words.next()?.to_string();
+                let descending = words
+                    .next()
+                    .is_some_and(|word| word.eq_ignore_ascii_case("desc"));
+                Some((field, descending))
+            })
+            .collect();
+
+        records.sort_by(|a, b| {
+            keys.iter()
+                .map(|(field, descending)| {
+                    let ordering = compare(lookup(a, field), lookup(b, field));
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    let start = captures
+        .get(5)
+        .and_then(|m| m.as_str().parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let max_results = captures
+        .get(6)
+        .and_then(|m| m.as_str().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let page: Vec<Value> = records
+        .into_iter()
+        .skip(start - 1)
+        .take(max_results)
+        .map(|record| select(record, columns))
+        .collect();
+
+    if page.is_empty() {
+        return Ok(json!({}));
+    }
+
+    let mut response = Map::new();
+    response.insert("startPosition".to_string(), start.into());
+    response.insert("maxResults".to_string(), page.len().into());
+    response.insert(name, Value::Array(page));
+
+    Ok(Value::Object(response))
+}
+
+/// the QBO query condition as a `--filter` expression: `AND`, `IN (...)` and `LIKE '%x%'` become
+/// `and`, `in [...]` and a regex
+fn to_filter(condition: &str) -> String {
+    let mut filter = String::new();
+    let mut chars = condition.chars().peekable();
+    let mut in_list = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let mut literal = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => literal.extend(chars.next()),
+                        '\'' => break,
+                        c => literal.push(c),
+                    }
+                }
+
+                if filter.trim_end().ends_with("=~") {
+                    let regex = regex_lite::escape(&literal).replace('%', ".*");
+                    filter.push_str(&format!("/^{}$/i", regex.replace('/', "\\/")));
+                } else {
+                    filter.push('\'');
+                    filter.push_str(&literal.replace('\\', "\\\\").replace('\'', "\\'"));
+                    filter.push('\'');
+                }
+            }
+            '(' if filter.trim_end().ends_with(" in") => {
+                in_list = true;
+                filter.push('[');
+            }
+            ')' if in_list => {
+                in_list = false;
+                filter.push(']');
+            }
+            c if c.is_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || matches!(c, '.' | '_'))
+                {
+                    word.push(c);
+                }
+
+                filter.push_str(match word.to_lowercase().as_str() {
+                    "and" => "and",
+                    "or" => "or",
+                    "in" => "in",
+                    "like" => "=~",
+                    "true" => "true",
+                    "false" => "false",
+                    _ => &word,
+                });
+            }
+            c => filter.push(c),
+        }
+    }
+
+    filter
+}
+
+fn lookup<'a>(record: &'a Value, path: &str) -> &'a Value {
+    path.split('.')
+        .try_fold(record, |value, field| value.get(field))
+        .unwrap_or(&Value::Null)
+}
+
+/// numbers (and numeric strings) by value, anything else as text; missing values first
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.parse::<f64>().ok(),
+        _ => None,
+    };
+
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        _ => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ => {
+                let text = |value: &Value| {
+                    value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), str::to_lowercase)
+                };
+                text(a).cmp(&text(b))
+            }
+        },
+    }
+}
+
+/// `record` with only `columns` (a comma-separated list, or `*`), plus `Id` like QuickBooks
+fn select(record: Value, columns: &str) -> Value {
+    if columns == "*" {
+        return record;
+    }
+
+    let mut selected = Map::new();
+    for column in std::iter::once("Id").chain(columns.split(',').map(str::trim)) {
+        if let Some(value) = record.get(column) {
+            selected.insert(column.to_string(), value.clone());
+        }
+    }
+
+    Value::Object(selected)
+}
+
+/// the current UTC time as QuickBooks writes it, eg. `2026-02-10T22:00:00+00:00`
+pub(crate) fn now() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}+00:00",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::BatchOperation;
+
+    fn client() -> MemoryClient {
+        let customers = (1..=5)
+            .map(|id| {
+                json!({
+                    "Id": id.to_string(),
+                    "SyncToken": "0",
+                    "DisplayName": format!("Customer {id}"),
+                    "Notes": "kept",
+                    "Active": id != 5,
+                })
+            })
+            .collect();
+
+        MemoryClient::new(Entities::from([("customer".to_string(), customers)]))
+    }
+
+    fn ids(response: &Value) -> Vec<&str> {
+        response["Customer"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|customer| customer["Id"].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn query_pages_through_active_records() {
+        let client = client();
+
+        let page = client
+            .query("SELECT * FROM Customer STARTPOSITION 3 MAXRESULTS 2")
+            .unwrap();
+        assert_eq!(ids(&page), ["3", "4"]);
+        assert_eq!(page["startPosition"], 3);
+        assert_eq!(page["maxResults"], 2);
+
+        // the inactive fifth customer is left out, so the last page is short
+        let last = client
+            .query("SELECT * FROM Customer STARTPOSITION 3 MAXRESULTS 3")
+            .unwrap();
+        assert_eq!(ids(&last), ["3", "4"]);
+
+        let past_the_end = client
+            .query("SELECT * FROM Customer STARTPOSITION 5 MAXRESULTS 2")
+            .unwrap();
+        assert_eq!(past_the_end, json!({}));
+
+        let inactive = client
+            .query("SELECT * FROM Customer WHERE Active = false")
+            .unwrap();
+        assert_eq!(ids(&inactive), ["5"]);
+
+        let count = client.query("SELECT COUNT(*) FROM Customer").unwrap();
+        assert_eq!(count, json!({ "totalCount": 4 }));
+    }
+
+    #[test]
+    fn query_orders_and_selects_columns() {
+        let page = client()
+            .query("SELECT Id, DisplayName FROM Customer WHERE Id IN ('1', '2') ORDERBY Id DESC")
+            .unwrap();
+
+        assert_eq!(
+            page["Customer"],
+            json!([
+                { "Id": "2", "DisplayName": "Customer 2" },
+                { "Id": "1", "DisplayName": "Customer 1" },
+            ])
+        );
+    }
+
+    #[test]
+    fn sparse_update_bumps_the_sync_token() {
+        let client = client();
+
+        let updated = client
+            .update(
+                "customer",
+                json!({ "Id": "1", "SyncToken": "0", "sparse": true, "DisplayName": "Renamed" }),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated["SyncToken"], "1");
+        assert_eq!(updated["DisplayName"], "Renamed");
+        assert_eq!(updated["Notes"], "kept");
+        assert!(updated.get("sparse").is_none());
+
+        assert_eq!(client.read("customer", "1").unwrap(), updated);
+    }
+
+    #[test]
+    fn update_with_a_stale_sync_token_is_rejected() {
+        let client = client();
+        let update = json!({ "Id": "1", "SyncToken": "0", "sparse": true, "Notes": "first" });
+
+        client.update("customer", update.clone()).unwrap();
+        let err = client.update("customer", update).unwrap_err();
+
+        assert!(err.is_stale_object(), "{err:?}");
+        assert!(matches!(err, Error::Status(400, _)));
+        assert_eq!(client.read("customer", "1").unwrap()["SyncToken"], "1");
+    }
+
+    #[test]
+    fn full_update_leaves_out_fields_not_sent() {
+        let client = client();
+
+        let updated = client
+            .update(
+                "customer",
+                json!({ "Id": "2", "SyncToken": "0", "DisplayName": "Replaced" }),
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(updated["SyncToken"], "1");
+        assert!(updated.get("Notes").is_none());
+    }
+
+    #[test]
+    fn update_of_a_missing_entity_is_not_found() {
+        let err = client()
+            .update("customer", json!({ "Id": "99", "SyncToken": "0" }))
+            .unwrap_err();
+
+        assert_eq!(err.fault_codes(), ["610"]);
+    }
+
+    #[test]
+    fn batch_answers_every_operation_in_order() {
+        let client = client();
+        let operations = [
+            BatchOperation::Create {
+                entity: Entity::Customer,
+                body: json!({ "DisplayName": "Created" }),
+            },
+            BatchOperation::Update {
+                entity: Entity::Customer,
+                body: json!({ "Id": "2", "SyncToken": "7", "sparse": true, "Notes": "stale" }),
+            },
+            BatchOperation::Query {
+                query: "SELECT * FROM Customer WHERE Id = '6'".to_string(),
+            },
+            BatchOperation::Delete {
+                entity: Entity::Customer,
+                body: json!({ "Id": "3", "SyncToken": "0" }),
+            },
+        ];
+
+        let results = client.batch(&operations).unwrap();
+
+        let indexes: Vec<usize> = results.iter().map(|result| result.index).collect();
+        assert_eq!(indexes, [0, 1, 2, 3]);
+
+        assert_eq!(results[0].result.as_ref().unwrap()["Id"], "6");
+        assert_eq!(
+            results[1].fault.as_ref().unwrap()["Error"][0]["code"],
+            "5010"
+        );
+        assert_eq!(ids(results[2].result.as_ref().unwrap()), ["6"]);
+        assert_eq!(results[3].result.as_ref().unwrap()["status"], "Deleted");
+
+        let stored: Vec<Value> = client.entities("customer");
+        assert_eq!(stored.len(), 5);
+        assert!(stored.iter().all(|customer| customer["Id"] != "3"));
+    }
+
+    #[test]
+    fn dry_run_plans_writes_without_storing_them() {
+        let client = client().with_dry_run(true);
+
+        let updated = client
+            .update(
+                "customer",
+                json!({ "Id": "1", "SyncToken": "0", "sparse": true, "Notes": "planned" }),
+            )
+            .unwrap();
+
+        assert_eq!(updated, None);
+        assert_eq!(client.planned_requests().len(), 1);
+        assert_eq!(client.read("customer", "1").unwrap()["Notes"], "kept");
+    }
+}
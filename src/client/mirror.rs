@@ -0,0 +1,70 @@
+//! A QuickBooks company kept in a directory, for working without a connection.
+//!
+//! The directory has an `<Entity>.json` file for every entity (eg. `Customer.json`, an array of
+//! customers as exported with `-f json`), and optionally reports as `reports/<name>.json`. Changes
+//! are written back to the files as soon as they are made.
+
+use super::{MemoryClient, QboClient};
+use crate::api::{Error, Method, PlannedRequest, Request};
+
+use serde_json::Value;
+
+use std::{io, path::PathBuf};
+
+/// A [`QboClient`] that reads and writes the entities in a directory (see the module docs).
+#[derive(Debug)]
+pub struct MirrorClient {
+    directory: PathBuf,
+    memory: MemoryClient,
+}
+
+impl MirrorClient {
+    pub fn open(directory: impl Into<PathBuf>, dry_run: bool) -> io::Result<Self> {
+        let directory = directory.into();
+        let memory = MemoryClient::from_directory(&directory)?.with_dry_run(dry_run);
+
+        Ok(Self { directory, memory })
+    }
+
+    /// stores `record` as it is, eg. one read from QuickBooks (see [`MemoryClient::put`])
+    pub fn put(&self, entity: &str, record: Value) -> io::Result<()> {
+        self.memory.put(entity, record);
+        self.memory.write_to_directory(&self.directory)
+    }
+
+    /// removes the entity of `entity` with `id` (see [`MemoryClient::remove`])
+    pub fn remove(&self, entity: &str, id: &str) -> io::Result<()> {
+        if self.memory.remove(entity, id) {
+            self.memory.write_to_directory(&self.directory)?;
+        }
+        Ok(())
+    }
+}
+
+impl QboClient for MirrorClient {
+    fn url(&self, request: &Request) -> String {
+        format!("{}/{}", self.directory.display(), request.endpoint)
+    }
+
+    fn call(&self, request: &Request) -> Result<Value, Error> {
+        let response = self.memory.call(request)?;
+
+        if request.method == Method::Post {
+            self.memory.write_to_directory(&self.directory)?;
+        }
+
+        Ok(response)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.memory.is_dry_run()
+    }
+
+    fn plan(&self, request: PlannedRequest) {
+        self.memory.plan(request);
+    }
+
+    fn planned_requests(&self) -> Vec<PlannedRequest> {
+        self.memory.planned_requests()
+    }
+}
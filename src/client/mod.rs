@@ -0,0 +1,389 @@
+//! The QuickBooks API as a trait, so commands can run against something other than QuickBooks.
+//!
+//! Implementations only have to send an [`api::Request`](crate::api::Request) somewhere and return
+//! the response body (or an [`Error::Status`] with a `Fault`, like QuickBooks); validation,
+//! dry-run mode and everything from queries to `/batch` are built on top of that. There are three:
+//! - [`api::Client`](crate::api::Client), which sends requests to QuickBooks
+//! - [`MemoryClient`], which keeps entities in memory, for tests
+//! - [`MirrorClient`], which keeps them in a directory of `<Entity>.json` files, for working offline
+//!
+//! Commands use [`connect`], which returns a mirror if `--mirror` was given. With the `async`
+//! feature, [`AsyncClient`] does the same as `api::Client` without blocking.
+
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod memory;
+pub mod mirror;
+
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+pub use memory::MemoryClient;
+pub use mirror::MirrorClient;
+
+use crate::{
+    api::{
+        take_entity, BatchItemResult, BatchOperation, Error, Method, PlannedRequest, Request,
+        ValidationError, MAX_BATCH_SIZE,
+    },
+    commands::OutputError,
+    entity::Entity,
+    model::{self, DecodeError, Model},
+};
+
+use serde_json::{json, Value};
+
+use std::{path::PathBuf, sync::Mutex};
+
+/// the directory given with `--mirror`, if any
+static MIRROR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Something that answers QuickBooks API requests.
+pub trait QboClient {
+    /// where `request` is sent, for logs and dry runs
+    fn url(&self, request: &Request) -> String;
+
+    /// sends `request` as it is, returning the response body
+    fn call(&self, request: &Request) -> Result<Value, Error>;
+
+    fn is_dry_run(&self) -> bool;
+
+    /// keeps a request that was not sent because of dry-run mode
+    fn plan(&self, request: PlannedRequest);
+
+    /// requests that were not sent because of dry-run mode
+    fn planned_requests(&self) -> Vec<PlannedRequest>;
+
+    /// Sends `request` as it is, returning the response body as it is, eg. a PDF.
+    ///
+    /// Answers with the `404` QuickBooks sends for an endpoint it does not have, unless
+    /// implemented.
+    fn call_bytes(&self, request: &Request) -> Result<Vec<u8>, Error> {
+        let detail = format!("{} {}", request.method.as_str(), self.url(request));
+        Err(memory::fault(404, "SERVICE", "610", "Not Found", &detail))
+    }
+
+    /// Validates and sends `request`, returning the response body.
+    ///
+    /// Returns `Ok(None)` without sending anything in dry-run mode; the request is kept and can be
+    /// retrieved with [`QboClient::planned_requests`].
+    fn send(&self, request: Request) -> Result<Option<Value>, Error> {
+        let url = self.url(&request);
+
+        match prepare_send(request, url, self.is_dry_run())? {
+            Prepared::Send(request) => self.call(&request).map(Some),
+            Prepared::Planned(planned) => {
+                self.plan(planned);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Sends a `GET` request, even in dry-run mode, returning the response body.
+    fn fetch(&self, request: Request) -> Result<Value, Error> {
+        prepare_fetch(&request, &self.url(&request))?;
+        self.call(&request)
+    }
+
+    /// runs a query, returning the `QueryResponse`
+    fn query(&self, query: &str) -> Result<Value, Error> {
+        let mut response = self.fetch(Request::query(query))?;
+        take_response(&mut response, "QueryResponse")
+    }
+
+    /// reads a single entity, eg. `client.read("customer", "58")`, returning the entity itself
+    /// rather than the `{ "Customer": { ... }, "time": ... }` wrapper
+    fn read(&self, entity: &str, id: &str) -> Result<Value, Error> {
+        Ok(entity_of(self.fetch(Request::read(entity, id))?))
+    }
+
+    /// the PDF of a transaction, eg. `client.pdf("invoice", "130")`, even in dry-run mode
+    fn pdf(&self, entity: &str, id: &str) -> Result<Vec<u8>, Error> {
+        let request = Request::pdf(entity, id);
+        prepare_fetch(&request, &self.url(&request))?;
+        self.call_bytes(&request)
+    }
+
+    /// creates an entity, returning it as QuickBooks saved it (or None in dry-run mode)
+    fn create(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self.send(Request::create(entity, body))?.map(entity_of))
+    }
+
+    /// updates an entity; `body` must contain `Id` and `SyncToken`, and `"sparse": true` to leave
+    /// out fields that do not change
+    fn update(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self.send(Request::update(entity, body))?.map(entity_of))
+    }
+
+    /// deletes an entity; `body` must contain `Id` and `SyncToken`
+    fn delete(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self.send(Request::delete(entity, body))?.map(entity_of))
+    }
+
+    /// voids a transaction; `body` must contain `Id` and `SyncToken`
+    fn void(&self, entity: &str, body: Value) -> Result<Option<Value>, Error> {
+        Ok(self.send(Request::void(entity, body))?.map(entity_of))
+    }
+
+    /// Sends `operations` through `/batch`, [`MAX_BATCH_SIZE`] at a time.
+    ///
+    /// Every operation is validated before anything is sent. Results are returned in the order of
+    /// `operations`; in dry-run mode nothing is sent and no results are returned.
+    fn batch(&self, operations: &[BatchOperation]) -> Result<Vec<BatchItemResult>, Error> {
+        let mut results = Vec::with_capacity(operations.len());
+
+        for (offset, chunk) in batch_chunks(operations)? {
+            if let Some(response) = self.send(batch_request(offset, chunk))? {
+                results.extend(batch_results(&response, offset, chunk, operations));
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// fetches a report, eg. `client.report("ProfitAndLoss", &[("start_date", "2026-01-01")])`
+    fn report(&self, name: &str, parameters: &[(&str, &str)]) -> Result<Value, Error> {
+        self.fetch(Request::report(name, parameters))
+    }
+
+    /// Fetches every entity of `entities` changed (or deleted) since `changed_since`, a date or
+    /// date time, returning the `CDCResponse`.
+    ///
+    /// QuickBooks only keeps changes for 30 days.
+    fn cdc(&self, entities: &[Entity], changed_since: &str) -> Result<Value, Error> {
+        let mut response = self.fetch(Request::cdc(entities, changed_since))?;
+        take_response(&mut response, "CDCResponse")
+    }
+}
+
+// What `QboClient` and `AsyncClient` do before sending a request and with its response, so that
+// the two only differ in how they send it.
+
+/// a validated request, to be sent or (in dry-run mode) kept as planned
+pub(crate) enum Prepared {
+    Send(Request),
+    Planned(PlannedRequest),
+}
+
+/// validates `request`, which is sent to `url` unless `dry_run`
+pub(crate) fn prepare_send(
+    request: Request,
+    url: String,
+    dry_run: bool,
+) -> Result<Prepared, Error> {
+    request.validate()?;
+
+    if dry_run {
+        log::info!("dry run: not sending {} {url}", request.method.as_str());
+        return Ok(Prepared::Planned(PlannedRequest { url, request }));
+    }
+
+    log::debug!("{} {url}", request.method.as_str());
+    Ok(Prepared::Send(request))
+}
+
+/// validates `request`, which is sent to `url` even in dry-run mode, so it has to be a `GET`
+pub(crate) fn prepare_fetch(request: &Request, url: &str) -> Result<(), Error> {
+    if request.method != Method::Get {
+        return Err(ValidationError::NotAGetRequest.into());
+    }
+    request.validate()?;
+
+    log::debug!("{} {url}", request.method.as_str());
+    Ok(())
+}
+
+/// validates every operation of a batch, then splits them into chunks of [`MAX_BATCH_SIZE`], each
+/// with the offset of its first operation
+pub(crate) fn batch_chunks(
+    operations: &[BatchOperation],
+) -> Result<impl Iterator<Item = (usize, &[BatchOperation])>, Error> {
+    for operation in operations {
+        operation.validate()?;
+    }
+
+    Ok(operations
+        .chunks(MAX_BATCH_SIZE)
+        .enumerate()
+        .map(|(index, chunk)| (index * MAX_BATCH_SIZE, chunk)))
+}
+
+/// the `key` of a response, eg. its `QueryResponse`, failing if QuickBooks sent something else
+pub(crate) fn take_response(response: &mut Value, key: &'static str) -> Result<Value, Error> {
+    response
+        .get_mut(key)
+        .map(Value::take)
+        .ok_or_else(|| DecodeError::missing_field(key).into())
+}
+
+/// the entity of a read/create/update/delete response
+pub(crate) fn entity_of(mut response: Value) -> Value {
+    take_entity(&mut response)
+}
+
+/// the `/batch` request for `chunk`, the operations of a batch from `offset` on
+pub(crate) fn batch_request(offset: usize, chunk: &[BatchOperation]) -> Request {
+    let items: Vec<_> = chunk
+        .iter()
+        .enumerate()
+        .map(|(index, operation)| operation.to_item(offset + index))
+        .collect();
+
+    Request::post("batch", json!({ "BatchItemRequest": items }))
+}
+
+/// The results of a `/batch` response to `chunk`, the operations of a batch from `offset` on, in
+/// the order they were sent.
+///
+/// An operation QuickBooks did not respond to gets a fault, since it was not carried out.
+pub(crate) fn batch_results(
+    response: &Value,
+    offset: usize,
+    chunk: &[BatchOperation],
+    operations: &[BatchOperation],
+) -> Vec<BatchItemResult> {
+    let responses = match response.get("BatchItemResponse") {
+        Some(Value::Array(responses)) => responses.clone(),
+        _ => {
+            log::error!("batch response has no BatchItemResponse: {response}");
+            Vec::new()
+        }
+    };
+
+    let mut results: Vec<_> = responses
+        .into_iter()
+        .filter_map(|item| BatchItemResult::from_item(item, operations))
+        .collect();
+
+    let missing: Vec<_> = (offset..offset + chunk.len())
+        .filter(|index| !results.iter().any(|result| result.index == *index))
+        .collect();
+    if !missing.is_empty() {
+        log::error!(
+            "sent {} operations in a batch but received no response to {}",
+            chunk.len(),
+            missing.len()
+        );
+    }
+
+    results.extend(missing.into_iter().map(|index| BatchItemResult {
+        index,
+        operation: operations[index].clone(),
+        result: None,
+        fault: Some(json!({
+            "Error": [{ "Message": "QuickBooks did not respond to this operation" }],
+            "type": "MissingResponse",
+        })),
+    }));
+    results.sort_by_key(|result| result.index);
+
+    results
+}
+
+/// reads a single entity as its model, eg. `read_as::<Customer, _>(&client, "58")`
+pub fn read_as<T, C>(client: &C, id: &str) -> Result<T, Error>
+where
+    T: Model,
+    C: QboClient + ?Sized,
+{
+    let entity = client.read(T::ENTITY.as_str(), id)?;
+    Ok(model::from_value(entity)?)
+}
+
+/// makes [`connect`] use the `<Entity>.json` files in `directory` instead of QuickBooks
+pub fn set_mirror(directory: Option<PathBuf>) {
+    *MIRROR.lock().expect("mirror lock to not be poisoned") = directory;
+}
+
+pub fn mirror() -> Option<PathBuf> {
+    MIRROR
+        .lock()
+        .expect("mirror lock to not be poisoned")
+        .clone()
+}
+
+/// A client for commands: the mirror given with `--mirror`, or QuickBooks, authorized with the
+/// config (see `config::get_authorized_config`).
+pub(crate) fn connect(quiet: bool, dry_run: bool) -> Result<Box<dyn QboClient>, OutputError> {
+    if let Some(directory) = mirror() {
+        return Ok(Box::new(MirrorClient::open(directory, dry_run)?));
+    }
+
+    Ok(Box::new(crate::api::Client::authorized(quiet, dry_run)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries(count: usize) -> Vec<BatchOperation> {
+        (0..count)
+            .map(|index| BatchOperation::Query {
+                query: format!("select * from Customer where Id = '{index}'"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_results_are_in_the_order_operations_were_sent() {
+        let operations = queries(3);
+        let response = json!({ "BatchItemResponse": [
+            { "bId": "1", "QueryResponse": { "Customer": [{ "Id": "1" }] } },
+            { "bId": "0", "QueryResponse": {} },
+            { "bId": "2", "Fault": { "Error": [{ "Message": "nope" }] } },
+        ]});
+
+        let results = batch_results(&response, 0, &operations, &operations);
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.index)
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            results[1].result,
+            Some(json!({ "Customer": [{ "Id": "1" }] }))
+        );
+        assert!(results[2].result.is_none());
+        assert!(results[2].fault.is_some());
+    }
+
+    #[test]
+    fn missing_batch_responses_are_faults() {
+        let operations = queries(4);
+        // the second chunk, of which QuickBooks only answered one operation
+        let response = json!({ "BatchItemResponse": [
+            { "bId": "3", "QueryResponse": {} },
+        ]});
+
+        let results = batch_results(&response, 2, &operations[2..], &operations);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].index, 2);
+        assert_eq!(results[0].operation, operations[2]);
+        assert!(results[0].fault.is_some());
+        assert!(results[1].fault.is_none());
+
+        let results = batch_results(&json!({}), 0, &operations, &operations);
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|result| result.fault.is_some()));
+    }
+
+    #[test]
+    fn takes_the_part_of_a_response_asked_for() {
+        let mut response = json!({ "QueryResponse": { "Customer": [] }, "time": "now" });
+        assert_eq!(
+            take_response(&mut response, "QueryResponse").unwrap(),
+            json!({ "Customer": [] })
+        );
+
+        for mut response in [json!({ "time": "now" }), json!("busy"), Value::Null] {
+            let error = take_response(&mut response, "QueryResponse").unwrap_err();
+            assert!(
+                matches!(&error, Error::Decode(error) if error.entity.is_none()),
+                "{error:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::{to_output, CommandError};
+use crate::{api::BatchOperation, args::OutputArgs, client::QboClient, fs};
+
+use serde::Deserialize;
+
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchArgs {
+    pub output: OutputArgs,
+    pub quiet: bool,
+    pub dry_run: bool,
+
+    pub input: PathBuf,
+}
+
+/// either a list of operations, or (since TOML has no top-level arrays) `[[operations]]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchInput {
+    List(Vec<BatchOperation>),
+    Table { operations: Vec<BatchOperation> },
+}
+
+/// runs the operations in `args.input` through `/batch` and prints one result per operation
+pub fn run<C>(client: &C, args: &BatchArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let operations = match fs::read_data_file(&args.input).map_err(CommandError::ReadInput)? {
+        BatchInput::List(operations) | BatchInput::Table { operations } => operations,
+    };
+
+    if operations.is_empty() {
+        return Err(CommandError::InvalidInput(format!(
+            "{} contains no operations",
+            args.input.display()
+        )));
+    }
+
+    let results = client.batch(&operations)?;
+
+    // the planned requests are printed instead
+    if client.is_dry_run() {
+        return Ok(());
+    }
+
+    to_output(&results, &args.output)?;
+
+    let faults = results
+        .iter()
+        .filter(|result| result.fault.is_some())
+        .count();
+    if faults > 0 {
+        return Err(CommandError::Faults(faults));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Parquet files and Arrow IPC streams, with a schema inferred from the records.
+
+use crate::{
+    export::MAX_PAGE_SIZE,
+    query::{is_date, is_date_time},
+};
+
+use arrow_schema::{ArrowError, DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use serde_json::{json, Value};
+
+use std::{collections::HashSet, io, sync::Arc};
+
+/// A Parquet file written a page of records at a time, a row group per page, so the records of an
+/// export never have to be held in memory at once.
+///
+/// The schema is the one inferred from the first page with records, so a field that only turns up
+/// in a later page fails the export rather than being left out; `--fields` gives every record the
+/// same columns.
+#[cfg(feature = "parquet")]
+pub(crate) enum ParquetWriter<W: io::Write + Send> {
+    /// no records yet, so no schema either
+    Empty(W),
+    Started(Box<StartedParquet<W>>),
+}
+
+#[cfg(feature = "parquet")]
+pub(crate) struct StartedParquet<W: io::Write + Send> {
+    writer: parquet::arrow::ArrowWriter<W>,
+    decoder: arrow_json::reader::Decoder,
+    columns: HashSet<String>,
+}
+
+#[cfg(feature = "parquet")]
+impl<W: io::Write + Send> ParquetWriter<W> {
+    /// writes the records of `page` (a record per element if it is an array) as a row group
+    pub(crate) fn write_page(self, page: &Value) -> Result<Self, super::OutputError> {
+        let records = as_records(page);
+        if records.is_empty() {
+            return Ok(self);
+        }
+
+        let mut started = match self {
+            Self::Empty(writer) => {
+                use parquet::{
+                    arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties,
+                };
+
+                let schema = infer_schema(&records)?;
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(MAX_PAGE_SIZE as usize)
+                    .build();
+
+                Box::new(StartedParquet {
+                    columns: schema
+                        .fields()
+                        .iter()
+                        .map(|field| field.name().clone())
+                        .collect(),
+                    decoder: decoder(schema.clone())?,
+                    writer: ArrowWriter::try_new(writer, schema, Some(properties))?,
+                })
+            }
+            Self::Started(started) => started,
+        };
+
+        let keys = records
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|record| record.keys());
+        for key in keys {
+            if !started.columns.contains(key) {
+                log::error!(
+                    "{key} is not in the first page of records, so it has no Parquet column; \
+                     use --fields to choose the columns"
+                );
+                return Err(super::OutputError::NewColumn(key.clone()));
+            }
+        }
+
+        for chunk in records.chunks(MAX_PAGE_SIZE as usize) {
+            started.decoder.serialize(chunk)?;
+            if let Some(batch) = started.decoder.flush()? {
+                started.writer.write(&batch)?;
+            }
+        }
+        // ends the row group
+        started.writer.flush()?;
+
+        Ok(Self::Started(started))
+    }
+
+    /// writes the footer (with an empty schema if there were no records at all) and returns the
+    /// underlying writer
+    pub(crate) fn finish(self) -> Result<W, super::OutputError> {
+        let writer = match self {
+            Self::Empty(writer) => {
+                parquet::arrow::ArrowWriter::try_new(writer, Arc::new(Schema::empty()), None)?
+            }
+            Self::Started(started) => started.writer,
+        };
+
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// Writes `value` as an Arrow IPC file, a record batch per page of results (1,000 records).
+#[cfg(feature = "arrow")]
+pub(crate) fn write_arrow<W>(writer: &mut W, value: &Value) -> Result<(), super::OutputError>
+where
+    W: io::Write,
+{
+    let (schema, batches) = record_batches(value)?;
+
+    let mut arrow_writer = arrow_ipc::writer::FileWriter::try_new(writer, &schema)?;
+    for batch in &batches {
+        arrow_writer.write(batch)?;
+    }
+    arrow_writer.finish()?;
+
+    Ok(())
+}
+
+/// `value` (a record per element if it is an array) as batches of at most 1,000 rows.
+#[cfg(feature = "arrow")]
+fn record_batches(value: &Value) -> Result<(SchemaRef, Vec<arrow_array::RecordBatch>), ArrowError> {
+    let records = as_records(value);
+    let schema = infer_schema(&records)?;
+    let mut decoder = decoder(schema.clone())?;
+
+    let mut batches = Vec::new();
+    for page in records.chunks(MAX_PAGE_SIZE as usize) {
+        decoder.serialize(page)?;
+        batches.extend(decoder.flush()?);
+    }
+
+    Ok((schema, batches))
+}
+
+/// The schema of `records`.
+///
+/// Nested objects become structs and arrays become lists. Strings that are all dates or date times
+/// (like `TxnDate` and `MetaData.CreateTime`) become `Date32` and UTC timestamps.
+fn infer_schema(records: &[Value]) -> Result<SchemaRef, ArrowError> {
+    let schema = arrow_json::reader::infer_json_schema_from_iterator(records.iter().map(Ok))?;
+    let record_refs: Vec<&Value> = records.iter().collect();
+    Ok(Arc::new(Schema::new(refine_fields(
+        schema.fields(),
+        &record_refs,
+    ))))
+}
+
+/// a decoder of records into batches of `schema`, which takes a number for a string field (and the
+/// other way around) as later pages may not have the types of the first
+fn decoder(schema: SchemaRef) -> Result<arrow_json::reader::Decoder, ArrowError> {
+    arrow_json::ReaderBuilder::new(schema)
+        .with_batch_size(MAX_PAGE_SIZE as usize)
+        .with_coerce_primitive(true)
+        .build_decoder()
+}
+
+fn as_records(value: &Value) -> Vec<Value> {
+    match value {
+        Value::Array(records) => records.iter().map(as_record).collect(),
+        record => vec![as_record(record)],
+    }
+}
+
+/// records have to be objects, so anything else is put in a `value` column
+fn as_record(value: &Value) -> Value {
+    match value {
+        Value::Object(_) => value.clone(),
+        value => json!({ "value": value }),
+    }
+}
+
+/// `fields` with strings that are all dates or date times in `records` typed as such
+fn refine_fields(fields: &Fields, records: &[&Value]) -> Vec<Field> {
+    fields
+        .iter()
+        .map(|field| {
+            let values: Vec<&Value> = records
+                .iter()
+                .map(|record| &record[field.name()])
+                .filter(|value| !value.is_null())
+                .collect();
+
+            let data_type = match field.data_type() {
+                // a field that is always null in the first page may not be in later ones
+                DataType::Null => DataType::Utf8,
+                DataType::Utf8 if values.is_empty() => DataType::Utf8,
+                DataType::Utf8
+                    if values
+                        .iter()
+                        .all(|value| value.as_str().is_some_and(is_date)) =>
+                {
+                    DataType::Date32
+                }
+                DataType::Utf8
+                    if values
+                        .iter()
+                        .all(|value| value.as_str().is_some_and(is_date_time)) =>
+                {
+                    DataType::Timestamp(TimeUnit::Second, Some("+00:00".into()))
+                }
+                DataType::Struct(children) => {
+                    DataType::Struct(refine_fields(children, &values).into())
+                }
+                data_type => data_type.clone(),
+            };
+
+            Field::new(field.name(), data_type, true)
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn write_pages(name: &str, pages: &[Value]) -> SerializedFileReader<std::fs::File> {
+        let path =
+            std::env::temp_dir().join(format!("qbtools-{}-{name}.parquet", std::process::id()));
+
+        let mut parquet = ParquetWriter::Empty(std::fs::File::create(&path).unwrap());
+        for page in pages {
+            parquet = parquet.write_page(page).unwrap();
+        }
+        parquet.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        reader
+    }
+
+    #[test]
+    fn writes_a_row_group_per_page() {
+        let reader = write_pages(
+            "pages",
+            &[
+                json!([{ "Id": "1", "Balance": 1.5 }, { "Id": "2", "Balance": 0 }]),
+                json!([]),
+                json!([{ "Id": "3", "Balance": 2 }]),
+            ],
+        );
+
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(metadata.file_metadata().num_rows(), 3);
+
+        let columns: Vec<&str> = metadata
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        assert_eq!(columns, ["Id", "Balance"]);
+    }
+
+    #[test]
+    fn fails_on_a_field_that_is_not_in_the_first_page() {
+        let parquet = ParquetWriter::Empty(Vec::new())
+            .write_page(&json!([{ "Id": "1" }]))
+            .unwrap();
+
+        let result = parquet.write_page(&json!([{ "Id": "2", "Notes": "new" }]));
+        assert!(
+            matches!(result, Err(crate::commands::OutputError::NewColumn(key)) if key == "Notes")
+        );
+    }
+
+    #[test]
+    fn writes_an_empty_file_without_records() {
+        let reader = write_pages("empty", &[json!([])]);
+
+        assert_eq!(reader.metadata().num_row_groups(), 0);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+    }
+}
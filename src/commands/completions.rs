@@ -0,0 +1,97 @@
+//! Shell completions, computed by qbtools itself while the command line is being typed.
+//!
+//! The script printed by `qbtools completions <shell>` runs qbtools with `COMPLETE=<shell>` set,
+//! which [`complete`] answers before anything else runs. Entity names are always completed; IDs
+//! only when `--mirror` was typed, from the customers and items in the mirror.
+
+use super::CommandError;
+use crate::{args::Args, client::MemoryClient, entity::Entity};
+
+use clap::CommandFactory;
+use clap_complete::{env::Shells, ArgValueCandidates, CompleteEnv, CompletionCandidate};
+
+use std::{
+    env,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// the variable the script sets to ask for completions
+const VAR: &str = "COMPLETE";
+
+/// writes the script that registers the completions with `shell`
+pub fn print(shell: &str) -> Result<(), CommandError> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .expect("clap to only accept shells with a completer");
+    let bin = env::current_exe()?;
+
+    let mut stdout = io::stdout().lock();
+    completer.write_registration(
+        VAR,
+        "qbtools",
+        "qbtools",
+        &bin.to_string_lossy(),
+        &mut stdout,
+    )?;
+    Ok(stdout.flush()?)
+}
+
+/// answers the script and exits if it asked for completions, otherwise does nothing
+pub fn complete() {
+    CompleteEnv::with_factory(Args::command).var(VAR).complete();
+}
+
+/// every entity name, eg. for `qbtools update <entity>`
+pub(crate) fn entities() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        Entity::ALL
+            .iter()
+            .map(|entity| CompletionCandidate::new(entity.as_str().to_lowercase()))
+            .collect()
+    })
+}
+
+/// The IDs of the customers and items in the mirror, described with their names.
+///
+/// Nothing is completed without `--mirror`, since asking QuickBooks would be too slow.
+pub(crate) fn ids() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        let Some(client) =
+            mirror().and_then(|directory| MemoryClient::from_directory(&directory).ok())
+        else {
+            return Vec::new();
+        };
+
+        [("Customer", "DisplayName"), ("Item", "Name")]
+            .into_iter()
+            .flat_map(|(entity, name)| {
+                client
+                    .entities(entity)
+                    .into_iter()
+                    .filter_map(move |record| {
+                        let id = record["Id"].as_str()?;
+                        let help = format!("{} {}", entity.to_lowercase(), record[name].as_str()?);
+                        Some(CompletionCandidate::new(id).help(Some(help.into())))
+                    })
+            })
+            .collect()
+    })
+}
+
+/// the `--mirror` of the command line being completed, which is not parsed yet
+fn mirror() -> Option<PathBuf> {
+    let mut args = env::args_os();
+    while let Some(arg) = args.next() {
+        let arg = arg.to_str()?;
+        if arg == "--mirror" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(directory) = arg.strip_prefix("--mirror=") {
+            return Some(directory.into());
+        }
+    }
+
+    None
+}
@@ -1,53 +1,131 @@
-use super::{get_desired_array, to_output_path, we_do_a_bit_of_logging, CommandError};
-use crate::args::{ExportCustomerArgs, OutputFormat};
-
-use quickbooks_ureq::config::QueryConfig;
-
-use std::path::PathBuf;
+use super::{model_pages, write_pages, CommandError};
+use crate::{
+    args::{ExportCustomerArgs, ExportItemArgs, ExportQueryArgs, OutputArgs},
+    client::QboClient,
+    entity::Entity,
+    export::ExportOptions,
+    model::{Customer, Item},
+    query::{field, is_date, is_date_time, Condition, Literal, Operator, Order},
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ExportArgs {
-    pub format: Option<OutputFormat>,
-    pub output_path: Option<PathBuf>,
-    pub pretty: bool,
+    pub output: OutputArgs,
     pub quiet: bool,
     pub verbose: bool,
 }
 
-pub fn customers(
+pub fn customers<C>(
+    client: &C,
     args: &ExportArgs,
     customer_args: &ExportCustomerArgs,
-) -> Result<(), CommandError> {
-    let options = QueryConfig {
-        r#where: customer_args.r#where.as_deref(),
-        ..Default::default()
-    };
-
-    let customers = get_desired_array(args.quiet, "Customer", &options)?;
-    let customers = customers
-        .as_array()
-        .expect("to be guaranteed by the QB API");
-
-    we_do_a_bit_of_logging(customers, "customers");
-
-    Ok(to_output_path(
-        customers,
-        &args.output_path,
-        &args.format.clone().unwrap_or_default(),
-        args.pretty,
-    )?)
+) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let mut query = export_options(Entity::Customer, &customer_args.query)?.query(Entity::Customer);
+    if let Some(r#where) = &customer_args.r#where {
+        query = query.filter(Condition::raw(r#where));
+    }
+    let query = query.build()?;
+
+    write_pages(
+        Entity::Customer.as_str(),
+        model_pages::<Customer, _>(client, &query),
+        &args.output,
+    )
+}
+
+pub fn items<C>(
+    client: &C,
+    args: &ExportArgs,
+    item_args: &ExportItemArgs,
+) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let query = export_options(Entity::Item, &item_args.query)?
+        .query(Entity::Item)
+        .build()?;
+
+    write_pages(
+        Entity::Item.as_str(),
+        model_pages::<Item, _>(client, &query),
+        &args.output,
+    )
 }
 
-pub fn items(args: &ExportArgs) -> Result<(), CommandError> {
-    let items = get_desired_array(args.quiet, "Item", &Default::default())?;
-    let items = items.as_array().expect("to be guaranteed by the QB API");
+/// the [`ExportOptions`] of the conditions and ordering given on the command line
+fn export_options(entity: Entity, args: &ExportQueryArgs) -> Result<ExportOptions, CommandError> {
+    let conditions = &args.conditions;
+    let count = conditions.fields.len();
+    if conditions.operators.len() != count || conditions.values.len() != count {
+        return Err(CommandError::InvalidInput(
+            "--where-field, --where-op and --where-value must be given the same number of times"
+                .to_string(),
+        ));
+    }
+
+    // `true` and `false` are the only values QBO does not want quoted
+    fn literal(value: &str) -> Literal {
+        match value {
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            value => value.into(),
+        }
+    }
+
+    let mut options = ExportOptions::new();
+    for ((name, operator), value) in conditions
+        .fields
+        .iter()
+        .zip(&conditions.operators)
+        .zip(&conditions.values)
+    {
+        options = options.filter(match operator {
+            Operator::In => field(name).is_in(value.split(',').map(str::trim).map(literal)),
+            operator => field(name).compare(*operator, literal(value)),
+        });
+    }
+
+    for date_time in args.updated_since.iter().chain(&args.created_between) {
+        if !is_date_time(date_time) {
+            return Err(CommandError::InvalidInput(format!(
+                "{date_time:?} is not a YYYY-MM-DD date or YYYY-MM-DDTHH:MM:SS date and time"
+            )));
+        }
+    }
+
+    if let Some(updated_since) = &args.updated_since {
+        options = options.updated_since(updated_since);
+    }
+    if let [from, to] = args.created_between.as_slice() {
+        // a date on its own would mean its midnight, leaving out the rest of the day
+        let to = if is_date(to) {
+            format!("{to}T23:59:59")
+        } else {
+            to.clone()
+        };
+
+        options = options
+            .filter(field("MetaData.CreateTime").ge(from.as_str()))
+            .filter(field("MetaData.CreateTime").le(to));
+    }
+
+    if args.include_inactive && !entity.fields().contains(&"Active") {
+        log::warn!("{entity} cannot be inactive; ignoring --include-inactive");
+    }
+    options = options.include_inactive(args.include_inactive);
 
-    we_do_a_bit_of_logging(items, "items");
+    // `asc`/`desc` applies to the field before it, eg. `--order-by Balance desc --order-by Id`
+    let mut order_by = args.order_by.iter().peekable();
+    while let Some(field) = order_by.next() {
+        let order = match order_by.next_if(|word| word.parse::<Order>().is_ok()) {
+            Some(order) => order.parse().expect("to have been parsed above"),
+            None => Order::Asc,
+        };
+        options = options.order_by_with(field, order);
+    }
 
-    Ok(to_output_path(
-        items,
-        &args.output_path,
-        &args.format.clone().unwrap_or_default(),
-        args.pretty,
-    )?)
+    Ok(options)
 }
@@ -1,24 +1,48 @@
-use crate::config::get_authorized_qb;
-
-use super::{CommandError, OutputFormat};
-
-use std::path::PathBuf;
+use super::{named_records, to_output, CommandError};
+use crate::{
+    args::OutputArgs,
+    client::{read_as, QboClient},
+    model::{Customer, Item, Model},
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct GetArgs {
-    pub format: Option<OutputFormat>,
-    pub output_path: Option<PathBuf>,
+    pub output: OutputArgs,
     pub quiet: bool,
 
     pub id: String,
 }
 
-pub fn customer(args: &GetArgs) -> Result<(), CommandError> {
-    let qb = get_authorized_qb(args.quiet)?;
-
-    let customer = qb.read("customer", "27")?;
+pub fn customer<C>(client: &C, args: &GetArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    get::<Customer, C>(client, args)
+}
 
-    println!("{:#?}", customer);
+pub fn item<C>(client: &C, args: &GetArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    get::<Item, C>(client, args)
+}
 
-    Ok(())
+/// reads the entity with the id given on the command line and writes it out as an object, or as
+/// the only record of its section for formats with sections (like an export of it)
+fn get<T, C>(client: &C, args: &GetArgs) -> Result<(), CommandError>
+where
+    T: Model,
+    C: QboClient + ?Sized,
+{
+    let record: T = read_as(client, &args.id)?;
+
+    let format = args.output.format();
+    if format.has_sections() {
+        Ok(to_output(
+            &named_records(T::ENTITY.as_str(), vec![record], &format),
+            &args.output,
+        )?)
+    } else {
+        Ok(to_output(&record, &args.output)?)
+    }
 }
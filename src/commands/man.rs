@@ -0,0 +1,18 @@
+use super::CommandError;
+use crate::args::Args;
+
+use clap::CommandFactory;
+
+use std::{fs, path::Path};
+
+/// writes `qbtools.1`, and eg. `qbtools-export-customers.1` for every subcommand, to `out_dir`
+pub fn generate(out_dir: &Path, quiet: bool) -> Result<(), CommandError> {
+    fs::create_dir_all(out_dir)?;
+    clap_mangen::generate_to(Args::command(), out_dir)?;
+
+    if !quiet {
+        eprintln!("wrote man pages to {}", out_dir.display());
+    }
+
+    Ok(())
+}
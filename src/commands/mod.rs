@@ -1,21 +1,114 @@
+#[cfg(feature = "cmd-batch")]
+pub mod batch;
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+mod columnar;
+#[cfg(feature = "cmd-completions")]
+pub mod completions;
 #[cfg(feature = "cmd-export")]
 pub mod export;
 #[cfg(feature = "cmd-get")]
 pub mod get;
+#[cfg(feature = "cmd-man")]
+pub mod man;
+#[cfg(feature = "cmd-pdf")]
+pub mod pdf;
+#[cfg(feature = "cmd-query")]
+pub mod query;
+#[cfg(feature = "cmd-remove")]
+pub mod remove;
+#[cfg(feature = "cmd-report")]
+pub mod report;
+mod table;
+pub(crate) mod tabular;
+#[cfg(feature = "template")]
+mod template;
+#[cfg(feature = "toml")]
+mod toml_document;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "cmd-update")]
+pub mod update;
+#[cfg(feature = "cmd-webhook")]
+pub mod webhook;
+#[cfg(feature = "xlsx")]
+pub(crate) mod xlsx;
+
+use crate::{
+    api,
+    args::{OutputArgs, OutputFormat, SelectArgs},
+    client::QboClient,
+    config::ConfigError,
+    entity::Entity,
+    export::MAX_PAGE_SIZE,
+    fs,
+    model::{self, DecodeError, Model},
+    query::BuiltQuery,
+};
+
+use serde_json::{Map, Value};
+
+use std::{
+    fs::File,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    slice,
+};
+
+// the fields are only read through `Debug`, by the error messages of `run`
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum CommandError {
+    QbUreq(quickbooks_ureq::Error),
+    Api(api::Error),
+    OutputError(OutputError),
+    Query(crate::query::Error),
+    /// QBO returned an entity that does not have the shape of its model
+    Decode(DecodeError),
+    /// the config could not be read, or QuickBooks did not accept its access token
+    Config(ConfigError),
+
+    /// failed to read a file given on the command line
+    ReadInput(fs::Error),
+    /// input given on the command line cannot be used
+    InvalidInput(String),
+    /// the user did not confirm the operation
+    Aborted,
+    /// this many operations of a batch request failed
+    Faults(usize),
+}
 
-use quickbooks_ureq::config::QueryConfig;
+impl From<api::Error> for CommandError {
+    fn from(error: api::Error) -> Self {
+        Self::Api(error)
+    }
+}
 
-use crate::{args::OutputFormat, config::get_authorized_qb};
+impl From<DecodeError> for CommandError {
+    fn from(error: DecodeError) -> Self {
+        Self::Decode(error)
+    }
+}
 
-use std::{fs::File, io, path::PathBuf, slice};
+impl From<ConfigError> for CommandError {
+    fn from(error: ConfigError) -> Self {
+        Self::Config(error)
+    }
+}
 
-#[derive(Debug)]
-pub enum CommandError {
-    /// failed to serialize initial response into a `serde_json::Value`
-    FailedToSerializeResponse(std::io::Error),
+impl From<crate::export::Error> for CommandError {
+    fn from(error: crate::export::Error) -> Self {
+        match error {
+            crate::export::Error::Query(error) => Self::Query(error),
+            crate::export::Error::Api(error) => Self::Api(error),
+            crate::export::Error::Decode(error) => Self::Decode(error),
+        }
+    }
+}
 
-    QbUreq(quickbooks_ureq::Error),
-    OutputError(OutputError),
+impl From<crate::query::Error> for CommandError {
+    fn from(error: crate::query::Error) -> Self {
+        Self::Query(error)
+    }
 }
 
 impl From<quickbooks_ureq::Error> for CommandError {
@@ -36,6 +129,8 @@ impl From<std::io::Error> for CommandError {
     }
 }
 
+// as with `CommandError`, the fields are only read through `Debug`
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum SerializationError {
     Json(serde_json::Error),
@@ -43,6 +138,14 @@ pub enum SerializationError {
     Toml(toml::ser::Error),
     #[cfg(feature = "yaml")]
     Yaml(serde_yaml::Error),
+    #[cfg(feature = "xlsx")]
+    Xlsx(rust_xlsxwriter::XlsxError),
+    #[cfg(any(feature = "arrow", feature = "parquet"))]
+    Arrow(arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "template")]
+    Template(handlebars::RenderError),
 }
 
 impl From<serde_json::Error> for SerializationError {
@@ -65,10 +168,47 @@ impl From<serde_yaml::Error> for SerializationError {
     }
 }
 
+#[cfg(feature = "xlsx")]
+impl From<rust_xlsxwriter::XlsxError> for SerializationError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        Self::Xlsx(error)
+    }
+}
+
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+impl From<arrow_schema::ArrowError> for SerializationError {
+    fn from(error: arrow_schema::ArrowError) -> Self {
+        Self::Arrow(error)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for SerializationError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        Self::Parquet(error)
+    }
+}
+
+#[cfg(feature = "template")]
+impl From<handlebars::RenderError> for SerializationError {
+    fn from(error: handlebars::RenderError) -> Self {
+        Self::Template(error)
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum OutputError {
     Io(io::Error),
     Serialization(SerializationError),
+    /// a binary format was to be written to a terminal, which should be given `--output-path`
+    /// or redirected instead
+    BinaryToTerminal(OutputFormat),
+    /// the template format was asked for without `--template`
+    NoTemplate,
+    /// a field turned up in a later page than the one the Parquet schema was inferred from
+    #[cfg(feature = "parquet")]
+    NewColumn(String),
 }
 
 impl From<io::Error> for OutputError {
@@ -103,58 +243,408 @@ impl From<serde_yaml::Error> for OutputError {
     }
 }
 
-fn process_response_for_desired_array(
-    response: quickbooks_ureq::Response,
-    key: &str,
-) -> Result<serde_json::Value, CommandError> {
-    let mut response: serde_json::Value = response
-        .into_json()
-        .map_err(CommandError::FailedToSerializeResponse)?;
+#[cfg(feature = "xlsx")]
+impl From<rust_xlsxwriter::XlsxError> for OutputError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        Self::Serialization(SerializationError::Xlsx(error))
+    }
+}
 
-    let values = response
-        .get_mut("QueryResponse")
-        .expect("to be guaranteed by the QB API");
+#[cfg(any(feature = "arrow", feature = "parquet"))]
+impl From<arrow_schema::ArrowError> for OutputError {
+    fn from(error: arrow_schema::ArrowError) -> Self {
+        Self::Serialization(SerializationError::Arrow(error))
+    }
+}
 
-    let values = values.get_mut(key).expect("programming error");
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for OutputError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        Self::Serialization(SerializationError::Parquet(error))
+    }
+}
 
-    Ok(values.take())
+#[cfg(feature = "template")]
+impl From<handlebars::RenderError> for OutputError {
+    fn from(error: handlebars::RenderError) -> Self {
+        Self::Serialization(SerializationError::Template(error))
+    }
 }
 
-/// returns QB API response as array of items
-fn get_desired_array(
-    quiet: bool,
-    key: &str,
-    options: &QueryConfig,
-) -> Result<serde_json::Value, CommandError> {
-    let qb = get_authorized_qb(quiet)?;
-    let response = qb.query(key, options)?;
-    process_response_for_desired_array(response, key)
+/// keys of a `QueryResponse` that are not the records themselves
+const QUERY_RESPONSE_METADATA: [&str; 3] = ["startPosition", "maxResults", "totalCount"];
+
+/// the records of a `QueryResponse`, which are under the name of the queried entity (the only key
+/// that is not metadata), along with that name; QBO leaves the key out if nothing matched
+pub(crate) fn take_records(query_response: &mut Value) -> (Option<String>, Vec<Value>) {
+    let Some(object) = query_response.as_object_mut() else {
+        return (None, Vec::new());
+    };
+
+    let key = object
+        .keys()
+        .find(|key| !QUERY_RESPONSE_METADATA.contains(&key.as_str()))
+        .cloned();
+
+    match key.as_ref().and_then(|key| object.remove(key)) {
+        Some(Value::Array(records)) => (key, records),
+        Some(record) => (key, vec![record]),
+        None => (key, Vec::new()),
+    }
+}
+
+/// a page of the records of a query, see [`take_records`]
+pub(crate) type Page = (Option<String>, Vec<Value>);
+
+/// runs `query` (which must not have its own `STARTPOSITION` or `MAXRESULTS`) `page_size`
+/// records at a time as the pages are asked for, until a page comes back short
+pub(crate) fn pages<'a, C>(
+    client: &'a C,
+    query: String,
+    page_size: u32,
+) -> impl Iterator<Item = Result<Page, CommandError>> + 'a
+where
+    C: QboClient + ?Sized,
+{
+    let mut start_position = Some(1);
+
+    std::iter::from_fn(move || {
+        let position = start_position.take()?;
+        if position > 1 {
+            log::debug!("fetched {} records, fetching the next page", position - 1);
+        }
+
+        let page_query = format!("{query} STARTPOSITION {position} MAXRESULTS {page_size}");
+        let page = match client.query(&page_query) {
+            Ok(mut response) => take_records(&mut response),
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        if page.1.len() == page_size as usize {
+            start_position = Some(position + page_size);
+        }
+        Some(Ok(page))
+    })
+}
+
+/// the pages of records `query` matches (see [`pages`]), each record decoded as its model and
+/// encoded again; fields the model does not type are kept in its `other`, so this validates the
+/// shape of the records, failing with a `DecodeError` on one that does not fit
+#[cfg(feature = "cmd-export")]
+fn model_pages<'a, T, C>(
+    client: &'a C,
+    query: &BuiltQuery,
+) -> Box<dyn Iterator<Item = Result<Page, CommandError>> + 'a>
+where
+    T: Model + std::fmt::Debug + 'a,
+    C: QboClient + ?Sized,
+{
+    let as_models = |page: Result<Page, CommandError>| {
+        let (key, records) = page?;
+        let models: Vec<T> = model::from_values(records)?;
+        log::trace!("{models:?}");
+
+        let records = models
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<_, _>>()
+            .map_err(OutputError::from)?;
+        Ok((key, records))
+    };
+
+    if query.is_paged() {
+        let page = client
+            .query(&query.sql())
+            .map(|mut response| take_records(&mut response));
+        return Box::new(std::iter::once(page.map_err(CommandError::from)).map(as_models));
+    }
+
+    Box::new(pages(client, query.sql(), MAX_PAGE_SIZE).map(as_models))
+}
+
+/// returns every record `query` matches, fetching 1,000 at a time unless the query has its own
+/// `STARTPOSITION` or `MAXRESULTS`
+#[cfg(feature = "tui")]
+fn get_desired_array<C>(client: &C, query: &BuiltQuery) -> Result<Vec<Value>, CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    if query.is_paged() {
+        return Ok(take_records(&mut client.query(&query.sql())?).1);
+    }
+
+    let mut records = Vec::new();
+    for page in pages(client, query.sql(), MAX_PAGE_SIZE) {
+        records.extend(page?.1);
+    }
+    Ok(records)
+}
+
+/// writes the records of `pages` as [`to_output`] would, under the name of the entity they are
+/// (or `name` if nothing matched); a Parquet file gets a row group per page as the pages are
+/// fetched, instead of being written once every record has been
+pub(crate) fn write_pages<I>(name: &str, pages: I, output: &OutputArgs) -> Result<(), CommandError>
+where
+    I: IntoIterator<Item = Result<Page, CommandError>>,
+{
+    let format = output.format();
+
+    #[cfg(feature = "parquet")]
+    if format == OutputFormat::Parquet {
+        let mut writer = io::BufWriter::new(open_output(&output.output_path, &format)?);
+        let mut parquet = columnar::ParquetWriter::Empty(&mut writer);
+        let mut count = 0;
+
+        for page in pages {
+            let (_, records) = page?;
+            count += records.len();
+            parquet = parquet.write_page(&apply_select(Value::Array(records), &output.select))?;
+        }
+        io::Write::flush(parquet.finish()?)?;
+
+        log::info!("number of {name}s: {count}");
+        return Ok(());
+    }
+
+    let mut key = None;
+    let mut records = Vec::new();
+    for page in pages {
+        let (page_key, page) = page?;
+        key = key.or(page_key);
+        records.extend(page);
+    }
+
+    let name = key.as_deref().unwrap_or(name);
+    log::info!("number of {name}s: {}", records.len());
+
+    Ok(to_output(&named_records(name, records, &format), output)?)
 }
 
-/// serializes `value` to `output_path` (or stdout if None) as `format`
+/// serializes `value` as the output options of a command ask, see [`to_output_path`]
+pub fn to_output<T>(value: &T, output: &OutputArgs) -> Result<(), OutputError>
+where
+    T: ?Sized + serde::ser::Serialize,
+{
+    to_output_path(
+        value,
+        &output.output_path,
+        &output.format(),
+        output.pretty,
+        &output.select,
+    )
+}
+
+/// serializes `value` to `output_path` (or stdout if None) as `format`, keeping only the records
+/// and fields picked by `select`
 pub fn to_output_path<T>(
     value: &T,
     output_path: &Option<PathBuf>,
     format: &OutputFormat,
     pretty: bool,
+    select: &SelectArgs,
 ) -> Result<(), OutputError>
 where
     T: ?Sized + serde::ser::Serialize,
 {
-    if let Some(output_path) = output_path {
-        // TODO: change map_err to inspect_err once stable
-        let file = File::create(output_path).map_err(|err| {
-            log::error!("{err}");
-            err
-        })?;
-
-        to_writer(file, value, format, pretty)?;
+    // `--template` is enough to ask for the template format
+    #[cfg(feature = "template")]
+    let format = if select.template.is_some() {
+        &OutputFormat::Template
     } else {
-        let writer = std::io::stdout();
-        to_writer(writer, value, format, pretty)?;
+        format
+    };
+
+    if !select.is_empty() || format.has_sections() {
+        let value = match serde_json::to_value(value)? {
+            // the records of every section, not the sections themselves
+            Value::Object(sections) if format.has_sections() && is_sections(&sections) => {
+                Value::Object(
+                    sections
+                        .into_iter()
+                        .map(|(name, records)| {
+                            let select = section_select(&name, &records, format, select);
+                            (name, apply_select(records, &select))
+                        })
+                        .collect(),
+                )
+            }
+            value => apply_select(value, select),
+        };
+        return write_output(&value, output_path, format, pretty, select);
     }
 
-    Ok(())
+    write_output(value, output_path, format, pretty, select)
+}
+
+fn write_output<T>(
+    value: &T,
+    output_path: &Option<PathBuf>,
+    format: &OutputFormat,
+    pretty: bool,
+    select: &SelectArgs,
+) -> Result<(), OutputError>
+where
+    T: ?Sized + serde::ser::Serialize,
+{
+    let writer = open_output(output_path, format)?;
+    to_writer(writer, value, format, pretty, select)
+}
+
+/// the file at `output_path`, or stdout if None (unless `format` is binary and stdout is a
+/// terminal)
+fn open_output(
+    output_path: &Option<PathBuf>,
+    format: &OutputFormat,
+) -> Result<Box<dyn io::Write + Send>, OutputError> {
+    if output_path.is_none() && format.is_binary() && io::stdout().is_terminal() {
+        log::error!(
+            "not writing {} to a terminal; use --output-path or redirect stdout",
+            format.as_str()
+        );
+        return Err(OutputError::BinaryToTerminal(format.clone()));
+    }
+
+    let Some(output_path) = output_path else {
+        return Ok(Box::new(io::stdout()));
+    };
+
+    // TODO: change map_err to inspect_err once stable
+    let file = File::create(output_path).map_err(|err| {
+        log::error!("{err}");
+        err
+    })?;
+    Ok(Box::new(file))
+}
+
+/// prints the requests `client` did not send because of `--dry-run` as JSON, if there are any
+#[cfg(any(feature = "cmdline", feature = "interactive"))]
+pub(crate) fn print_planned_requests<C>(client: &C) -> Result<(), OutputError>
+where
+    C: QboClient + ?Sized,
+{
+    let planned = client.planned_requests();
+    if planned.is_empty() {
+        return Ok(());
+    }
+
+    to_output_path(
+        &planned,
+        &None,
+        &OutputFormat::Json,
+        true,
+        &SelectArgs::default(),
+    )
+}
+
+/// `records` under `name` for formats with sections (so an export of customers is written to a
+/// `Customer` sheet, as a table of the customer columns or as `[[Customer]]` tables), or just
+/// `records` for every other format
+pub(crate) fn named_records<T>(name: &str, records: Vec<T>, format: &OutputFormat) -> Value
+where
+    T: serde::ser::Serialize,
+{
+    if format.has_sections() {
+        serde_json::json!({ name: records })
+    } else {
+        serde_json::json!(records)
+    }
+}
+
+/// whether every value of `object` is an array of records, each of which is its own section
+pub(crate) fn is_sections(object: &Map<String, Value>) -> bool {
+    !object.is_empty() && object.values().all(Value::is_array)
+}
+
+/// `select` for the records of a section; tables show the default columns of the entity the
+/// section is named after, unless `--fields` or `--wide` is given
+fn section_select(
+    name: &str,
+    records: &Value,
+    format: &OutputFormat,
+    select: &SelectArgs,
+) -> SelectArgs {
+    if *format != OutputFormat::Table || !select.fields.is_empty() || select.wide {
+        return select.clone();
+    }
+    let Ok(entity) = name.parse::<Entity>() else {
+        return select.clone();
+    };
+
+    // columns a query did not select (or QBO left out) would only be empty
+    let fields: Vec<String> = entity
+        .default_columns()
+        .iter()
+        .filter(|column| {
+            records
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|record| !tabular::lookup(record, column).is_null())
+        })
+        .map(|column| column.to_string())
+        .collect();
+
+    SelectArgs {
+        fields,
+        ..select.clone()
+    }
+}
+
+/// applies `--filter` to every record of `value` (or to `value` itself if it is not an array),
+/// then `--fields` to the records that are left
+fn apply_select(value: Value, select: &SelectArgs) -> Value {
+    let keep = |record: &Value| match &select.filter {
+        Some(filter) => filter.matches(record),
+        None => true,
+    };
+    let project = |record: Value| {
+        if select.fields.is_empty() {
+            record
+        } else {
+            project(&record, &select.fields)
+        }
+    };
+
+    match value {
+        Value::Array(records) => records.into_iter().filter(keep).map(project).collect(),
+        record if keep(&record) => project(record),
+        _ => Value::Null,
+    }
+}
+
+/// a copy of `record` with only `fields` (dotted paths), in that order; missing fields are `null`
+/// so every record has the same columns
+fn project(record: &Value, fields: &[String]) -> Value {
+    fn insert(object: &mut Map<String, Value>, path: &[&str], value: Value) {
+        match path {
+            [] => {}
+            [field] => {
+                object.insert(field.to_string(), value);
+            }
+            [field, rest @ ..] => {
+                let child = object
+                    .entry(field.to_string())
+                    .or_insert_with(|| Value::Object(Map::new()));
+
+                // `A.B` after `A` is already part of `A`
+                if let Value::Object(child) = child {
+                    insert(child, rest, value);
+                }
+            }
+        }
+    }
+
+    let mut projected = Map::new();
+    for field in fields {
+        let path: Vec<&str> = field.split('.').collect();
+        insert(
+            &mut projected,
+            &path,
+            tabular::lookup(record, field).clone(),
+        );
+    }
+
+    Value::Object(projected)
 }
 
 pub(crate) fn to_writer<W, T>(
@@ -162,9 +652,10 @@ pub(crate) fn to_writer<W, T>(
     value: &T,
     format: &OutputFormat,
     pretty: bool,
+    select: &SelectArgs,
 ) -> Result<(), OutputError>
 where
-    W: std::io::Write,
+    W: std::io::Write + Send,
     T: ?Sized + serde::ser::Serialize,
 {
     fn expect<T, E>(result: Result<T, E>, format: &OutputFormat) -> Result<T, OutputError>
@@ -193,37 +684,76 @@ where
             }(&mut writer, value),
             format,
         )?,
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let table = tabular::Table::from_value(&expect(serde_json::to_value(value), format)?);
+            let delimiter = if *format == OutputFormat::Csv {
+                ','
+            } else {
+                '\t'
+            };
+            expect(table.write_delimited(&mut writer, delimiter), format)?
+        }
+        OutputFormat::Table => {
+            let value = expect(serde_json::to_value(value), format)?;
+            expect(
+                table::write_tables(&mut writer, &value, select.wide),
+                format,
+            )?
+        }
         #[cfg(feature = "toml")]
-        OutputFormat::Toml => expect(
-            writer.write_all(
-                match toml::to_string(value) {
-                    Ok(toml_str) => toml_str,
-                    Err(err) => {
-                        log::error!("failed to serialize value to {}: {err}", format.as_str());
-                        return Err(err)?;
-                    }
-                }
-                .as_bytes(),
-            ),
-            format,
-        )?,
+        OutputFormat::Toml => {
+            let value = expect(serde_json::to_value(value), format)?;
+            let document = expect(toml_document::to_string(&value, pretty), format)?;
+            expect(writer.write_all(document.as_bytes()), format)?
+        }
         #[cfg(feature = "yaml")]
         OutputFormat::Yaml => expect(serde_yaml::to_writer(&mut writer, value), format)?,
+        #[cfg(feature = "xlsx")]
+        OutputFormat::Xlsx => {
+            let value = expect(serde_json::to_value(value), format)?;
+            let workbook = expect(xlsx::to_workbook(&value, "Sheet1"), format)?;
+            expect(writer.write_all(&workbook), format)?;
+
+            // a new line would corrupt the workbook
+            return Ok(());
+        }
+        #[cfg(feature = "template")]
+        OutputFormat::Template => {
+            let Some(template_path) = &select.template else {
+                log::error!("the template format needs --template");
+                return Err(OutputError::NoTemplate);
+            };
+            let value = expect(serde_json::to_value(value), format)?;
+            expect(
+                template::write_rendered(&mut writer, &value, template_path, select.each),
+                format,
+            )?;
+
+            // the template decides where every new line goes
+            return Ok(());
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => {
+            let value = expect(serde_json::to_value(value), format)?;
+            let parquet = columnar::ParquetWriter::Empty(&mut writer);
+            let parquet = expect(parquet.write_page(&value), format)?;
+            expect(parquet.finish(), format)?;
+
+            return Ok(());
+        }
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => {
+            let value = expect(serde_json::to_value(value), format)?;
+            expect(columnar::write_arrow(&mut writer, &value), format)?;
+
+            return Ok(());
+        }
     }
 
     if let Err(err) = writer.write_all(slice::from_ref(&b'\n')) {
         log::error!("failed to write a new line to writer: {err}");
-        return Err(err)?;
+        return Err(err.into());
     }
 
     Ok(())
 }
-
-fn we_do_a_bit_of_logging(values: &Vec<serde_json::Value>, key: &str) {
-    log::trace!("{key}: {values:?}");
-    log::info!("number of {key}s: {}", values.len());
-
-    if values.len() == 1000 {
-        log::error!("number of {key}s is equal to 1,000; if you have more than 1,000 {key}s, the response has been reduced to 1,000 {key}s");
-    }
-}
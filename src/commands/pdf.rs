@@ -0,0 +1,305 @@
+use super::CommandError;
+use crate::{
+    args::{PdfArgs, PdfBulkArgs, PdfFileArgs},
+    client::QboClient,
+    entity::Entity,
+    export::{export, ExportOptions},
+    query::Condition,
+};
+
+use serde_json::Value;
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// downloads the PDF of a single transaction to `-o`, or to a file named with `--name`
+pub fn one<C>(client: &C, entity: Entity, args: &PdfArgs, quiet: bool) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let path = match &args.output_path {
+        Some(path) => path.clone(),
+        None => {
+            let record = client.read(entity.as_str(), &args.id)?;
+            relative_path(&file_name(&args.file.name, &record))?
+        }
+    };
+
+    let written = download(client, entity, &args.id, &path, &args.file)?;
+
+    if quiet {
+        return Ok(());
+    }
+
+    if written {
+        eprintln!("wrote {}", path.display());
+    } else {
+        eprintln!("skipped {}, which already exists", path.display());
+    }
+
+    Ok(())
+}
+
+/// Downloads the PDF of every transaction matching `--where` to `--out-dir`.
+///
+/// Files that already exist are skipped, so an archive can be brought up to date (or a failed run
+/// finished) by running the same command again.
+pub fn many<C>(
+    client: &C,
+    entity: Entity,
+    args: &PdfBulkArgs,
+    quiet: bool,
+) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let mut options = ExportOptions::new().order_by("Id");
+    if let Some(r#where) = &args.r#where {
+        options = options.filter(Condition::raw(r#where));
+    }
+
+    let mut names = HashSet::new();
+    let (mut written, mut skipped) = (0, 0);
+
+    for record in export(client, entity, options) {
+        let record = record?;
+        let id = record["Id"].as_str().unwrap_or_default();
+
+        let name = unique_name(&mut names, file_name(&args.file.name, &record), id);
+        let path = args.out_dir.join(relative_path(&name)?);
+        if download(client, entity, id, &path, &args.file)? {
+            written += 1;
+        } else {
+            skipped += 1;
+        }
+    }
+
+    if !quiet {
+        eprintln!(
+            "wrote {written} PDFs to {}, skipped {skipped} that already existed",
+            args.out_dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// writes the PDF to `path`, returning false without downloading it if `path` already exists
+fn download<C>(
+    client: &C,
+    entity: Entity,
+    id: &str,
+    path: &Path,
+    args: &PdfFileArgs,
+) -> Result<bool, CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    if path.exists() && !args.force {
+        return Ok(false);
+    }
+
+    let pdf = client.pdf(entity.as_str(), id)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, pdf)?;
+
+    Ok(true)
+}
+
+/// Adds `name` to `names`, or a name made unique with the `Id` of the transaction, then a number,
+/// if it is already there.
+///
+/// Transactions without a `DocNumber` would all get the same name otherwise, and each would
+/// overwrite (or, without `--force`, be skipped for) the one before.
+fn unique_name(names: &mut HashSet<String>, name: String, id: &str) -> String {
+    if names.insert(name.clone()) {
+        return name;
+    }
+
+    let path = Path::new(&name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    let candidate = |suffix: &str| {
+        path.with_file_name(format!("{stem}-{suffix}{extension}"))
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let mut unique = candidate(id);
+    let mut number = 2;
+    while !names.insert(unique.clone()) {
+        unique = candidate(&format!("{id}-{number}"));
+        number += 1;
+    }
+    unique
+}
+
+/// `name` as a path, if it stays in the directory it is joined to: a name with `..`, or one that
+/// is absolute, could write anywhere
+fn relative_path(name: &str) -> Result<PathBuf, CommandError> {
+    let path = PathBuf::from(name);
+
+    let is_relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if name.is_empty() || !is_relative {
+        return Err(CommandError::InvalidInput(format!(
+            "the PDF file name {name:?} is not a path under the output directory"
+        )));
+    }
+
+    Ok(path)
+}
+
+/// `pattern` with every `{Field.Path}` replaced by that field of `record`, eg.
+/// `{DocNumber}-{CustomerRef.name}.pdf` to `1037-Amy's Bird Sanctuary.pdf`
+fn file_name(pattern: &str, record: &Value) -> String {
+    let mut name = String::new();
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        name.push_str(&rest[..start]);
+
+        let path = &rest[start + 1..start + length];
+        let value = path
+            .split('.')
+            .try_fold(record, |value, field| value.get(field))
+            .unwrap_or(&Value::Null);
+        let value = match value {
+            Value::Null => String::new(),
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        };
+
+        // a value is never a directory (or `.` or `..`), unlike a `/` in the pattern itself
+        if !value.is_empty() && value.chars().all(|c| c == '.') {
+            name.push_str(&"_".repeat(value.len()));
+        } else {
+            name.extend(value.chars().map(|c| {
+                if c.is_control()
+                    || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+                {
+                    '_'
+                } else {
+                    c
+                }
+            }));
+        }
+
+        rest = &rest[start + length + 1..];
+    }
+
+    name.push_str(rest);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn invoice() -> Value {
+        json!({
+            "Id": "130",
+            "DocNumber": "1037",
+            "TxnDate": "2026-02-01",
+            "CustomerRef": { "value": "1", "name": "Amy's Bird Sanctuary" },
+            "TotalAmt": 239.5,
+        })
+    }
+
+    #[test]
+    fn fills_in_fields() {
+        assert_eq!(
+            file_name("{DocNumber}-{CustomerRef.name}.pdf", &invoice()),
+            "1037-Amy's Bird Sanctuary.pdf"
+        );
+        assert_eq!(
+            file_name("{TxnDate}/{TotalAmt}{Missing}.pdf", &invoice()),
+            "2026-02-01/239.5.pdf"
+        );
+        assert_eq!(
+            file_name("unclosed {DocNumber", &invoice()),
+            "unclosed {DocNumber"
+        );
+    }
+
+    #[test]
+    fn values_are_never_directories() {
+        let record =
+            json!({ "DocNumber": "../../etc/passwd", "Dot": ".", "Dots": "..", "Name": "a:b*c" });
+
+        assert_eq!(
+            file_name("{DocNumber}.pdf", &record),
+            ".._.._etc_passwd.pdf"
+        );
+        assert_eq!(file_name("{Dots}/{Dot}/{Name}", &record), "__/_/a_b_c");
+    }
+
+    #[test]
+    fn paths_stay_in_the_output_directory() {
+        assert_eq!(
+            relative_path("2026/1037.pdf").unwrap(),
+            Path::new("2026/1037.pdf")
+        );
+
+        for name in [
+            "",
+            "../1037.pdf",
+            "2026/../../1037.pdf",
+            "/etc/1037.pdf",
+            "..",
+        ] {
+            assert!(
+                matches!(relative_path(name), Err(CommandError::InvalidInput(_))),
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn names_are_made_unique_with_the_id() {
+        let mut names = HashSet::new();
+
+        assert_eq!(
+            unique_name(&mut names, "invoice.pdf".to_string(), "1"),
+            "invoice.pdf"
+        );
+        assert_eq!(
+            unique_name(&mut names, "invoice.pdf".to_string(), "2"),
+            "invoice-2.pdf"
+        );
+        assert_eq!(
+            unique_name(&mut names, "2026/invoice".to_string(), "3"),
+            "2026/invoice"
+        );
+        assert_eq!(
+            unique_name(&mut names, "2026/invoice".to_string(), "4"),
+            "2026/invoice-4"
+        );
+
+        // a name taken by another transaction's own name
+        assert_eq!(
+            unique_name(&mut names, "invoice-5.pdf".to_string(), "9"),
+            "invoice-5.pdf"
+        );
+        assert_eq!(
+            unique_name(&mut names, "invoice.pdf".to_string(), "5"),
+            "invoice-5-2.pdf"
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use super::{pages, take_records, to_output, write_pages, CommandError};
+use crate::{args::OutputArgs, client::QboClient};
+
+use serde_json::json;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryArgs {
+    pub output: OutputArgs,
+    pub quiet: bool,
+
+    pub query: String,
+    pub page_size: u32,
+}
+
+/// Runs a query as-is and prints every record it matches.
+///
+/// Unless the query has its own `STARTPOSITION` or `MAXRESULTS`, pages of `page_size` records
+/// are fetched until one comes back short. `COUNT(*)` queries print `{ "totalCount": N }`.
+pub fn run<C>(client: &C, args: &QueryArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let query = args.query.trim().trim_end_matches(';').trim_end();
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    if words.first().map(String::as_str) != Some("select") {
+        return Err(CommandError::InvalidInput(
+            "only SELECT queries can be run".to_string(),
+        ));
+    }
+
+    if words.concat().starts_with("selectcount(*)") {
+        let count = client.query(query)?["totalCount"].take();
+
+        return Ok(to_output(&json!({ "totalCount": count }), &args.output)?);
+    }
+
+    let paginate = !words
+        .iter()
+        .any(|word| word == "startposition" || word == "maxresults");
+
+    if paginate {
+        return write_pages(
+            "record",
+            pages(client, query.to_string(), args.page_size),
+            &args.output,
+        );
+    }
+
+    let page = take_records(&mut client.query(query)?);
+    write_pages("record", [Ok(page)], &args.output)
+}
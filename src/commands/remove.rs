@@ -0,0 +1,223 @@
+use super::{to_output, to_output_path, CommandError};
+use crate::{
+    api::{self, BatchOperation, Request},
+    args::{OutputArgs, OutputFormat, SelectArgs},
+    client::QboClient,
+    entity::{Entity, Removal},
+};
+
+use serde_json::{json, Value};
+
+use std::{
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoveArgs {
+    pub output: OutputArgs,
+    pub quiet: bool,
+    pub dry_run: bool,
+
+    pub entity: Entity,
+    pub ids: Vec<String>,
+    pub void: bool,
+    pub yes: bool,
+    pub backup_dir: PathBuf,
+}
+
+/// Deactivates, deletes or voids entities, depending on what the entity type supports.
+///
+/// Every record is written to `backup_dir` before any of them are modified. Deactivations and
+/// deletions are sent through `/batch`.
+pub fn remove<C>(client: &C, args: &RemoveArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let removal = removal_for(args)?;
+
+    let records = args
+        .ids
+        .iter()
+        .map(|id| client.read(args.entity.as_str(), id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !args.dry_run && !args.yes {
+        eprintln!("about to {} {}:", removal.as_str(), args.entity);
+        for record in &records {
+            eprintln!("  {}", describe(record));
+        }
+
+        if !confirm("continue?")? {
+            return Err(CommandError::Aborted);
+        }
+    }
+
+    if !args.dry_run {
+        for record in &records {
+            backup(&args.backup_dir, args.entity, removal, record)?;
+        }
+    }
+
+    let mut removed = Vec::with_capacity(records.len());
+    let mut faults = 0;
+
+    let operations: Option<Vec<_>> = records
+        .iter()
+        .map(|record| operation(args.entity, removal, record))
+        .collect();
+
+    match operations {
+        Some(operations) => {
+            for result in client.batch(&operations)? {
+                match (result.result, result.fault) {
+                    (_, Some(fault)) => {
+                        log::error!(
+                            "failed to {} {} {}: {fault}",
+                            removal.as_str(),
+                            args.entity,
+                            describe(&records[result.index])
+                        );
+                        faults += 1;
+                    }
+                    (Some(entity), None) => removed.push(entity),
+                    (None, None) => {}
+                }
+            }
+        }
+        None => {
+            for record in &records {
+                if let Some(mut response) = client.send(void_request(args.entity, record))? {
+                    removed.push(api::take_entity(&mut response));
+                }
+            }
+        }
+    }
+
+    // the planned requests are printed instead
+    if args.dry_run {
+        return Ok(());
+    }
+
+    to_output(&removed, &args.output)?;
+
+    if faults > 0 {
+        return Err(CommandError::Faults(faults));
+    }
+
+    Ok(())
+}
+
+fn removal_for(args: &RemoveArgs) -> Result<Removal, CommandError> {
+    if args.void {
+        return if args.entity.is_voidable() {
+            Ok(Removal::Void)
+        } else {
+            Err(CommandError::InvalidInput(format!(
+                "{} cannot be voided",
+                args.entity
+            )))
+        };
+    }
+
+    args.entity.removal().ok_or_else(|| {
+        CommandError::InvalidInput(format!("{} cannot be removed through the API", args.entity))
+    })
+}
+
+/// the `/batch` operation removing `record`, or None for voids, which `/batch` cannot send
+fn operation(entity: Entity, removal: Removal, record: &Value) -> Option<BatchOperation> {
+    let (id, sync_token) = (record["Id"].clone(), record["SyncToken"].clone());
+
+    match removal {
+        Removal::Deactivate => Some(BatchOperation::Update {
+            entity,
+            body: json!({ "Id": id, "SyncToken": sync_token, "sparse": true, "Active": false }),
+        }),
+        Removal::Delete => Some(BatchOperation::Delete {
+            entity,
+            body: json!({ "Id": id, "SyncToken": sync_token }),
+        }),
+        Removal::Void => None,
+    }
+}
+
+fn void_request(entity: Entity, record: &Value) -> Request {
+    let (id, sync_token) = (record["Id"].clone(), record["SyncToken"].clone());
+
+    if entity == Entity::Payment {
+        // payments are voided through a sparse update rather than `operation=void`
+        Request::update(
+            entity.as_str(),
+            json!({ "Id": id, "SyncToken": sync_token, "sparse": true }),
+        )
+        .with_query("include", "void")
+    } else {
+        Request::void(
+            entity.as_str(),
+            json!({ "Id": id, "SyncToken": sync_token }),
+        )
+    }
+}
+
+/// eg. `58 (Amy's Bird Sanctuary)`
+fn describe(record: &Value) -> String {
+    let id = record["Id"].as_str().unwrap_or("?");
+    let name = ["DisplayName", "FullyQualifiedName", "Name", "DocNumber"]
+        .into_iter()
+        .find_map(|field| record.get(field)?.as_str());
+
+    match name {
+        Some(name) => format!("{id} ({name})"),
+        None => id.to_string(),
+    }
+}
+
+fn confirm(prompt: &str) -> io::Result<bool> {
+    let stdin = io::stdin();
+
+    if !stdin.is_terminal() {
+        log::error!("stdin is not a terminal; pass --yes to skip confirmation");
+        return Ok(false);
+    }
+
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// writes `record` to `<backup_dir>/<Entity>-<Id>-<operation>-<unix time>.json`
+fn backup(
+    backup_dir: &Path,
+    entity: Entity,
+    removal: Removal,
+    record: &Value,
+) -> Result<(), CommandError> {
+    std::fs::create_dir_all(backup_dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = backup_dir.join(format!(
+        "{entity}-{}-{}-{timestamp}.json",
+        record["Id"].as_str().unwrap_or("unknown"),
+        removal.as_str()
+    ));
+
+    log::info!("backing up {entity} to {}", path.display());
+
+    Ok(to_output_path(
+        record,
+        &Some(path),
+        &OutputFormat::Json,
+        true,
+        // backups always have every field
+        &SelectArgs::default(),
+    )?)
+}
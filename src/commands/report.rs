@@ -0,0 +1,351 @@
+use super::{to_output, CommandError};
+use crate::{
+    api::Request,
+    args::{AccountingMethod, OutputArgs, ReportName},
+    client::QboClient,
+    query::is_date,
+};
+
+#[cfg(feature = "xlsx")]
+use crate::args::OutputFormat;
+
+use serde_json::{json, Map, Value};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportArgs {
+    pub output: OutputArgs,
+    pub quiet: bool,
+
+    pub report: ReportName,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub accounting_method: Option<AccountingMethod>,
+    pub summarize_by: Option<String>,
+    pub flat: bool,
+}
+
+/// Fetches a report and writes it as one row per report line (for tabular formats and `--flat`)
+/// or as a tree of sections.
+pub fn export<C>(client: &C, args: &ReportArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let request = request(args)?;
+
+    // reports never modify anything, so they are fetched in dry-run mode too
+    let report = client.fetch(request)?;
+
+    let format = args.output.format();
+    let columns = column_names(&report);
+
+    // binary formats are for loading into spreadsheets and databases, which want rows
+    let value = if args.flat || format.is_tabular() || format.is_binary() {
+        let mut lines = Vec::new();
+        flatten_rows(&report["Rows"], &columns, &mut Vec::new(), &mut lines);
+
+        #[cfg(feature = "xlsx")]
+        if !args.flat && format == OutputFormat::Xlsx {
+            return Ok(to_output(&by_top_section(lines), &args.output)?);
+        }
+
+        Value::Array(lines)
+    } else {
+        json!({
+            "Header": report["Header"],
+            "Columns": columns,
+            "Rows": tree(&report["Rows"], &columns),
+        })
+    };
+
+    Ok(to_output(&value, &args.output)?)
+}
+
+fn request(args: &ReportArgs) -> Result<Request, CommandError> {
+    for date in [&args.from, &args.to].into_iter().flatten() {
+        if !is_date(date) {
+            return Err(CommandError::InvalidInput(format!(
+                "{date:?} is not a YYYY-MM-DD date"
+            )));
+        }
+    }
+
+    let mut request = Request::get(format!("reports/{}", args.report.as_str()));
+
+    if args.report.is_aging() {
+        if args.from.is_some() {
+            log::warn!(
+                "{} is as of a single date; ignoring --from",
+                args.report.as_str()
+            );
+        }
+        if let Some(to) = &args.to {
+            request = request.with_query("report_date", to);
+        }
+    } else {
+        if let Some(from) = &args.from {
+            request = request.with_query("start_date", from);
+        }
+        if let Some(to) = &args.to {
+            request = request.with_query("end_date", to);
+        }
+    }
+
+    if let Some(method) = args.accounting_method {
+        request = request.with_query("accounting_method", method.as_str());
+    }
+    if let Some(summarize_by) = &args.summarize_by {
+        request = request.with_query("summarize_column_by", summarize_by);
+    }
+
+    Ok(request)
+}
+
+/// `ColTitle` of every column (`ColType` if the title is empty), made unique
+fn column_names(report: &Value) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for column in report["Columns"]["Column"].as_array().into_iter().flatten() {
+        let name = match column["ColTitle"].as_str() {
+            Some(title) if !title.is_empty() => title,
+            _ => column["ColType"].as_str().unwrap_or("Column"),
+        };
+
+        let mut unique = name.to_string();
+        let mut suffix = 2;
+        while names.contains(&unique) {
+            unique = format!("{name} ({suffix})");
+            suffix += 1;
+        }
+
+        names.push(unique);
+    }
+
+    names
+}
+
+/// `{ "ColData": [{ "value": ... }, ...] }` as `{ "<column name>": value, ... }`
+fn cells(row: &Value, columns: &[String]) -> Map<String, Value> {
+    columns
+        .iter()
+        .zip(row["ColData"].as_array().into_iter().flatten())
+        .map(|(column, data)| (column.clone(), data["value"].clone()))
+        .collect()
+}
+
+fn section_name(section: &Value) -> String {
+    section["Header"]["ColData"][0]["value"]
+        .as_str()
+        .or_else(|| section["group"].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn is_section(row: &Value) -> bool {
+    row["type"] == "Section" || row.get("Rows").is_some()
+}
+
+fn tree(rows: &Value, columns: &[String]) -> Vec<Value> {
+    rows["Row"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|row| {
+            if is_section(row) {
+                json!({
+                    "Section": section_name(row),
+                    "Group": row["group"],
+                    "Rows": tree(&row["Rows"], columns),
+                    "Summary": cells(&row["Summary"], columns),
+                })
+            } else {
+                Value::Object(cells(row, columns))
+            }
+        })
+        .collect()
+}
+
+/// one line per data and summary row, with the sections they are in joined by ` > `
+fn flatten_rows(rows: &Value, columns: &[String], path: &mut Vec<String>, lines: &mut Vec<Value>) {
+    for row in rows["Row"].as_array().into_iter().flatten() {
+        if is_section(row) {
+            path.push(section_name(row));
+
+            flatten_rows(&row["Rows"], columns, path, lines);
+            if row.get("Summary").is_some() {
+                lines.push(line(path, "Summary", cells(&row["Summary"], columns)));
+            }
+
+            path.pop();
+        } else {
+            lines.push(line(path, "Data", cells(row, columns)));
+        }
+    }
+}
+
+/// the lines of each top-level section (eg. Income, Expenses) apart, which workbooks write as a
+/// sheet each; lines outside of any section are under `Report`
+#[cfg(feature = "xlsx")]
+fn by_top_section(lines: Vec<Value>) -> Value {
+    let mut sections = Map::new();
+
+    for line in lines {
+        let section = match line["Section"].as_str() {
+            Some(section) if !section.is_empty() => section.split(" > ").next().unwrap_or(section),
+            _ => "Report",
+        }
+        .to_string();
+
+        if let Some(Value::Array(section_lines)) = sections.get_mut(&section) {
+            section_lines.push(line);
+        } else {
+            sections.insert(section, Value::Array(vec![line]));
+        }
+    }
+
+    Value::Object(sections)
+}
+
+fn line(path: &[String], row_type: &str, cells: Map<String, Value>) -> Value {
+    let mut line = Map::new();
+    line.insert("Section".to_string(), path.join(" > ").into());
+    line.insert("RowType".to_string(), row_type.into());
+    line.extend(cells);
+
+    Value::Object(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a profit and loss report, shortened
+    fn profit_and_loss() -> Value {
+        json!({
+            "Header": { "ReportName": "ProfitAndLoss" },
+            "Columns": { "Column": [
+                { "ColTitle": "", "ColType": "Account" },
+                { "ColTitle": "Total", "ColType": "Money" },
+                { "ColTitle": "Total", "ColType": "Money" },
+            ]},
+            "Rows": { "Row": [
+                {
+                    "type": "Section",
+                    "group": "Income",
+                    "Header": { "ColData": [{ "value": "Income" }, { "value": "" }] },
+                    "Rows": { "Row": [
+                        { "type": "Data", "ColData": [{ "value": "Design income" }, { "value": "2250.00" }] },
+                        {
+                            "type": "Section",
+                            "Header": { "ColData": [{ "value": "Landscaping Services" }, { "value": "" }] },
+                            "Rows": { "Row": [
+                                { "type": "Data", "ColData": [{ "value": "Job Materials" }, { "value": "1000.00" }] },
+                            ]},
+                            "Summary": { "ColData": [{ "value": "Total Landscaping Services" }, { "value": "1000.00" }] },
+                        },
+                    ]},
+                    "Summary": { "ColData": [{ "value": "Total Income" }, { "value": "3250.00" }] },
+                },
+                {
+                    "type": "Section",
+                    "group": "NetIncome",
+                    "Summary": { "ColData": [{ "value": "Net Income" }, { "value": "3250.00" }] },
+                },
+            ]},
+        })
+    }
+
+    fn args(report: ReportName, from: Option<&str>, to: Option<&str>) -> ReportArgs {
+        ReportArgs {
+            output: OutputArgs::default(),
+            quiet: true,
+            report,
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            accounting_method: None,
+            summarize_by: None,
+            flat: false,
+        }
+    }
+
+    #[test]
+    fn column_names_are_unique() {
+        assert_eq!(
+            column_names(&profit_and_loss()),
+            ["Account", "Total", "Total (2)"]
+        );
+    }
+
+    #[test]
+    fn flat_rows_have_their_sections() {
+        let report = profit_and_loss();
+        let columns = column_names(&report);
+        let mut lines = Vec::new();
+        flatten_rows(&report["Rows"], &columns, &mut Vec::new(), &mut lines);
+
+        let summary: Vec<_> = lines
+            .iter()
+            .map(|line| {
+                (
+                    line["Section"].as_str().unwrap(),
+                    line["RowType"].as_str().unwrap(),
+                    line["Account"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Income", "Data", "Design income"),
+                ("Income > Landscaping Services", "Data", "Job Materials"),
+                (
+                    "Income > Landscaping Services",
+                    "Summary",
+                    "Total Landscaping Services"
+                ),
+                ("Income", "Summary", "Total Income"),
+                ("NetIncome", "Summary", "Net Income"),
+            ]
+        );
+        assert_eq!(lines[0]["Total"], "2250.00");
+    }
+
+    #[test]
+    fn tree_keeps_sections_nested() {
+        let report = profit_and_loss();
+        let columns = column_names(&report);
+        let tree = tree(&report["Rows"], &columns);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0]["Section"], "Income");
+        assert_eq!(tree[0]["Rows"][1]["Section"], "Landscaping Services");
+        assert_eq!(tree[0]["Rows"][1]["Rows"][0]["Account"], "Job Materials");
+        assert_eq!(tree[0]["Summary"]["Total"], "3250.00");
+    }
+
+    #[test]
+    fn aging_reports_are_as_of_a_date() {
+        let aging = request(&args(
+            ReportName::AgedReceivables,
+            Some("2026-01-01"),
+            Some("2026-01-31"),
+        ))
+        .unwrap();
+        assert_eq!(aging.endpoint, "reports/AgedReceivables");
+        assert_eq!(
+            aging.query.into_iter().collect::<Vec<_>>(),
+            [("report_date".to_string(), "2026-01-31".to_string())]
+        );
+
+        let profit_and_loss =
+            request(&args(ReportName::ProfitAndLoss, Some("2026-01-01"), None)).unwrap();
+        assert_eq!(
+            profit_and_loss.query.into_iter().collect::<Vec<_>>(),
+            [("start_date".to_string(), "2026-01-01".to_string())]
+        );
+    }
+
+    #[test]
+    fn dates_are_checked() {
+        assert!(request(&args(ReportName::ProfitAndLoss, Some("01/01/2026"), None)).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Aligned tables for reading records in a terminal.
+
+use super::{
+    is_sections,
+    tabular::{cell_to_string, Table},
+};
+
+use serde_json::Value;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use std::io;
+
+/// the widest a column gets without `--wide`
+const MAX_COLUMN_WIDTH: usize = 40;
+/// columns are not narrowed below this to fit the terminal, but left out
+const MIN_COLUMN_WIDTH: usize = 6;
+
+const COLUMN_SEPARATOR: &str = " │ ";
+const HEADER_SEPARATOR: &str = "─┼─";
+
+/// Writes `value` as a table, or as a table per key (under the key, if there are several) if it is
+/// an object of arrays like `{ "Customer": [...], "Item": [...] }`.
+///
+/// Unless `wide`, long text is truncated and columns that do not fit in the terminal are left out.
+pub(crate) fn write_tables<W>(writer: &mut W, value: &Value, wide: bool) -> io::Result<()>
+where
+    W: io::Write,
+{
+    let max_width = if wide {
+        None
+    } else {
+        terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+    };
+
+    let mut lines = Vec::new();
+    match value {
+        Value::Object(sections) if is_sections(sections) => {
+            for (name, records) in sections {
+                if sections.len() > 1 {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.push(name.clone());
+                }
+
+                lines.extend(table_lines(&Table::from_value(records), wide, max_width));
+            }
+        }
+        value => lines.extend(table_lines(&Table::from_value(value), wide, max_width)),
+    }
+
+    write!(writer, "{}", lines.join("\n"))
+}
+
+fn table_lines(table: &Table, wide: bool, max_width: Option<usize>) -> Vec<String> {
+    if table.rows.is_empty() {
+        return vec!["(no records)".to_string()];
+    }
+
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell_to_string(cell).replace(['\n', '\r', '\t'], " "))
+                .collect()
+        })
+        .collect();
+
+    // amounts and quantities line up on the right
+    let is_numeric: Vec<bool> = (0..table.columns.len())
+        .map(|col| {
+            table.rows.iter().any(|row| row[col].is_number())
+                && table
+                    .rows
+                    .iter()
+                    .all(|row| row[col].is_number() || row[col].is_null())
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(col, column)| {
+            let widest = rows.iter().map(|row| row[col].width()).max();
+            let width = widest.unwrap_or_default().max(column.width());
+
+            if wide {
+                width
+            } else {
+                width.min(MAX_COLUMN_WIDTH)
+            }
+        })
+        .collect();
+
+    // narrow the widest column until the table fits, then leave out columns from the right
+    let mut shown = widths.len();
+    if let Some(max_width) = max_width {
+        while shown > 1 && total_width(&widths[..shown]) > max_width {
+            let (widest, width) = widths[..shown]
+                .iter()
+                .copied()
+                .enumerate()
+                .max_by_key(|(_, width)| *width)
+                .unwrap_or_default();
+
+            if width > MIN_COLUMN_WIDTH {
+                widths[widest] -= 1;
+            } else {
+                shown -= 1;
+            }
+        }
+    }
+
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells[..shown]
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| pad(truncate(cell, widths[col]), widths[col], is_numeric[col]))
+            .collect();
+
+        cells.join(COLUMN_SEPARATOR).trim_end().to_string()
+    };
+
+    let mut lines = vec![line(&table.columns)];
+    lines.push(
+        widths[..shown]
+            .iter()
+            .map(|width| "─".repeat(*width))
+            .collect::<Vec<_>>()
+            .join(HEADER_SEPARATOR),
+    );
+    lines.extend(rows.iter().map(|row| line(row)));
+
+    if shown < widths.len() {
+        lines.push(format!(
+            "({} more columns; use --wide or --fields to see them)",
+            widths.len() - shown
+        ));
+    }
+
+    lines
+}
+
+fn total_width(widths: &[usize]) -> usize {
+    widths.iter().sum::<usize>() + COLUMN_SEPARATOR.width() * widths.len().saturating_sub(1)
+}
+
+/// `text` cut to `width` columns, ending in `…` if anything was cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or_default();
+        if truncated_width + c_width + 1 > width {
+            break;
+        }
+
+        truncated.push(c);
+        truncated_width += c_width;
+    }
+    truncated.push('…');
+
+    truncated
+}
+
+fn pad(text: String, width: usize, right_align: bool) -> String {
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+
+    if right_align {
+        padding + &text
+    } else {
+        text + &padding
+    }
+}
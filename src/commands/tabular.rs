@@ -0,0 +1,207 @@
+//! Flattening of JSON values into rows and columns, for output formats that cannot nest.
+
+use serde_json::Value;
+
+use std::{collections::HashMap, io};
+
+/// Rows of cells under a shared header.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// One row per element of `value` (a single row if it is not an array).
+    ///
+    /// Nested objects become `Parent.Child` columns, in order of first appearance; arrays are kept
+    /// as a single cell.
+    pub fn from_value(value: &Value) -> Self {
+        let records: Vec<&Value> = match value {
+            Value::Array(records) => records.iter().collect(),
+            record => vec![record],
+        };
+
+        let mut table = Self::default();
+        let mut column_indices = HashMap::new();
+
+        for record in records {
+            let mut cells = Vec::new();
+            flatten_into(String::new(), record, &mut cells);
+
+            let mut row = vec![Value::Null; table.columns.len()];
+            for (column, cell) in cells {
+                let index = *column_indices.entry(column.clone()).or_insert_with(|| {
+                    table.columns.push(column);
+                    table.columns.len() - 1
+                });
+
+                if index >= row.len() {
+                    row.resize(index + 1, Value::Null);
+                }
+                row[index] = cell;
+            }
+
+            table.rows.push(row);
+        }
+
+        for row in &mut table.rows {
+            row.resize(table.columns.len(), Value::Null);
+        }
+
+        table
+    }
+
+    /// writes the table as CSV (`delimiter` is `,`) or TSV (`delimiter` is `\t`), without a
+    /// trailing new line
+    pub fn write_delimited<W>(&self, writer: &mut W, delimiter: char) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let header: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| escape(column, delimiter))
+            .collect();
+        write!(writer, "{}", header.join(&delimiter.to_string()))?;
+
+        for row in &self.rows {
+            let cells: Vec<_> = row
+                .iter()
+                .map(|cell| escape(&cell_to_string(cell), delimiter))
+                .collect();
+            write!(writer, "\n{}", cells.join(&delimiter.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn flatten_into(prefix: String, value: &Value, cells: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let column = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                flatten_into(column, value, cells);
+            }
+        }
+        value if prefix.is_empty() => cells.push(("value".to_string(), value.clone())),
+        value => cells.push((prefix, value.clone())),
+    }
+}
+
+/// the value at a dotted path like `PrimaryEmailAddr.Address`, or `null` if there is none
+pub(crate) fn lookup<'a>(record: &'a Value, path: &str) -> &'a Value {
+    path.split('.')
+        .try_fold(record, |value, field| value.get(field))
+        .unwrap_or(&Value::Null)
+}
+
+/// strings as-is, `null` as an empty cell and everything else as JSON
+pub(crate) fn cell_to_string(cell: &Value) -> String {
+    match cell {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        cell => cell.to_string(),
+    }
+}
+
+fn escape(cell: &str, delimiter: char) -> String {
+    if delimiter == '\t' {
+        // TSV has no quoting, so characters that would break the layout are replaced
+        return cell.replace(['\t', '\n', '\r'], " ");
+    }
+
+    if cell.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn csv(value: &Value, delimiter: char) -> String {
+        let mut out = Vec::new();
+        Table::from_value(value)
+            .write_delimited(&mut out, delimiter)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn nested_objects_become_dotted_columns() {
+        let table = Table::from_value(&json!([
+            { "Id": "1", "PrimaryEmailAddr": { "Address": "amy@example.com" } },
+            { "Id": "2", "Balance": 12.5, "Tags": ["a", "b"] },
+        ]));
+
+        assert_eq!(
+            table.columns,
+            ["Id", "PrimaryEmailAddr.Address", "Balance", "Tags"]
+        );
+        assert_eq!(
+            table.rows,
+            [
+                vec![
+                    json!("1"),
+                    json!("amy@example.com"),
+                    Value::Null,
+                    Value::Null
+                ],
+                vec![json!("2"), Value::Null, json!(12.5), json!(["a", "b"])],
+            ]
+        );
+    }
+
+    #[test]
+    fn scalars_are_a_value_column() {
+        let table = Table::from_value(&json!(["a", 1]));
+
+        assert_eq!(table.columns, ["value"]);
+        assert_eq!(table.rows, [vec![json!("a")], vec![json!(1)]]);
+    }
+
+    #[test]
+    fn csv_quotes_cells_that_need_it() {
+        let value = json!([
+            { "Name": "Amy's Bird Sanctuary", "Notes": "plain" },
+            { "Name": "Cool Cars, Inc.", "Notes": "says \"hi\"\nand bye" },
+        ]);
+
+        assert_eq!(
+            csv(&value, ','),
+            "Name,Notes\n\
+             Amy's Bird Sanctuary,plain\n\
+             \"Cool Cars, Inc.\",\"says \"\"hi\"\"\nand bye\""
+        );
+    }
+
+    #[test]
+    fn tsv_replaces_tabs_and_new_lines() {
+        let value = json!([{ "Name": "a\tb", "Notes": "c\r\nd", "Balance": null, "Active": true }]);
+
+        assert_eq!(
+            csv(&value, '\t'),
+            "Name\tNotes\tBalance\tActive\na b\tc  d\t\ttrue"
+        );
+    }
+
+    #[test]
+    fn lookup_follows_dotted_paths() {
+        let record = json!({ "BillAddr": { "City": "Tucson" } });
+
+        assert_eq!(lookup(&record, "BillAddr.City"), &json!("Tucson"));
+        assert_eq!(lookup(&record, "BillAddr.Line1"), &Value::Null);
+        assert_eq!(lookup(&record, "ShipAddr.City"), &Value::Null);
+    }
+}
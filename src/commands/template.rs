@@ -0,0 +1,278 @@
+//! Output rendered through a Handlebars template, for letters, price lists and summaries.
+//!
+//! Besides the built-in helpers, templates can use:
+//! - `{{currency Balance}}`: `$1,234.50`, or with another symbol given as `symbol="€"`
+//! - `{{date TxnDate}}`: `January 2, 2026`, or in another format given as eg. `format="%d/%m/%Y"`
+//!   (`%Y`, `%m`, `%d`, `%B`, `%b`, `%H`, `%M` and `%S`, with `%-m` and `%-d` leaving out the zero)
+//! - `{{address BillAddr}}`: the lines of an address, or on one line with eg. `separator=", "`
+
+use super::OutputError;
+use crate::query::is_date_time;
+
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde_json::{json, Value};
+
+use std::{fs, io, path::Path};
+
+const TEMPLATE_NAME: &str = "output";
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+handlebars_helper!(CurrencyHelper: |value: Json, {symbol: str = "$"}| format_currency(value, symbol));
+handlebars_helper!(DateHelper: |value: Json, {format: str = "%B %-d, %Y"}| format_date(value, format));
+handlebars_helper!(AddressHelper: |value: Json, {separator: str = "\n"}| format_address(value, separator));
+
+/// Renders the template at `template_path` once with every record of `value` as `records` (and how
+/// many there are as `count`), or with each record as the context if `each`.
+///
+/// Nothing is escaped, since the output is rarely HTML.
+pub(crate) fn write_rendered<W>(
+    writer: &mut W,
+    value: &Value,
+    template_path: &Path,
+    each: bool,
+) -> Result<(), OutputError>
+where
+    W: io::Write,
+{
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry.register_helper("currency", Box::new(CurrencyHelper));
+    registry.register_helper("date", Box::new(DateHelper));
+    registry.register_helper("address", Box::new(AddressHelper));
+    registry
+        .register_template_string(TEMPLATE_NAME, fs::read_to_string(template_path)?)
+        .map_err(handlebars::RenderError::from)?;
+
+    let records = match value {
+        Value::Array(records) => records.as_slice(),
+        record => std::slice::from_ref(record),
+    };
+
+    if each {
+        for record in records {
+            writer.write_all(registry.render(TEMPLATE_NAME, record)?.as_bytes())?;
+        }
+    } else {
+        let rendered = registry.render(
+            TEMPLATE_NAME,
+            &json!({ "records": records, "count": records.len() }),
+        )?;
+        writer.write_all(rendered.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// `-$1,234.50` for `-1234.5`; numbers in strings (as in reports) are formatted too, anything else
+/// is kept as-is
+fn format_currency(amount: &Value, symbol: &str) -> String {
+    let number = match amount {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+    let Some(number) = number else {
+        return super::tabular::cell_to_string(amount);
+    };
+
+    let cents = (number.abs() * 100.0).round() as u64;
+    let digits = (cents / 100).to_string();
+
+    // a comma goes before every digit with a multiple of three digits from it to the end
+    let comma_at = digits.len() % 3;
+    let mut whole = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && index % 3 == comma_at {
+            whole.push(',');
+        }
+        whole.push(digit);
+    }
+
+    let sign = if number < 0.0 && cents > 0 { "-" } else { "" };
+    format!("{sign}{symbol}{whole}.{:02}", cents % 100)
+}
+
+/// a `YYYY-MM-DD` date (or date time) in `format`; anything else is kept as-is
+fn format_date(value: &Value, format: &str) -> String {
+    let Some(date) = value.as_str().filter(|date| is_date_time(date)) else {
+        return super::tabular::cell_to_string(value);
+    };
+
+    // is_date_time checked that these are digits where they are expected
+    let part = |range: std::ops::Range<usize>| date.get(range).unwrap_or("00");
+    let month_index = part(5..7).parse::<usize>().unwrap_or(1).clamp(1, 12) - 1;
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+
+        let mut spec = chars.next();
+        let unpadded = spec == Some('-');
+        if unpadded {
+            spec = chars.next();
+        }
+        let number = |text: &str| {
+            if unpadded {
+                text.trim_start_matches('0').to_string()
+            } else {
+                text.to_string()
+            }
+        };
+
+        match spec {
+            Some('Y') => formatted.push_str(part(0..4)),
+            Some('m') => formatted.push_str(&number(part(5..7))),
+            Some('d') => formatted.push_str(&number(part(8..10))),
+            Some('B') => formatted.push_str(MONTHS[month_index]),
+            Some('b') => formatted.push_str(&MONTHS[month_index][..3]),
+            Some('H') => formatted.push_str(part(11..13)),
+            Some('M') => formatted.push_str(part(14..16)),
+            Some('S') => formatted.push_str(part(17..19)),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                formatted.push('%');
+                if unpadded {
+                    formatted.push('-');
+                }
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+
+    formatted
+}
+
+/// the street lines of a QBO address, then `City, ST 12345`, then the country
+fn format_address(address: &Value, separator: &str) -> String {
+    let field = |name: &str| address[name].as_str().unwrap_or_default().trim();
+
+    let mut lines: Vec<String> = ["Line1", "Line2", "Line3", "Line4", "Line5"]
+        .into_iter()
+        .map(field)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let region = [field("CountrySubDivisionCode"), field("PostalCode")]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let city = [field("City"), region.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    lines.extend([city, field("Country").to_string()]);
+    lines.retain(|line| !line.is_empty());
+
+    lines.join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_currency() {
+        assert_eq!(format_currency(&json!(1234.5), "$"), "$1,234.50");
+        assert_eq!(format_currency(&json!(-1234567.891), "€"), "-€1,234,567.89");
+        assert_eq!(format_currency(&json!(999.999), "$"), "$1,000.00");
+        assert_eq!(format_currency(&json!(0), "$"), "$0.00");
+        // an amount that rounds to zero has no sign
+        assert_eq!(format_currency(&json!(-0.004), "$"), "$0.00");
+    }
+
+    #[test]
+    fn formats_report_amounts_given_as_strings() {
+        assert_eq!(format_currency(&json!("-250.00"), "$"), "-$250.00");
+        assert_eq!(format_currency(&json!(" 100000 "), "$"), "$100,000.00");
+        assert_eq!(format_currency(&json!("n/a"), "$"), "n/a");
+        assert_eq!(format_currency(&Value::Null, "$"), "");
+    }
+
+    #[test]
+    fn formats_dates() {
+        let date = json!("2026-03-05");
+
+        assert_eq!(format_date(&date, "%B %-d, %Y"), "March 5, 2026");
+        assert_eq!(format_date(&date, "%d/%m/%Y"), "05/03/2026");
+        assert_eq!(format_date(&date, "%-d.%-m. %b"), "5.3. Mar");
+        assert_eq!(format_date(&json!("2026-10-20"), "%-d/%-m"), "20/10");
+        assert_eq!(
+            format_date(&json!("2026-03-05T09:07:00-08:00"), "%Y-%m-%d %H:%M:%S"),
+            "2026-03-05 09:07:00"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_specifiers_and_values() {
+        assert_eq!(
+            format_date(&json!("2026-03-05"), "%q %-q 100%% %"),
+            "%q %-q 100% %"
+        );
+        assert_eq!(format_date(&json!("next week"), "%Y"), "next week");
+        assert_eq!(format_date(&json!(20260305), "%Y"), "20260305");
+    }
+
+    #[test]
+    fn formats_addresses() {
+        let address = json!({
+            "Line1": "Amy's Bird Sanctuary",
+            "Line2": " 4581 Finch St. ",
+            "City": "Bayshore",
+            "CountrySubDivisionCode": "CA",
+            "PostalCode": "94326",
+            "Country": "USA",
+        });
+
+        assert_eq!(
+            format_address(&address, ", "),
+            "Amy's Bird Sanctuary, 4581 Finch St., Bayshore, CA 94326, USA"
+        );
+    }
+
+    #[test]
+    fn formats_partial_addresses() {
+        assert_eq!(
+            format_address(
+                &json!({ "Line1": "1 Main St.", "PostalCode": "94043" }),
+                "\n"
+            ),
+            "1 Main St.\n94043"
+        );
+        assert_eq!(
+            format_address(&json!({ "City": "Bayshore", "Country": "USA" }), "\n"),
+            "Bayshore\nUSA"
+        );
+        assert_eq!(
+            format_address(
+                &json!({ "CountrySubDivisionCode": "CA", "Line1": "" }),
+                "\n"
+            ),
+            "CA"
+        );
+        assert_eq!(format_address(&json!({}), "\n"), "");
+        assert_eq!(format_address(&Value::Null, "\n"), "");
+    }
+}
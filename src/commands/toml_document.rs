@@ -0,0 +1,232 @@
+//! TOML documents from JSON values, which can have top-level arrays and `null`s that TOML cannot.
+
+use serde_json::Value;
+
+/// `value` as a TOML document.
+///
+/// An array of records is written as an array of tables (`[[records]]`, or `[[Customer]]` for an
+/// export of customers, which is passed as `{ "Customer": [...] }`) and anything else that is not
+/// an object as `value = ...`. `null`s are left out, since TOML has no null.
+pub(crate) fn to_string(value: &Value, pretty: bool) -> Result<String, toml::ser::Error> {
+    let document = match value {
+        Value::Object(_) => to_toml(value),
+        Value::Array(_) => to_toml(&serde_json::json!({ "records": value })),
+        value => to_toml(&serde_json::json!({ "value": value })),
+    }
+    .unwrap_or_else(|| toml::Value::Table(toml::value::Table::new()));
+
+    // toml::Value puts plain values before tables, as TOML needs
+    if pretty {
+        toml::to_string_pretty(&document)
+    } else {
+        toml::to_string(&document)
+    }
+}
+
+/// `None` for `null`, which is left out of tables and arrays
+fn to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(bool) => toml::Value::Boolean(*bool),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => toml::Value::Integer(integer),
+            // u64s too big for an i64 lose precision either way
+            None => toml::Value::Float(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(string) => toml::Value::String(string.clone()),
+        Value::Array(elements) => {
+            // an array of tables cannot have anything else in it, and the serializer writes tables
+            // nested in other arrays as tables of their own, so those are kept as JSON text; so is
+            // every element of an array of mixed types, which not every TOML reader takes
+            let mut kinds = elements
+                .iter()
+                .filter(|element| !element.is_null())
+                .map(kind);
+            let first_kind = kinds.next();
+            let is_mixed = kinds.any(|kind| Some(kind) != first_kind);
+            let is_array_of_tables = !is_mixed && matches!(first_kind, None | Some("object"));
+
+            toml::Value::Array(
+                elements
+                    .iter()
+                    .filter_map(|element| {
+                        if is_mixed && !element.is_null()
+                            || !is_array_of_tables && has_object(element)
+                        {
+                            Some(toml::Value::String(element.to_string()))
+                        } else {
+                            to_toml(element)
+                        }
+                    })
+                    .collect(),
+            )
+        }
+        Value::Object(object) => toml::Value::Table(
+            object
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), to_toml(value)?)))
+                .collect(),
+        ),
+    })
+}
+
+/// the kind of TOML value `value` becomes, which is the same for integers and floats since a
+/// number can be either
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_object(value: &Value) -> bool {
+    match value {
+        Value::Object(_) => true,
+        Value::Array(elements) => elements.iter().any(has_object),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{OutputFormat, SelectArgs};
+
+    use serde_json::json;
+
+    fn customers() -> Value {
+        json!({ "Customer": [{
+            "Id": "58",
+            "SyncToken": "0",
+            "DisplayName": "Bob's Burgers",
+            "Active": true,
+            "Balance": 1250.5,
+            "PrimaryEmailAddr": { "Address": "bob@example.com" },
+            "BillAddr": { "Line1": "12 Main St", "City": "Springfield" },
+            "MetaData": { "CreateTime": "2024-01-05T10:00:00-08:00" },
+            "Notes": null,
+        }] })
+    }
+
+    fn invoices() -> Value {
+        json!({ "Invoice": [{
+            "Id": "130",
+            "DocNumber": "1037",
+            "TxnDate": "2024-03-01",
+            "CustomerRef": { "value": "58", "name": "Bob's Burgers" },
+            "Line": [
+                {
+                    "Id": "1",
+                    "Amount": 100.0,
+                    "DetailType": "SalesItemLineDetail",
+                    "SalesItemLineDetail": { "ItemRef": { "value": "1" }, "Qty": 2, "UnitPrice": 50 },
+                },
+                { "Amount": 100.0, "DetailType": "SubTotalLineDetail", "SubTotalLineDetail": {} },
+            ],
+            "TotalAmt": 100.0,
+        }] })
+    }
+
+    fn records() -> Value {
+        json!([
+            { "Id": "1", "Name": "Hours", "UnitPrice": null },
+            { "Id": "2", "Name": "Parts", "UnitPrice": 12.5 },
+        ])
+    }
+
+    /// `value` written as `format` and read back
+    fn round_trip(value: &Value, format: &OutputFormat) -> Value {
+        let mut out = Vec::new();
+        super::super::to_writer(&mut out, value, format, true, &SelectArgs::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        match format {
+            OutputFormat::Json => serde_json::from_str(&text).unwrap(),
+            OutputFormat::Toml => {
+                serde_json::to_value(toml::from_str::<toml::Value>(&text).unwrap()).unwrap()
+            }
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml => serde_yaml::from_str(&text).unwrap(),
+            format => panic!("{format} cannot be read back"),
+        }
+    }
+
+    fn without_nulls(value: Value) -> Value {
+        match value {
+            Value::Array(elements) => elements
+                .into_iter()
+                .filter(|element| !element.is_null())
+                .map(without_nulls)
+                .collect(),
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .filter(|(_, value)| !value.is_null())
+                    .map(|(key, value)| (key, without_nulls(value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    #[test]
+    fn round_trips_records_through_toml() {
+        for value in [customers(), invoices()] {
+            assert_eq!(
+                round_trip(&value, &OutputFormat::Toml),
+                without_nulls(value)
+            );
+        }
+
+        assert_eq!(
+            round_trip(&records(), &OutputFormat::Toml),
+            json!({ "records": without_nulls(records()) })
+        );
+    }
+
+    #[test]
+    fn round_trips_records_through_json_and_yaml() {
+        let formats = [
+            OutputFormat::Json,
+            #[cfg(feature = "yaml")]
+            OutputFormat::Yaml,
+        ];
+
+        for format in &formats {
+            for value in [customers(), invoices(), records()] {
+                assert_eq!(round_trip(&value, format), value, "{format}");
+            }
+        }
+    }
+
+    #[test]
+    fn writes_scalars_and_top_level_nulls() {
+        assert_eq!(to_string(&json!(5), false).unwrap(), "value = 5\n");
+        assert_eq!(to_string(&Value::Null, false).unwrap(), "");
+    }
+
+    #[test]
+    fn keeps_elements_of_mixed_arrays_as_json_text() {
+        let value = json!({
+            "Scalars": [1, "a", null, true],
+            "Numbers": [1, 2.5],
+            "Records": [{ "Id": "1" }, 2],
+            "Nested": [[1, 2], ["a"]],
+        });
+
+        let document = to_string(&value, false).unwrap();
+        assert_eq!(
+            serde_json::to_value(toml::from_str::<toml::Value>(&document).unwrap()).unwrap(),
+            json!({
+                "Scalars": ["1", "\"a\"", "true"],
+                "Numbers": [1, 2.5],
+                "Records": ["{\"Id\":\"1\"}", "2"],
+                "Nested": [[1, 2], ["a"]],
+            })
+        );
+    }
+}
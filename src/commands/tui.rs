@@ -0,0 +1,481 @@
+//! Full-screen browser for customers, items, invoices and vendors.
+
+use super::{get_desired_array, tabular, to_output_path, CommandError};
+use crate::{
+    args::{OutputFormat, SelectArgs},
+    client::QboClient,
+    entity::Entity,
+    query::Query,
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs},
+    Frame, Terminal,
+};
+use serde_json::Value;
+
+use std::{
+    io::{self, Stdout},
+    panic,
+    path::PathBuf,
+};
+
+const ENTITIES: [Entity; 4] = [
+    Entity::Customer,
+    Entity::Item,
+    Entity::Invoice,
+    Entity::Vendor,
+];
+
+const HELP: &str =
+    "q quit  tab switch  / search  J/K scroll detail  f format  e export selected  E export all  r reload";
+
+/// cells wider than this are cut off
+const MAX_COLUMN_WIDTH: usize = 40;
+const PAGE: isize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuiArgs {
+    pub quiet: bool,
+}
+
+/// `client` is connected (and so authorized) before this, on the normal screen, so company info
+/// and errors stay visible
+pub fn browse<C>(client: &C, _args: &TuiArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    // leave the terminal usable if loading panics, eg. on an unexpected response
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = stop();
+        hook(info);
+    }));
+
+    let mut terminal = start()?;
+    let result = run(&mut terminal, App::new(), client);
+    stop()?;
+
+    result
+}
+
+fn start() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn stop() -> io::Result<()> {
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+fn run<C>(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    mut app: App,
+    client: &C,
+) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    loop {
+        if app.tab().records.is_none() {
+            app.status = format!("loading {}s...", app.tab().entity);
+            terminal.draw(|frame| draw(frame, &mut app))?;
+            app.load(client);
+        }
+
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+struct Tab {
+    entity: Entity,
+    /// `None` until the tab is first shown
+    records: Option<Vec<Value>>,
+    /// lower-cased JSON of every record, for searching
+    haystacks: Vec<String>,
+    /// indices of the records matching the search
+    visible: Vec<usize>,
+    state: TableState,
+}
+
+impl Tab {
+    fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            records: None,
+            haystacks: Vec::new(),
+            visible: Vec::new(),
+            state: TableState::default(),
+        }
+    }
+
+    fn filter(&mut self, search: &str) {
+        let search = search.to_lowercase();
+
+        self.visible = self
+            .haystacks
+            .iter()
+            .enumerate()
+            .filter(|(_, haystack)| haystack.contains(&search))
+            .map(|(index, _)| index)
+            .collect();
+        self.state.select(if self.visible.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn selected(&self) -> Option<&Value> {
+        let index = *self.visible.get(self.state.selected()?)?;
+        self.records.as_ref()?.get(index)
+    }
+
+    fn visible_records(&self) -> Vec<Value> {
+        let records = self.records.as_deref().unwrap_or_default();
+        self.visible
+            .iter()
+            .map(|&index| records[index].clone())
+            .collect()
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let Some(last) = self.visible.len().checked_sub(1) else {
+            return;
+        };
+
+        let current = self.state.selected().unwrap_or_default() as isize;
+        self.state
+            .select(Some((current + delta).clamp(0, last as isize) as usize));
+    }
+}
+
+enum Mode {
+    Browse,
+    Search,
+    Export { all: bool, path: String },
+}
+
+struct App {
+    tabs: Vec<Tab>,
+    current: usize,
+    mode: Mode,
+    search: String,
+    format: OutputFormat,
+    detail_scroll: u16,
+    status: String,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            tabs: ENTITIES.into_iter().map(Tab::new).collect(),
+            current: 0,
+            mode: Mode::Browse,
+            search: String::new(),
+            format: OutputFormat::default(),
+            detail_scroll: 0,
+            status: String::new(),
+        }
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.current]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.current]
+    }
+
+    fn load<C>(&mut self, client: &C)
+    where
+        C: QboClient + ?Sized,
+    {
+        let search = self.search.clone();
+        let tab = self.tab_mut();
+
+        let query = Query::from_entity(tab.entity).build();
+        let records = match query
+            .map_err(CommandError::from)
+            .and_then(|query| get_desired_array(client, &query))
+        {
+            Ok(records) => records,
+            Err(err) => {
+                // an empty list, so the tab is not loaded again until `r` is pressed
+                tab.records = Some(Vec::new());
+                tab.haystacks.clear();
+                tab.filter(&search);
+                self.status = format!("failed to load {}s: {err:?}", self.tab().entity);
+                return;
+            }
+        };
+
+        tab.haystacks = records
+            .iter()
+            .map(|record| record.to_string().to_lowercase())
+            .collect();
+        tab.records = Some(records);
+        tab.filter(&search);
+
+        self.status.clear();
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        self.current = index % self.tabs.len();
+        self.detail_scroll = 0;
+
+        let search = self.search.clone();
+        self.tab_mut().filter(&search);
+    }
+
+    /// handles a key press, returning false to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        match &mut self.mode {
+            Mode::Browse => return self.handle_browse_key(key),
+            Mode::Search => match key.code {
+                KeyCode::Char(c) => self.search.push(c),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.mode = Mode::Browse;
+                }
+                _ => return true,
+            },
+            Mode::Export { all, path } => match key.code {
+                KeyCode::Char(c) => path.push(c),
+                KeyCode::Backspace => {
+                    path.pop();
+                }
+                KeyCode::Enter => {
+                    let (all, path) = (*all, PathBuf::from(path.as_str()));
+                    self.mode = Mode::Browse;
+                    self.export(all, path);
+                    return true;
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                _ => {}
+            },
+        }
+
+        if matches!(self.mode, Mode::Search | Mode::Browse) {
+            let search = self.search.clone();
+            self.tab_mut().filter(&search);
+            self.detail_scroll = 0;
+        }
+
+        true
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent) -> bool {
+        self.status.clear();
+
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc if self.search.is_empty() => return false,
+            KeyCode::Esc => {
+                self.search.clear();
+                let search = self.search.clone();
+                self.tab_mut().filter(&search);
+            }
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => self.switch_to(self.current + 1),
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                self.switch_to(self.current + self.tabs.len() - 1)
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < self.tabs.len() {
+                    self.switch_to(index);
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(PAGE),
+            KeyCode::PageUp => self.move_by(-PAGE),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX / 2),
+            KeyCode::Char('J') => self.detail_scroll = self.detail_scroll.saturating_add(1),
+            KeyCode::Char('K') => self.detail_scroll = self.detail_scroll.saturating_sub(1),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('f') => {
+                let formats = OutputFormat::ALL;
+                let index = formats.iter().position(|f| *f == self.format);
+                self.format = formats[index.map_or(0, |index| (index + 1) % formats.len())].clone();
+                self.status = format!("exporting as {}", self.format.as_str());
+            }
+            KeyCode::Char('e') => match self.tab().selected() {
+                Some(record) => {
+                    let path = format!(
+                        "{}-{}.{}",
+                        self.tab().entity,
+                        record["Id"].as_str().unwrap_or("unknown"),
+                        self.format.as_str()
+                    );
+                    self.mode = Mode::Export { all: false, path };
+                }
+                None => self.status = "nothing selected".to_string(),
+            },
+            KeyCode::Char('E') => {
+                let path = format!(
+                    "{}s.{}",
+                    self.tab().entity.as_str().to_lowercase(),
+                    self.format.as_str()
+                );
+                self.mode = Mode::Export { all: true, path };
+            }
+            KeyCode::Char('r') => self.tab_mut().records = None,
+            _ => {}
+        }
+
+        true
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        self.tab_mut().move_by(delta);
+        self.detail_scroll = 0;
+    }
+
+    /// writes the selected record, or every record matching the search if `all`, to `path`
+    fn export(&mut self, all: bool, path: PathBuf) {
+        let tab = self.tab();
+        let value = if all {
+            Value::Array(tab.visible_records())
+        } else {
+            match tab.selected() {
+                Some(record) => record.clone(),
+                None => return,
+            }
+        };
+
+        self.status = match to_output_path(
+            &value,
+            &Some(path.clone()),
+            &self.format,
+            true,
+            &SelectArgs::default(),
+        ) {
+            Ok(()) => format!("wrote {}", path.display()),
+            Err(err) => format!("failed to write {}: {err:?}", path.display()),
+        };
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [tabs_area, main_area, status_area] = *Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(frame.size())
+    else {
+        unreachable!("three constraints give three areas");
+    };
+    let [table_area, detail_area] = *Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(main_area)
+    else {
+        unreachable!("two constraints give two areas");
+    };
+
+    let titles: Vec<String> = app
+        .tabs
+        .iter()
+        .enumerate()
+        .map(|(index, tab)| format!("{} {}s", index + 1, tab.entity))
+        .collect();
+    frame.render_widget(
+        Tabs::new(titles)
+            .block(Block::default().borders(Borders::ALL).title("qbtools"))
+            .select(app.current)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        tabs_area,
+    );
+
+    let tab = &mut app.tabs[app.current];
+    let columns = tab.entity.default_columns();
+    let records = tab.records.as_deref().unwrap_or_default();
+
+    let rows: Vec<Vec<String>> = tab
+        .visible
+        .iter()
+        .map(|&index| {
+            columns
+                .iter()
+                .map(|column| tabular::cell_to_string(tabular::lookup(&records[index], column)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<Constraint> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let width = rows
+                .iter()
+                .map(|row| row[index].chars().count())
+                .chain([column.len()])
+                .max()
+                .unwrap_or_default()
+                .min(MAX_COLUMN_WIDTH);
+            Constraint::Length(width as u16)
+        })
+        .collect();
+
+    let mut title = format!("{} of {}", tab.visible.len(), records.len());
+    if !app.search.is_empty() {
+        title = format!("{title} matching {:?}", app.search);
+    }
+
+    let table = Table::new(rows.into_iter().map(Row::new), widths)
+        .header(Row::new(columns.to_vec()).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, table_area, &mut tab.state);
+
+    let detail = tab
+        .selected()
+        .and_then(|record| serde_json::to_string_pretty(record).ok())
+        .unwrap_or_default();
+    frame.render_widget(
+        Paragraph::new(detail)
+            .block(Block::default().borders(Borders::ALL).title("detail"))
+            .scroll((app.detail_scroll, 0)),
+        detail_area,
+    );
+
+    let status = match &app.mode {
+        Mode::Search => format!("/{}", app.search),
+        Mode::Export { all, path } => format!(
+            "export {} as {} to: {path}",
+            if *all { "all" } else { "selected" },
+            app.format.as_str()
+        ),
+        Mode::Browse if !app.status.is_empty() => app.status.clone(),
+        Mode::Browse => format!("{HELP}  [{}]", app.format.as_str()),
+    };
+    frame.render_widget(Paragraph::new(status), status_area);
+}
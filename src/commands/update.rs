@@ -0,0 +1,174 @@
+use super::{to_output, CommandError};
+use crate::{
+    args::{OutputArgs, SetField},
+    client::QboClient,
+    entity::Entity,
+    fs,
+};
+
+use serde_json::{Map, Value};
+
+use std::path::PathBuf;
+
+/// how many times `--retry-on-conflict` re-reads the entity before giving up
+const MAX_CONFLICT_RETRIES: usize = 3;
+
+/// fields that are filled in from the current entity and must not be patched
+const RESERVED_FIELDS: [&str; 3] = ["Id", "SyncToken", "sparse"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateArgs {
+    pub output: OutputArgs,
+    pub quiet: bool,
+    pub dry_run: bool,
+
+    pub entity: Entity,
+    pub id: String,
+    pub set: Vec<SetField>,
+    pub patch: Option<PathBuf>,
+    pub retry_on_conflict: bool,
+}
+
+/// reads the entity's current `SyncToken`, sends a sparse update and prints the updated entity
+pub fn sparse<C>(client: &C, args: &UpdateArgs) -> Result<(), CommandError>
+where
+    C: QboClient + ?Sized,
+{
+    let patch = build_patch(args)?;
+
+    let mut retries = 0;
+    let updated = loop {
+        let current = client.read(args.entity.as_str(), &args.id)?;
+        let body = sparse_update_body(&current, &patch);
+
+        match client.update(args.entity.as_str(), body) {
+            Ok(updated) => break updated,
+            Err(err)
+                if err.is_stale_object()
+                    && args.retry_on_conflict
+                    && retries < MAX_CONFLICT_RETRIES =>
+            {
+                retries += 1;
+                log::warn!(
+                    "{} {} was modified since it was read, retrying ({retries}/{MAX_CONFLICT_RETRIES})",
+                    args.entity,
+                    args.id
+                );
+            }
+            Err(err) => {
+                if err.is_stale_object() && !args.retry_on_conflict {
+                    log::error!(
+                        "{} {} was modified since it was read; use --retry-on-conflict to re-read it and reapply the update",
+                        args.entity,
+                        args.id
+                    );
+                }
+                return Err(err.into());
+            }
+        }
+    };
+
+    match updated {
+        Some(updated) => Ok(to_output(&updated, &args.output)?),
+        // dry run, the planned request is printed instead
+        None => Ok(()),
+    }
+}
+
+/// merges `--patch` and `--set` (in that order) into a single object
+fn build_patch(args: &UpdateArgs) -> Result<Map<String, Value>, CommandError> {
+    let mut patch = match &args.patch {
+        Some(path) => match fs::read_data_file(path).map_err(CommandError::ReadInput)? {
+            Value::Object(patch) => patch,
+            _ => {
+                return Err(CommandError::InvalidInput(format!(
+                    "{} does not contain an object",
+                    path.display()
+                )))
+            }
+        },
+        None => Map::new(),
+    };
+
+    for field in &args.set {
+        set_path(&mut patch, &field.path, field.value.clone());
+    }
+
+    if let Some(field) = RESERVED_FIELDS
+        .iter()
+        .find(|field| patch.contains_key(**field))
+    {
+        return Err(CommandError::InvalidInput(format!(
+            "`{field}` is taken from the current entity and cannot be set"
+        )));
+    }
+
+    if patch.is_empty() {
+        return Err(CommandError::InvalidInput("nothing to update".to_string()));
+    }
+
+    Ok(patch)
+}
+
+fn set_path(object: &mut Map<String, Value>, path: &[String], value: Value) {
+    match path {
+        [] => {}
+        [field] => {
+            object.insert(field.clone(), value);
+        }
+        [field, rest @ ..] => {
+            let child = object
+                .entry(field.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+
+            if let Value::Object(child) = child {
+                set_path(child, rest, value);
+            }
+        }
+    }
+}
+
+/// Builds the body of a sparse update: `Id` and `SyncToken` of `current` plus the patched fields.
+///
+/// Patched objects are merged into the current value of the field, so setting
+/// `PrimaryEmailAddr.Address` does not drop the rest of `PrimaryEmailAddr`.
+fn sparse_update_body(current: &Value, patch: &Map<String, Value>) -> Value {
+    let mut body = Map::new();
+
+    for field in ["Id", "SyncToken"] {
+        body.insert(field.to_string(), current[field].clone());
+    }
+    body.insert("sparse".to_string(), Value::Bool(true));
+
+    for (field, value) in patch {
+        let value = match current.get(field) {
+            Some(current) => merge(current.clone(), value),
+            None => value.clone(),
+        };
+
+        body.insert(field.clone(), value);
+    }
+
+    Value::Object(body)
+}
+
+fn merge(mut current: Value, patch: &Value) -> Value {
+    match (&mut current, patch) {
+        (Value::Object(current), Value::Object(patch)) => {
+            for (field, value) in patch {
+                let merged = match current.remove(field) {
+                    Some(existing) => merge(existing, value),
+                    None => value.clone(),
+                };
+                current.insert(field.clone(), merged);
+            }
+        }
+        _ => return patch.clone(),
+    }
+
+    current
+}
@@ -0,0 +1,382 @@
+//! Webhook notifications from QuickBooks, for reacting to changes as they happen.
+//!
+//! QuickBooks POSTs `eventNotifications` to the webhook URL of the app, signing each payload with
+//! its verifier token: the `intuit-signature` header is the base64 HMAC-SHA256 of the body.
+//! Payloads with any other signature are rejected. For the rest, every changed entity is read from
+//! QuickBooks and forwarded as a line of JSON, eg.
+//!
+//! ```json
+//! { "realmId": "1234", "name": "Customer", "id": "58", "operation": "Update", "lastUpdated": "...", "entity": { ... } }
+//! ```
+//!
+//! without `entity` for deleted entities. Notifications are answered straight away and forwarded
+//! in the order they arrived, since QuickBooks gives up on a webhook that takes too long.
+
+use super::CommandError;
+use crate::{
+    api::{self, Client},
+    client::{self, MirrorClient, QboClient},
+    config, fs,
+    local_server::{self, Request, Response},
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+
+use std::{
+    io::{self, Write},
+    net::TcpListener,
+    path::PathBuf,
+    process::{self, Stdio},
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServeArgs {
+    pub quiet: bool,
+
+    pub bind: String,
+    pub port: u16,
+    pub verifier_token: String,
+    pub exec: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SendArgs {
+    pub url: String,
+    pub verifier_token: String,
+    pub payload: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Payload {
+    #[serde(default)]
+    event_notifications: Vec<Notification>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    realm_id: String,
+    #[serde(default)]
+    data_change_event: DataChangeEvent,
+}
+
+#[derive(Default, Deserialize)]
+struct DataChangeEvent {
+    /// eg. `{ "name": "Customer", "id": "58", "operation": "Merge", "deletedId": "61", ... }`
+    #[serde(default)]
+    entities: Vec<Map<String, Value>>,
+}
+
+/// a change to a single entity of a company
+struct Event {
+    realm_id: String,
+    change: Map<String, Value>,
+}
+
+/// where changed entities go
+enum Sink {
+    Stdout,
+    /// a shell command run for every entity, with the line of JSON on its stdin
+    Exec(String),
+    /// the `--mirror`, which is kept up to date instead of printing anything
+    Mirror(MirrorClient),
+}
+
+/// serves on `args.port` until the process is stopped, forwarding the entities that changed
+pub fn serve(args: &ServeArgs) -> Result<(), CommandError> {
+    let sink = match (&args.exec, client::mirror()) {
+        (Some(_), Some(_)) => {
+            return Err(CommandError::InvalidInput(
+                "--exec and --mirror cannot be used together".to_string(),
+            ))
+        }
+        (Some(command), None) => Sink::Exec(command.clone()),
+        (None, Some(directory)) => Sink::Mirror(MirrorClient::open(directory, false)?),
+        (None, None) => Sink::Stdout,
+    };
+
+    // entities are read from QuickBooks even with `--mirror`, which they are written to
+    let client = Client::connect(config::profile().as_deref(), false)?;
+
+    let listener = TcpListener::bind((args.bind.as_str(), args.port))?;
+    if !args.quiet {
+        eprintln!(
+            "listening for webhook notifications on http://{}",
+            listener.local_addr()?
+        );
+    }
+
+    let (events, received) = mpsc::channel();
+    let verifier_token = args.verifier_token.clone();
+    thread::spawn(move || {
+        local_server::serve(listener, "webhook", |request| {
+            receive(&verifier_token, request, &events)
+        })
+    });
+
+    for event in received {
+        if let Err(err) = forward(&client, &sink, event) {
+            log::error!("webhook: {err:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// POSTs `args.payload` to `args.url`, signed like QuickBooks signs it, eg. to try out `serve`
+pub fn send(args: &SendArgs) -> Result<(), CommandError> {
+    let payload =
+        std::fs::read(&args.payload).map_err(|err| CommandError::ReadInput(fs::Error::IO(err)))?;
+
+    ureq::post(&args.url)
+        .set("Content-Type", "application/json")
+        .set("intuit-signature", &sign(&args.verifier_token, &payload))
+        .send_bytes(&payload)
+        .map_err(api::Error::from_ureq)?;
+
+    Ok(())
+}
+
+/// the `intuit-signature` of `payload`
+fn sign(verifier_token: &str, payload: &[u8]) -> String {
+    let mut mac = hmac(verifier_token);
+    mac.update(payload);
+    BASE64_STANDARD.encode(mac.finalize().into_bytes())
+}
+
+fn verify(verifier_token: &str, payload: &[u8], signature: &str) -> bool {
+    let Ok(signature) = BASE64_STANDARD.decode(signature.trim()) else {
+        return false;
+    };
+
+    let mut mac = hmac(verifier_token);
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn hmac(verifier_token: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(verifier_token.as_bytes()).expect("HMAC to take keys of any size")
+}
+
+/// checks the signature of a notification and passes its changes on to be forwarded
+fn receive(verifier_token: &str, request: &Request, events: &Sender<Event>) -> Response {
+    if request.method != "POST" {
+        return Response::json(404, &json!({ "error": "notifications are POSTed" }));
+    }
+
+    let signature = request.header("intuit-signature").unwrap_or_default();
+    if !verify(verifier_token, &request.body, signature) {
+        log::warn!("webhook: rejected a notification with a wrong intuit-signature");
+        return Response::json(401, &json!({ "error": "wrong intuit-signature" }));
+    }
+
+    let payload: Payload = match serde_json::from_slice(&request.body) {
+        Ok(payload) => payload,
+        Err(err) => return Response::json(400, &json!({ "error": err.to_string() })),
+    };
+
+    for notification in payload.event_notifications {
+        for change in notification.data_change_event.entities {
+            let event = Event {
+                realm_id: notification.realm_id.clone(),
+                change,
+            };
+            // the receiver only stops when the process does
+            let _ = events.send(event);
+        }
+    }
+
+    Response::json(200, &json!({}))
+}
+
+/// reads the entity that changed and sends it to `sink`
+fn forward(client: &Client, sink: &Sink, event: Event) -> Result<(), CommandError> {
+    let field = |name: &str| {
+        event
+            .change
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let (name, id, operation) = (field("name"), field("id"), field("operation"));
+
+    // the client can only read entities of its own company, and would read the wrong one
+    if event.realm_id != client.company_id() {
+        log::warn!(
+            "webhook: ignoring {operation} of {name} {id} in company {}",
+            event.realm_id
+        );
+        return Ok(());
+    }
+
+    let entity = match operation.as_str() {
+        "Delete" => None,
+        _ => Some(client.read(&name, &id)?),
+    };
+
+    if let Sink::Mirror(mirror) = sink {
+        // a merge leaves one entity of the two
+        if let Some(merged) = event.change.get("deletedId").and_then(Value::as_str) {
+            mirror.remove(&name, merged)?;
+        }
+
+        match entity {
+            Some(entity) => mirror.put(&name, entity)?,
+            None => mirror.remove(&name, &id)?,
+        }
+        return Ok(());
+    }
+
+    let mut line = Map::new();
+    line.insert("realmId".to_string(), event.realm_id.into());
+    line.extend(event.change);
+    if let Some(entity) = entity {
+        line.insert("entity".to_string(), entity);
+    }
+    let line = Value::Object(line).to_string();
+
+    match sink {
+        Sink::Exec(command) => run_hook(command, &line),
+        _ => {
+            let mut stdout = io::stdout().lock();
+            writeln!(stdout, "{line}")?;
+            Ok(stdout.flush()?)
+        }
+    }
+}
+
+/// runs `command` with the shell, with `line` on its stdin
+fn run_hook(command: &str, line: &str) -> Result<(), CommandError> {
+    #[cfg(windows)]
+    let mut hook = process::Command::new("cmd");
+    #[cfg(windows)]
+    hook.args(["/C", command]);
+    #[cfg(not(windows))]
+    let mut hook = process::Command::new("sh");
+    #[cfg(not(windows))]
+    hook.args(["-c", command]);
+
+    let mut child = hook.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{line}")?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        log::warn!("webhook: `{command}` exited with {status}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERIFIER_TOKEN: &str = "verifier-token";
+
+    fn payload() -> Vec<u8> {
+        json!({ "eventNotifications": [{
+            "realmId": "1234",
+            "dataChangeEvent": { "entities": [
+                { "name": "Customer", "id": "58", "operation": "Update" },
+                { "name": "Invoice", "id": "130", "operation": "Delete" },
+            ] },
+        }] })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn notification(body: Vec<u8>, signature: &str) -> Request {
+        Request {
+            method: "POST".to_string(),
+            path: "/".to_string(),
+            query: Vec::new(),
+            headers: vec![("intuit-signature".to_string(), signature.to_string())],
+            body,
+        }
+    }
+
+    #[test]
+    fn verifies_its_own_signature() {
+        let payload = payload();
+        let signature = sign(VERIFIER_TOKEN, &payload);
+
+        assert!(verify(VERIFIER_TOKEN, &payload, &signature));
+        assert!(verify(VERIFIER_TOKEN, &payload, &format!(" {signature}\n")));
+        assert!(!verify("another-token", &payload, &signature));
+    }
+
+    #[test]
+    fn rejects_a_tampered_payload() {
+        let signature = sign(VERIFIER_TOKEN, &payload());
+        let tampered = String::from_utf8(payload()).unwrap().replace("58", "59");
+
+        assert!(!verify(VERIFIER_TOKEN, tampered.as_bytes(), &signature));
+    }
+
+    #[test]
+    fn rejects_a_signature_that_is_not_base64() {
+        assert!(!verify(VERIFIER_TOKEN, &payload(), "not base64!"));
+        assert!(!verify(VERIFIER_TOKEN, &payload(), ""));
+    }
+
+    #[test]
+    fn receives_every_change_of_a_signed_notification() {
+        let (events, received) = mpsc::channel();
+        let request = notification(payload(), &sign(VERIFIER_TOKEN, &payload()));
+
+        let response = receive(VERIFIER_TOKEN, &request, &events);
+        assert_eq!(response.status, 200);
+
+        let changes: Vec<(String, Value)> = received
+            .try_iter()
+            .map(|event| (event.realm_id, event.change["id"].clone()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("1234".to_string(), json!("58")),
+                ("1234".to_string(), json!("130")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_a_notification_with_a_wrong_signature() {
+        let (events, received) = mpsc::channel();
+        let request = notification(payload(), &sign("another-token", &payload()));
+
+        assert_eq!(receive(VERIFIER_TOKEN, &request, &events).status, 401);
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn rejects_a_signed_body_that_is_not_a_notification() {
+        let (events, received) = mpsc::channel();
+        let body = b"not json".to_vec();
+        let request = notification(body.clone(), &sign(VERIFIER_TOKEN, &body));
+
+        assert_eq!(receive(VERIFIER_TOKEN, &request, &events).status, 400);
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn only_takes_posts() {
+        let (events, _received) = mpsc::channel();
+        let mut request = notification(payload(), &sign(VERIFIER_TOKEN, &payload()));
+        request.method = "GET".to_string();
+
+        assert_eq!(receive(VERIFIER_TOKEN, &request, &events).status, 404);
+    }
+}
@@ -0,0 +1,244 @@
+//! Excel workbooks, with a sheet per entity (or report section) and typed cells.
+
+use super::{is_sections, tabular::Table};
+use crate::query::{is_date, is_date_time};
+
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet, XlsxError};
+use serde_json::Value;
+
+/// Excel's limits on sheet names and cell text
+const MAX_SHEET_NAME_LEN: usize = 31;
+const MAX_STRING_LEN: usize = 32_767;
+
+/// the most significant digits Excel keeps in a number
+const MAX_NUMBER_DIGITS: usize = 15;
+
+/// Writes `value` as a workbook.
+///
+/// An object whose values are all arrays, like `{ "Customer": [...], "Item": [...] }`, becomes a
+/// sheet per key; anything else becomes a single sheet named `sheet_name`.
+pub(crate) fn to_workbook(value: &Value, sheet_name: &str) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let mut names: Vec<String> = Vec::new();
+
+    let sheets: Vec<(&str, &Value)> = match value {
+        Value::Object(object) if is_sections(object) => object
+            .iter()
+            .map(|(name, records)| (name.as_str(), records))
+            .collect(),
+        value => vec![(sheet_name, value)],
+    };
+
+    for (name, records) in sheets {
+        let name = unique_sheet_name(name, &names);
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&name)?;
+        write_sheet(worksheet, &Table::from_value(records))?;
+
+        names.push(name);
+    }
+
+    workbook.save_to_buffer()
+}
+
+fn write_sheet(worksheet: &mut Worksheet, table: &Table) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let date_time = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+    for (col, column) in (0..).zip(&table.columns) {
+        worksheet.write_string_with_format(0, col, column, &header)?;
+    }
+
+    for (row, cells) in (1..).zip(&table.rows) {
+        for (col, cell) in (0..).zip(cells) {
+            match cell {
+                Value::Null => {}
+                Value::Bool(bool) => {
+                    worksheet.write_boolean(row, col, *bool)?;
+                }
+                Value::Number(number) => {
+                    worksheet.write_number(row, col, number.as_f64().unwrap_or_default())?;
+                }
+                Value::String(string) if is_date(string) || is_date_time(string) => {
+                    let format = if is_date(string) { &date } else { &date_time };
+                    match excel_date_time(string) {
+                        Some(datetime) => {
+                            worksheet.write_datetime_with_format(row, col, &datetime, format)?
+                        }
+                        // a date Excel cannot hold, eg. `0000-00-00` or one before 1900
+                        None => worksheet.write_string(row, col, string)?,
+                    };
+                }
+                Value::String(string) if is_number(string) => {
+                    // amounts in reports are strings
+                    worksheet.write_number(row, col, string.parse::<f64>().unwrap_or_default())?;
+                }
+                Value::String(string) => {
+                    worksheet.write_string(row, col, truncate(string))?;
+                }
+                cell => {
+                    worksheet.write_string(row, col, truncate(&cell.to_string()))?;
+                }
+            }
+        }
+    }
+
+    if !table.columns.is_empty() {
+        let last_row = table.rows.len() as u32;
+        let last_col = table.columns.len() as u16 - 1;
+
+        worksheet.autofilter(0, 0, last_row, last_col)?;
+        worksheet.set_freeze_panes(1, 0)?;
+    }
+    worksheet.autofit();
+
+    Ok(())
+}
+
+/// A `YYYY-MM-DD` date, or one followed by `THH:MM:SS` (see [`is_date_time`]), as Excel holds it;
+/// the offset is dropped, leaving the time as QBO wrote it.
+///
+/// None for a date or time that does not exist, or a date before 1900, where Excel's dates begin.
+/// (`ExcelDateTime::parse_from_str` panics on a date time with an invalid date, so the parts are
+/// read here.)
+fn excel_date_time(string: &str) -> Option<ExcelDateTime> {
+    fn part<T: std::str::FromStr>(string: &str, range: std::ops::Range<usize>) -> Option<T> {
+        string.get(range)?.parse().ok()
+    }
+
+    let year = part(string, 0..4).filter(|year| *year >= 1900)?;
+    let date = ExcelDateTime::from_ymd(year, part(string, 5..7)?, part(string, 8..10)?).ok()?;
+    if string.len() == 10 {
+        return Some(date);
+    }
+
+    let hour = part(string, 11..13).filter(|hour| *hour < 24)?;
+    let minute = part(string, 14..16).filter(|minute| *minute < 60)?;
+    let second: u8 = part(string, 17..19).filter(|second| *second < 60)?;
+    date.and_hms(hour, minute, second).ok()
+}
+
+/// a decimal like `-1234.50`, which is how QBO writes amounts in reports; whole numbers (ids, phone
+/// numbers) and anything with a leading zero or too many digits to survive as a number are text
+fn is_number(string: &str) -> bool {
+    let unsigned = string.strip_prefix('-').unwrap_or(string);
+    let Some((integer, fraction)) = unsigned.split_once('.') else {
+        return false;
+    };
+
+    !integer.is_empty()
+        && !fraction.is_empty()
+        && integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+        && (integer == "0" || !integer.starts_with('0'))
+        && integer.len() + fraction.len() <= MAX_NUMBER_DIGITS
+}
+
+fn truncate(string: &str) -> &str {
+    match string.char_indices().nth(MAX_STRING_LEN) {
+        Some((index, _)) => &string[..index],
+        None => string,
+    }
+}
+
+/// `name` without the characters Excel does not allow, cut to 31 characters and made unique
+fn unique_sheet_name(name: &str, taken: &[String]) -> String {
+    let mut base: String = name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(MAX_SHEET_NAME_LEN)
+        .collect();
+    base = base.trim_matches('\'').trim().to_string();
+    if base.is_empty() {
+        base = "Sheet".to_string();
+    }
+
+    let mut unique = base.clone();
+    let mut suffix = 2;
+    while taken
+        .iter()
+        .any(|taken| taken.eq_ignore_ascii_case(&unique))
+    {
+        let suffix_text = format!(" ({suffix})");
+        let kept: String = base
+            .chars()
+            .take(MAX_SHEET_NAME_LEN - suffix_text.len())
+            .collect();
+        unique = format!("{kept}{suffix_text}");
+        suffix += 1;
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn only_decimals_are_numbers() {
+        for number in ["0.5", "-1234.50", "10.00"] {
+            assert!(is_number(number), "{number}");
+        }
+        for text in [
+            "",
+            "42",
+            "007.5",
+            ".5",
+            "5.",
+            "-",
+            "1.2.3",
+            "1e5",
+            "1234567890.1234567",
+        ] {
+            assert!(!is_number(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn sheet_names_are_valid_and_unique() {
+        let taken = vec!["Customer".to_string(), "Customer (2)".to_string()];
+
+        assert_eq!(unique_sheet_name("Item", &taken), "Item");
+        assert_eq!(unique_sheet_name("customer", &taken), "customer (3)");
+        assert_eq!(unique_sheet_name("A/B: [C]?", &taken), "AB C");
+        assert_eq!(unique_sheet_name("'*'", &taken), "Sheet");
+
+        let long = "Profit and Loss by Customer and Month";
+        let name = unique_sheet_name(long, &[long[..31].to_string()]);
+        assert_eq!(name, "Profit and Loss by Customer (2)");
+        assert_eq!(name.len(), MAX_SHEET_NAME_LEN);
+    }
+
+    #[test]
+    fn writes_dates_excel_cannot_hold_as_text() {
+        let records = json!([
+            { "TxnDate": "2026-01-31", "MetaData": { "CreateTime": "2026-01-31T09:30:00-08:00" } },
+            { "TxnDate": "2026-13-40", "MetaData": { "CreateTime": "0000-00-00T00:00:00Z" } },
+            { "TxnDate": "1899-12-31", "MetaData": { "CreateTime": "0000-00-00" } },
+        ]);
+
+        assert!(to_workbook(&records, "Invoice").is_ok());
+    }
+
+    #[test]
+    fn reads_dates_and_times_excel_can_hold() {
+        assert!(excel_date_time("2026-01-31").is_some());
+        assert!(excel_date_time("2026-01-31T09:30:00-08:00").is_some());
+        assert!(excel_date_time("1900-01-01T00:00:00Z").is_some());
+
+        for invalid in [
+            "2026-13-40",
+            "0000-00-00",
+            "1899-12-31",
+            "2026-02-01T09:61:00Z",
+        ] {
+            assert!(excel_date_time(invalid).is_none(), "{invalid}");
+        }
+    }
+}
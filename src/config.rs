@@ -1,4 +1,9 @@
-use std::{io, path::Path};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -12,6 +17,15 @@ use core::result::Result;
 pub const CLIENT_ID: &str = "ABZYrDWkeAGWlwcMlNm9vvHjC0nTT4wXrwfjnuujJgBCr0sRJR";
 pub const CLIENT_SECRET: &str = "TbFFiO2pdnHXnvAZkveZ8GXMQMxeHbhpggUDNmGe";
 
+/// access tokens are valid for an hour; authorize again a bit before that
+const AUTHORIZATION_LIFETIME: Duration = Duration::from_secs(50 * 60);
+
+static PROFILE: Mutex<Option<String>> = Mutex::new(None);
+/// the last config returned by `get_authorized_config`, and when it was authorized
+static AUTHORIZED: Mutex<Option<(Instant, Config)>> = Mutex::new(None);
+/// where requests go instead of the configured `base_url`, while recording or replaying
+static REDIRECT: Mutex<Option<String>> = Mutex::new(None);
+
 mod dummy {
     pub const COMPANY_ID: &str = "0000000000000000000";
     pub const ACCESS_TOKEN: &str = "XX00000000000Xxx0XXXXX0xxxxXXX0X0XXXxXxXxXXXxXxXxx";
@@ -24,6 +38,49 @@ pub struct Config {
     pub company_id: String,
     #[serde(flatten)]
     pub token: Option<AccessToken>,
+    /// sends requests somewhere other than QuickBooks, eg. to `qbtools mock-server`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
+/// Why a config could not be read or authorized; the command line exits with an example config
+/// instead.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// none of the files the config is looked for in exist, eg. `qb-api-cfg.{json,toml,yaml}`
+    NotFound(String),
+    /// the config file could not be read or parsed
+    Invalid(String),
+    /// the config has no access token, or no refresh token to replace an expired one with
+    NoToken,
+    /// QuickBooks rejected the access token, and refreshing it failed
+    Refresh(String),
+    /// the config (with a refreshed access token) could not be written to its file
+    Write(io::Error),
+    /// checking the access token against QuickBooks failed
+    Api(api::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(files) => write!(f, "no config file found at {files}"),
+            Self::Invalid(error) => write!(f, "invalid config file: {error}"),
+            Self::NoToken => f.write_str("the config has no access token or refresh token"),
+            Self::Refresh(error) => write!(f, "failed to refresh the access token: {error}"),
+            Self::Write(error) => write!(f, "failed to write the config: {error}"),
+            Self::Api(error) => write!(f, "failed to check the access token: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<api::Error> for ConfigError {
+    fn from(error: api::Error) -> Self {
+        Self::Api(error)
+    }
 }
 
 impl From<QuickbooksConfig> for Config {
@@ -31,6 +88,7 @@ impl From<QuickbooksConfig> for Config {
         Self {
             company_id: config.company_id,
             token: Some(config.token),
+            base_url: Some(config.base_url),
         }
     }
 }
@@ -47,16 +105,43 @@ impl Config {
         Config {
             company_id: dummy::COMPANY_ID.to_string(),
             token: Some(token),
+            base_url: None,
         }
     }
 
+    /// where requests are sent: a redirect (see [`redirect`]), the configured `base_url`, or
+    /// QuickBooks itself
+    pub fn base_url(&self) -> String {
+        redirected().unwrap_or_else(|| self.upstream_url().to_string())
+    }
+
+    /// the configured `base_url`, or QuickBooks itself, ignoring any redirect
+    pub fn upstream_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(QB_BASE_URL)
+    }
+
+    /// Reads the config file of `profile` (`qb-api-cfg-<profile>.*`, or `qb-api-cfg.*` if None).
+    ///
+    /// Nothing is written and the process is not exited if it is missing, unlike when the command
+    /// line reads it.
+    pub fn read(profile: Option<&str>) -> Result<Self, ConfigError> {
+        let base_path = profile_base_path(profile);
+
+        fs::read_config(Path::new(&base_path)).map_err(|error| match error {
+            fs::Error::IO(error) if error.kind() == io::ErrorKind::NotFound => {
+                ConfigError::NotFound(fs::get_possible_files(&base_path))
+            }
+            error => ConfigError::Invalid(error.to_string()),
+        })
+    }
+
     pub fn get_example_json() -> Result<String, serde_json::Error> {
         let example = Self::get_dummy_config();
         serde_json::to_string_pretty(&example)
     }
 
     /// read config file, or write example config and exit the program
-    pub fn read_or_write_and_exit(base_path: &str) -> Self {
+    pub(crate) fn read_or_write_and_exit(base_path: &str) -> Self {
         /// this should always exit
         fn fail(config: Result<Config, fs::Error>, base_path: &str) -> ! {
             let error = config.expect_err("programming error");
@@ -107,59 +192,137 @@ impl Config {
         }
     }
 
-    pub fn write_to(&self, base_path: &Path) -> Result<(), io::Error> {
+    /// writes the config to the file it was read from (see [`Config::read`]), or `<base_path>.json`
+    pub fn write_to(&self, base_path: &Path) -> Result<(), ConfigError> {
+        let (file, out) = self.serialize_for(base_path)?;
+        std::fs::write(file, out).map_err(ConfigError::Write)
+    }
+
+    /// the file [`Config::write_to`] writes to, and what it writes there
+    pub(crate) fn serialize_for(&self, base_path: &Path) -> Result<(PathBuf, String), ConfigError> {
         let file = fs::get_first_file(base_path);
-        let file_type = fs::get_extension(&file);
+        let invalid =
+            |error: String| ConfigError::Write(io::Error::new(io::ErrorKind::InvalidData, error));
 
-        let out = match file_type.as_str() {
-            "json" => {
-                serde_json::to_string_pretty(self).expect("failed to serialize config to JSON")
-            }
+        let out = match fs::get_extension(&file).as_str() {
+            "json" => serde_json::to_string_pretty(self).map_err(|err| invalid(err.to_string()))?,
             #[cfg(feature = "toml")]
-            "toml" => toml::to_string_pretty(self).expect("failed to serialize config to TOML"),
+            "toml" => toml::to_string_pretty(self).map_err(|err| invalid(err.to_string()))?,
             #[cfg(feature = "yaml")]
             "yaml" | "yml" => {
-                serde_yaml::to_string(self).expect("failed to serialize config to YAML")
-            }
-            _ => {
-                panic!("this should not have happened");
+                serde_yaml::to_string(self).map_err(|err| invalid(err.to_string()))?
             }
+            extension => return Err(invalid(format!("cannot write a .{extension} config"))),
         };
 
-        std::fs::write(file, out)?;
+        Ok((file, out))
+    }
+}
+
+/// the access token of `config`, or exits with an example config if it has none
+pub(crate) fn token_or_exit(config: &Config) -> AccessToken {
+    config.token.clone().unwrap_or_else(|| {
+        log::error!("QuickBooks OAuth workflow is not implemented; `token` is currently required in the config.");
+        eprintln!("EXAMPLE CONFIG (.json):");
+        eprintln!("{}", Config::get_example_json().unwrap_or_else(|err| {
+            log::error!("failed to create example JSON config: {err}");
+            exit(-1);
+        }));
+        exit(1);
+    })
+}
+
+pub(crate) fn make_client(config: &Config) -> Quickbooks {
+    let token = token_or_exit(config);
 
-        Ok(())
+    let cfg = QuickbooksConfig {
+        client_id: config::CLIENT_ID.to_string(),
+        client_secret: config::CLIENT_SECRET.to_string(),
+
+        base_url: config.base_url(),
+        company_id: config.company_id.clone(),
+        token,
+        api: None,
+    };
+
+    Quickbooks::from(cfg)
+}
+
+/// selects the config file `qb-api-cfg-<profile>.*` instead of `qb-api-cfg.*`
+pub fn set_profile(profile: Option<&str>) {
+    *PROFILE.lock().expect("profile lock to not be poisoned") = profile.map(str::to_string);
+    forget_authorization();
+}
+
+pub fn profile() -> Option<String> {
+    PROFILE
+        .lock()
+        .expect("profile lock to not be poisoned")
+        .clone()
+}
+
+/// config file path (without extension) of the active profile
+pub fn base_path() -> String {
+    profile_base_path(profile().as_deref())
+}
+
+pub(crate) fn profile_base_path(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{BASE_CONFIG_PATH}-{profile}"),
+        None => BASE_CONFIG_PATH.to_string(),
     }
 }
 
-pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputError> {
-    use quickbooks_types::CompanyInfo;
+/// sends every request to `base_url` instead of the configured one, eg. to record it
+pub fn redirect(base_url: Option<String>) {
+    *REDIRECT.lock().expect("redirect lock to not be poisoned") = base_url;
+}
 
-    fn make_client(config: &Config) -> Quickbooks {
-        let token = config.token.clone().unwrap_or_else(|| {
-            log::error!("QuickBooks OAuth workflow is not implemented; `token` is currently required in the config.");
-            eprintln!("EXAMPLE CONFIG (.json):");
-            eprintln!("{}", Config::get_example_json().unwrap_or_else(|err| {
-                log::error!("failed to create example JSON config: {err}");
-                exit(-1);
-            }));
-            exit(1);
-        });
-
-        let cfg = QuickbooksConfig {
-            client_id: config::CLIENT_ID.to_string(),
-            client_secret: config::CLIENT_SECRET.to_string(),
-
-            base_url: QB_BASE_URL.to_string(),
-            company_id: config.company_id.clone(),
-            token,
-            api: None,
-        };
+fn redirected() -> Option<String> {
+    REDIRECT
+        .lock()
+        .expect("redirect lock to not be poisoned")
+        .clone()
+}
+
+/// makes the next `get_authorized_config` read the config and authorize again
+pub fn forget_authorization() {
+    *AUTHORIZED
+        .lock()
+        .expect("authorization lock to not be poisoned") = None;
+}
 
-        Quickbooks::from(cfg)
+/// Reads the config, refreshing (and writing back) the access token if QuickBooks rejects it.
+///
+/// The result is reused until the access token is close to expiring, so a session only has to
+/// authorize once.
+pub fn get_authorized_config(quiet: bool) -> Result<Config, commands::OutputError> {
+    if let Some((authorized_at, config)) = AUTHORIZED
+        .lock()
+        .expect("authorization lock to not be poisoned")
+        .as_ref()
+    {
+        if authorized_at.elapsed() < AUTHORIZATION_LIFETIME {
+            return Ok(config.clone());
+        }
     }
 
-    fn print_company_info(company_info: &CompanyInfo) {
+    let config = authorize(quiet)?;
+    *AUTHORIZED
+        .lock()
+        .expect("authorization lock to not be poisoned") = Some((Instant::now(), config.clone()));
+
+    Ok(config)
+}
+
+fn authorize(quiet: bool) -> Result<Config, commands::OutputError> {
+    fn print_company_info(response: &quickbooks_types::Response) {
+        // QBO answers with a single company, but an empty list is not worth failing over
+        let Some(company_info) = response.query_response.company_info.first() else {
+            log::warn!("QuickBooks did not return any company info");
+            return;
+        };
+
         log::info!("COMPANY INFO:");
         log::info!("Company name: {}", company_info.company_name);
         log::info!("Legal name:   {}", company_info.legal_name);
@@ -167,7 +330,8 @@ pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputErro
         log::info!("Legal address:   {}", company_info.legal_addr);
     }
 
-    let mut config = Config::read_or_write_and_exit(BASE_CONFIG_PATH);
+    let base_path = base_path();
+    let mut config = Config::read_or_write_and_exit(&base_path);
 
     let has_refresh = if let Some(token) = config.token.clone() {
         !token.refresh_token.is_empty()
@@ -229,9 +393,8 @@ pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputErro
                     exit(1);
                 }
             };
-            let company_info = &response.query_response.company_info[0];
             if !quiet {
-                print_company_info(company_info);
+                print_company_info(&response);
             }
         }
         Err(error) => {
@@ -245,8 +408,7 @@ pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputErro
                                 if token != config.token.clone().unwrap() {
                                     log::trace!("AccessToken changed, writing to config...");
                                     config.token = Some(token);
-                                    if let Err(error) = config.write_to(Path::new(BASE_CONFIG_PATH))
-                                    {
+                                    if let Err(error) = config.write_to(Path::new(&base_path)) {
                                         log::error!("failed to write config: {}", error);
                                         exit(1);
                                     }
@@ -256,7 +418,7 @@ pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputErro
                                 log::error!("failed to refresh access token: {}", err);
                                 eprintln!(
                                     "Try generating a new refresh token and placing it in {}",
-                                    fs::get_possible_files(BASE_CONFIG_PATH)
+                                    fs::get_possible_files(&base_path)
                                 );
                                 exit(1);
                             }
@@ -278,18 +440,27 @@ pub fn get_authorized_qb(quiet: bool) -> Result<Quickbooks, commands::OutputErro
                 }
             }
 
-            let response: quickbooks_types::Response = qb
+            let response = qb
                 .company_info()
-                .expect("this should not have happened")
-                .into_json()
-                .expect("this should not have happened");
+                .map_err(|err| err.to_string())
+                .and_then(|response| {
+                    response
+                        .into_json::<quickbooks_types::Response>()
+                        .map_err(|err| err.to_string())
+                });
+            let response = match response {
+                Ok(response) => response,
+                Err(err) => {
+                    log::error!("failed to retrieve company info with the refreshed token: {err}");
+                    exit(1);
+                }
+            };
 
             if !quiet {
-                let company_info = &response.query_response.company_info[0];
-                print_company_info(company_info);
+                print_company_info(&response);
             }
         }
     }
 
-    Ok(make_client(&config))
+    Ok(config)
 }
@@ -0,0 +1,542 @@
+use serde::{Deserialize, Serialize};
+
+use std::str::FromStr;
+
+/// QuickBooks Online entities, named as they are in queries and responses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String")]
+pub enum Entity {
+    Account,
+    Bill,
+    BillPayment,
+    Class,
+    CreditMemo,
+    Customer,
+    Department,
+    Deposit,
+    Employee,
+    Estimate,
+    Invoice,
+    Item,
+    JournalEntry,
+    Payment,
+    PaymentMethod,
+    Purchase,
+    PurchaseOrder,
+    RefundReceipt,
+    SalesReceipt,
+    TaxCode,
+    Term,
+    TimeActivity,
+    Transfer,
+    Vendor,
+    VendorCredit,
+}
+
+impl Entity {
+    pub const ALL: [Self; 25] = [
+        Self::Account,
+        Self::Bill,
+        Self::BillPayment,
+        Self::Class,
+        Self::CreditMemo,
+        Self::Customer,
+        Self::Department,
+        Self::Deposit,
+        Self::Employee,
+        Self::Estimate,
+        Self::Invoice,
+        Self::Item,
+        Self::JournalEntry,
+        Self::Payment,
+        Self::PaymentMethod,
+        Self::Purchase,
+        Self::PurchaseOrder,
+        Self::RefundReceipt,
+        Self::SalesReceipt,
+        Self::TaxCode,
+        Self::Term,
+        Self::TimeActivity,
+        Self::Transfer,
+        Self::Vendor,
+        Self::VendorCredit,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Account => "Account",
+            Self::Bill => "Bill",
+            Self::BillPayment => "BillPayment",
+            Self::Class => "Class",
+            Self::CreditMemo => "CreditMemo",
+            Self::Customer => "Customer",
+            Self::Department => "Department",
+            Self::Deposit => "Deposit",
+            Self::Employee => "Employee",
+            Self::Estimate => "Estimate",
+            Self::Invoice => "Invoice",
+            Self::Item => "Item",
+            Self::JournalEntry => "JournalEntry",
+            Self::Payment => "Payment",
+            Self::PaymentMethod => "PaymentMethod",
+            Self::Purchase => "Purchase",
+            Self::PurchaseOrder => "PurchaseOrder",
+            Self::RefundReceipt => "RefundReceipt",
+            Self::SalesReceipt => "SalesReceipt",
+            Self::TaxCode => "TaxCode",
+            Self::Term => "Term",
+            Self::TimeActivity => "TimeActivity",
+            Self::Transfer => "Transfer",
+            Self::Vendor => "Vendor",
+            Self::VendorCredit => "VendorCredit",
+        }
+    }
+
+    /// whether the entity is a transaction (as opposed to a name list entity like `Customer`)
+    pub const fn is_transaction(&self) -> bool {
+        matches!(
+            self,
+            Self::Bill
+                | Self::BillPayment
+                | Self::CreditMemo
+                | Self::Deposit
+                | Self::Estimate
+                | Self::Invoice
+                | Self::JournalEntry
+                | Self::Payment
+                | Self::Purchase
+                | Self::PurchaseOrder
+                | Self::RefundReceipt
+                | Self::SalesReceipt
+                | Self::TimeActivity
+                | Self::Transfer
+                | Self::VendorCredit
+        )
+    }
+
+    /// how the entity is removed, or `None` if it cannot be removed through the API
+    pub const fn removal(&self) -> Option<Removal> {
+        match self {
+            Self::TaxCode => None,
+            _ if self.is_transaction() => Some(Removal::Delete),
+            // name list entities can only be made inactive
+            _ => Some(Removal::Deactivate),
+        }
+    }
+
+    /// whether the entity is a transaction that can be voided instead of deleted
+    pub const fn is_voidable(&self) -> bool {
+        matches!(
+            self,
+            Self::BillPayment | Self::Invoice | Self::Payment | Self::SalesReceipt
+        )
+    }
+}
+
+impl Entity {
+    /// Fields qbtools knows about, including commonly used nested fields like
+    /// `PrimaryEmailAddr.Address`. QuickBooks may return more than these.
+    pub fn fields(&self) -> Vec<&'static str> {
+        let mut fields = COMMON_FIELDS.to_vec();
+
+        if self.is_transaction() && *self != Self::Transfer && *self != Self::TimeActivity {
+            fields.extend_from_slice(TRANSACTION_FIELDS);
+        }
+
+        fields.extend_from_slice(match self {
+            Self::Account => &[
+                "Name",
+                "AcctNum",
+                "FullyQualifiedName",
+                "Active",
+                "Classification",
+                "AccountType",
+                "AccountSubType",
+                "Description",
+                "CurrentBalance",
+                "CurrentBalanceWithSubAccounts",
+                "CurrencyRef",
+                "SubAccount",
+                "ParentRef",
+            ],
+            Self::Bill => &[
+                "VendorRef",
+                "VendorRef.name",
+                "VendorRef.value",
+                "APAccountRef",
+                "DueDate",
+                "Balance",
+                "SalesTermRef",
+            ],
+            Self::BillPayment => &[
+                "VendorRef",
+                "VendorRef.name",
+                "VendorRef.value",
+                "PayType",
+                "CheckPayment",
+                "CreditCardPayment",
+            ],
+            Self::Class => &[
+                "Name",
+                "FullyQualifiedName",
+                "Active",
+                "SubClass",
+                "ParentRef",
+            ],
+            Self::CreditMemo => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "RemainingCredit",
+                "Balance",
+                "BillEmail",
+                "BillEmail.Address",
+            ],
+            Self::Customer => &[
+                "DisplayName",
+                "Title",
+                "GivenName",
+                "MiddleName",
+                "FamilyName",
+                "Suffix",
+                "FullyQualifiedName",
+                "CompanyName",
+                "PrintOnCheckName",
+                "Active",
+                "PrimaryPhone",
+                "PrimaryPhone.FreeFormNumber",
+                "Mobile",
+                "Mobile.FreeFormNumber",
+                "Fax",
+                "PrimaryEmailAddr",
+                "PrimaryEmailAddr.Address",
+                "WebAddr",
+                "WebAddr.URI",
+                "BillAddr",
+                "BillAddr.Line1",
+                "BillAddr.City",
+                "BillAddr.CountrySubDivisionCode",
+                "BillAddr.PostalCode",
+                "BillAddr.Country",
+                "ShipAddr",
+                "Notes",
+                "Job",
+                "ParentRef",
+                "Level",
+                "Taxable",
+                "Balance",
+                "BalanceWithJobs",
+                "OpenBalanceDate",
+                "CurrencyRef",
+                "PreferredDeliveryMethod",
+                "SalesTermRef",
+                "PaymentMethodRef",
+                "ResaleNum",
+            ],
+            Self::Department => &[
+                "Name",
+                "FullyQualifiedName",
+                "Active",
+                "SubDepartment",
+                "ParentRef",
+            ],
+            Self::Deposit => &["DepositToAccountRef"],
+            Self::Employee => &[
+                "DisplayName",
+                "GivenName",
+                "MiddleName",
+                "FamilyName",
+                "PrintOnCheckName",
+                "Active",
+                "PrimaryPhone",
+                "Mobile",
+                "PrimaryEmailAddr",
+                "PrimaryEmailAddr.Address",
+                "PrimaryAddr",
+                "EmployeeNumber",
+                "BillableTime",
+                "HiredDate",
+                "ReleasedDate",
+                "BirthDate",
+                "Gender",
+            ],
+            Self::Estimate => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "BillEmail",
+                "BillEmail.Address",
+                "ExpirationDate",
+                "TxnStatus",
+                "AcceptedBy",
+                "AcceptedDate",
+                "CustomerMemo",
+            ],
+            Self::Invoice => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "BillEmail",
+                "BillEmail.Address",
+                "BillAddr",
+                "ShipAddr",
+                "DueDate",
+                "Balance",
+                "SalesTermRef",
+                "EmailStatus",
+                "PrintStatus",
+                "CustomerMemo",
+                "Deposit",
+                "TxnTaxDetail",
+                "LinkedTxn",
+            ],
+            Self::Item => &[
+                "Name",
+                "Sku",
+                "Description",
+                "Active",
+                "FullyQualifiedName",
+                "Taxable",
+                "UnitPrice",
+                "Type",
+                "IncomeAccountRef",
+                "PurchaseDesc",
+                "PurchaseCost",
+                "ExpenseAccountRef",
+                "AssetAccountRef",
+                "TrackQtyOnHand",
+                "QtyOnHand",
+                "InvStartDate",
+                "SubItem",
+                "ParentRef",
+                "Level",
+            ],
+            Self::JournalEntry => &["Adjustment"],
+            Self::Payment => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "DepositToAccountRef",
+                "PaymentMethodRef",
+                "PaymentRefNum",
+                "UnappliedAmt",
+                "ProcessPayment",
+            ],
+            Self::PaymentMethod => &["Name", "Active", "Type"],
+            Self::Purchase => &[
+                "AccountRef",
+                "PaymentType",
+                "EntityRef",
+                "EntityRef.name",
+                "Credit",
+                "PrintStatus",
+            ],
+            Self::PurchaseOrder => &[
+                "VendorRef",
+                "VendorRef.name",
+                "VendorRef.value",
+                "APAccountRef",
+                "POStatus",
+                "POEmail",
+                "DueDate",
+                "ShipAddr",
+            ],
+            Self::RefundReceipt => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "DepositToAccountRef",
+                "PaymentMethodRef",
+                "Balance",
+            ],
+            Self::SalesReceipt => &[
+                "CustomerRef",
+                "CustomerRef.name",
+                "CustomerRef.value",
+                "DepositToAccountRef",
+                "PaymentMethodRef",
+                "Balance",
+                "BillEmail",
+                "BillEmail.Address",
+                "CustomerMemo",
+            ],
+            Self::TaxCode => &["Name", "Description", "Active", "Taxable", "TaxGroup"],
+            Self::Term => &[
+                "Name",
+                "Active",
+                "Type",
+                "DueDays",
+                "DiscountPercent",
+                "DiscountDays",
+            ],
+            Self::TimeActivity => &[
+                "TxnDate",
+                "NameOf",
+                "EmployeeRef",
+                "VendorRef",
+                "CustomerRef",
+                "ItemRef",
+                "Hours",
+                "Minutes",
+                "StartTime",
+                "EndTime",
+                "BillableStatus",
+                "HourlyRate",
+                "Description",
+            ],
+            Self::Transfer => &[
+                "TxnDate",
+                "PrivateNote",
+                "FromAccountRef",
+                "ToAccountRef",
+                "Amount",
+            ],
+            Self::Vendor => &[
+                "DisplayName",
+                "Title",
+                "GivenName",
+                "MiddleName",
+                "FamilyName",
+                "Suffix",
+                "CompanyName",
+                "PrintOnCheckName",
+                "Active",
+                "PrimaryPhone",
+                "Mobile",
+                "Fax",
+                "PrimaryEmailAddr",
+                "PrimaryEmailAddr.Address",
+                "WebAddr",
+                "BillAddr",
+                "TaxIdentifier",
+                "AcctNum",
+                "Vendor1099",
+                "Balance",
+                "CurrencyRef",
+                "TermRef",
+            ],
+            Self::VendorCredit => &[
+                "VendorRef",
+                "VendorRef.name",
+                "VendorRef.value",
+                "APAccountRef",
+                "Balance",
+            ],
+        });
+
+        fields
+    }
+}
+
+impl Entity {
+    /// the columns shown when listing the entity, eg. in the TUI
+    pub const fn default_columns(&self) -> &'static [&'static str] {
+        match self {
+            Self::Customer | Self::Employee | Self::Vendor => {
+                &["Id", "DisplayName", "PrimaryEmailAddr.Address", "Balance"]
+            }
+            Self::Item => &["Id", "Name", "Type", "UnitPrice", "QtyOnHand"],
+            Self::Account => &["Id", "FullyQualifiedName", "AccountType", "CurrentBalance"],
+            Self::Class | Self::Department | Self::PaymentMethod | Self::TaxCode | Self::Term => {
+                &["Id", "Name", "Active"]
+            }
+            Self::Bill | Self::BillPayment | Self::PurchaseOrder | Self::VendorCredit => {
+                &["Id", "DocNumber", "VendorRef.name", "TxnDate", "TotalAmt"]
+            }
+            Self::CreditMemo
+            | Self::Estimate
+            | Self::Invoice
+            | Self::Payment
+            | Self::RefundReceipt
+            | Self::SalesReceipt => &[
+                "Id",
+                "DocNumber",
+                "CustomerRef.name",
+                "TxnDate",
+                "TotalAmt",
+                "Balance",
+            ],
+            Self::Deposit | Self::JournalEntry | Self::Purchase => {
+                &["Id", "DocNumber", "TxnDate", "TotalAmt"]
+            }
+            Self::TimeActivity => &["Id", "TxnDate", "NameOf", "Hours", "Minutes"],
+            Self::Transfer => &[
+                "Id",
+                "TxnDate",
+                "FromAccountRef.name",
+                "ToAccountRef.name",
+                "Amount",
+            ],
+        }
+    }
+}
+
+const COMMON_FIELDS: &[&str] = &[
+    "Id",
+    "SyncToken",
+    "MetaData",
+    "MetaData.CreateTime",
+    "MetaData.LastUpdatedTime",
+    "domain",
+    "sparse",
+];
+
+const TRANSACTION_FIELDS: &[&str] = &[
+    "DocNumber",
+    "TxnDate",
+    "PrivateNote",
+    "Line",
+    "TotalAmt",
+    "CurrencyRef",
+    "ExchangeRate",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Removal {
+    /// sparse update setting `Active` to `false`
+    Deactivate,
+    Delete,
+    Void,
+}
+
+impl Removal {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deactivate => "deactivate",
+            Self::Delete => "delete",
+            Self::Void => "void",
+        }
+    }
+}
+
+impl FromStr for Entity {
+    type Err = &'static str;
+
+    /// case-insensitive, eg. `customer`, `Customer` and `CUSTOMER` are all `Entity::Customer`
+    fn from_str(entity: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(entity))
+            .ok_or("Could not parse entity")
+    }
+}
+
+impl TryFrom<String> for Entity {
+    type Error = &'static str;
+
+    fn try_from(entity: String) -> Result<Self, Self::Error> {
+        entity.parse()
+    }
+}
+
+impl std::fmt::Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// a type standing for an entity, so it can be named in generic code, eg.
+/// `Query::select::<Customer>()`; implemented by the models of [`crate::model`]
+pub trait QbEntity {
+    const ENTITY: Entity;
+}
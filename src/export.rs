@@ -0,0 +1,261 @@
+//! Exporting every entity of a type, a page at a time.
+//!
+//! ```ignore
+//! use qbtools::{api::Client, entity::Entity, export::ExportOptions, query::field};
+//!
+//! let client = Client::connect(None, false)?;
+//! let options = ExportOptions::new().filter(field("Balance").gt(0)).order_by("DisplayName");
+//!
+//! for customer in qbtools::export(&client, Entity::Customer, options) {
+//!     let customer = customer?;
+//!     // ...
+//! }
+//! ```
+//!
+//! Pages are fetched as the iterator reaches them, so an export can be stopped early without
+//! fetching the rest.
+
+use crate::{
+    api,
+    client::QboClient,
+    commands::take_records,
+    entity::Entity,
+    model::{self, DecodeError, Model},
+    query::{self, field, Condition, Order, Query},
+};
+
+use serde_json::Value;
+
+use std::{collections::VecDeque, fmt};
+
+/// the most records QBO returns for one query
+pub(crate) const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// the options do not make a valid query, eg. a field the entity does not have
+    Query(query::Error),
+    Api(api::Error),
+    /// an entity does not have the shape of its model (see [`export_as`])
+    Decode(DecodeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Query(error) => write!(f, "invalid query: {error}"),
+            Self::Api(error) => write!(f, "request failed: {error}"),
+            Self::Decode(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<query::Error> for Error {
+    fn from(error: query::Error) -> Self {
+        Self::Query(error)
+    }
+}
+
+impl From<api::Error> for Error {
+    fn from(error: api::Error) -> Self {
+        Self::Api(error)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(error: DecodeError) -> Self {
+        Self::Decode(error)
+    }
+}
+
+/// Which entities to export, and in what order; every entity that is active, by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportOptions {
+    conditions: Vec<Condition>,
+    order_by: Vec<(String, Order)>,
+    include_inactive: bool,
+    page_size: u32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            conditions: Vec::new(),
+            order_by: Vec::new(),
+            include_inactive: false,
+            page_size: MAX_PAGE_SIZE,
+        }
+    }
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// only exports entities matching `condition`, eg. `field("Balance").gt(0)`
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// only exports entities changed since a date or date time, eg. `2026-01-01`
+    pub fn updated_since(self, date_time: impl Into<String>) -> Self {
+        self.filter(field("MetaData.LastUpdatedTime").ge(date_time.into()))
+    }
+
+    pub fn order_by(self, field: impl Into<String>) -> Self {
+        self.order_by_with(field, Order::Asc)
+    }
+
+    pub fn order_by_desc(self, field: impl Into<String>) -> Self {
+        self.order_by_with(field, Order::Desc)
+    }
+
+    pub fn order_by_with(mut self, field: impl Into<String>, order: Order) -> Self {
+        self.order_by.push((field.into(), order));
+        self
+    }
+
+    /// exports inactive entities too (QuickBooks leaves them out unless asked for them)
+    pub fn include_inactive(mut self, include_inactive: bool) -> Self {
+        self.include_inactive = include_inactive;
+        self
+    }
+
+    /// how many entities to fetch per request, at most 1,000
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    /// `SELECT * FROM <entity>` with these conditions and ordering
+    pub(crate) fn query(&self, entity: Entity) -> Query {
+        let mut query = Query::from_entity(entity);
+        for condition in &self.conditions {
+            query = query.filter(condition.clone());
+        }
+        for (field, order) in &self.order_by {
+            query = query.order_by_with(field, *order);
+        }
+
+        if self.include_inactive && entity.fields().contains(&"Active") {
+            query = query.filter(field("Active").is_in([true, false]));
+        }
+
+        query
+    }
+}
+
+/// Every entity of `entity` matching `options`, fetched a page at a time (see the module docs).
+///
+/// An invalid query is the first (and only) item, and iteration stops after a failed request.
+pub fn export<C>(client: &C, entity: Entity, options: ExportOptions) -> Export<'_, C>
+where
+    C: QboClient + ?Sized,
+{
+    Export {
+        client,
+        pages: Pages::new(entity, &options).map_err(Some),
+    }
+}
+
+/// [`export`], with every entity as its model, eg. `export_as::<Customer, _>(&client, options)`
+pub fn export_as<T, C>(
+    client: &C,
+    options: ExportOptions,
+) -> impl Iterator<Item = Result<T, Error>> + '_
+where
+    T: Model,
+    C: QboClient + ?Sized,
+{
+    export(client, T::ENTITY, options).map(|record| Ok(model::from_value(record?)?))
+}
+
+/// The iterator returned by [`export`].
+pub struct Export<'a, C: ?Sized> {
+    client: &'a C,
+    /// the error is taken when it is returned
+    pages: Result<Pages, Option<query::Error>>,
+}
+
+impl<C> Iterator for Export<'_, C>
+where
+    C: QboClient + ?Sized,
+{
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pages = match &mut self.pages {
+            Ok(pages) => pages,
+            Err(error) => return error.take().map(|error| Err(error.into())),
+        };
+
+        if let Some(query) = pages.next_query() {
+            match self.client.query(&query) {
+                Ok(mut response) => pages.add(&mut response),
+                Err(error) => {
+                    pages.finish();
+                    return Some(Err(error.into()));
+                }
+            }
+        }
+
+        pages.pop().map(Ok)
+    }
+}
+
+/// The records of an export, and where the next page starts; the requests are left to the caller,
+/// so blocking and async exports page the same way.
+pub(crate) struct Pages {
+    query: String,
+    page_size: u32,
+    start_position: u32,
+    page: VecDeque<Value>,
+    /// whether the last page has been fetched
+    done: bool,
+}
+
+impl Pages {
+    pub(crate) fn new(entity: Entity, options: &ExportOptions) -> Result<Self, query::Error> {
+        Ok(Self {
+            query: options.query(entity).build()?.sql(),
+            page_size: options.page_size,
+            start_position: 1,
+            page: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// the query for the next page, once the records of this one have been taken (and if there
+    /// is one)
+    pub(crate) fn next_query(&self) -> Option<String> {
+        (self.page.is_empty() && !self.done).then(|| {
+            format!(
+                "{} STARTPOSITION {} MAXRESULTS {}",
+                self.query, self.start_position, self.page_size
+            )
+        })
+    }
+
+    /// adds the records of the `QueryResponse` to the next page
+    pub(crate) fn add(&mut self, query_response: &mut Value) {
+        let (_, page) = take_records(query_response);
+
+        self.done = page.len() < self.page_size as usize;
+        self.start_position += self.page_size;
+        self.page.extend(page);
+    }
+
+    /// stops fetching pages, eg. after a request failed
+    pub(crate) fn finish(&mut self) {
+        self.done = true;
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Value> {
+        self.page.pop_front()
+    }
+}
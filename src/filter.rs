@@ -0,0 +1,665 @@
+//! Client-side `--filter` expressions, for what the QBO query language cannot express.
+//!
+//! ```text
+//! Balance > 0 && PrimaryEmailAddr.Address =~ /@example\.com$/i
+//! Line[].SalesItemLineDetail.ItemRef.name contains 'Gardening'
+//! not Active || CustomerRef.value in ['58', '59']
+//! ```
+//!
+//! Paths are dotted field names; `[]` (or `[*]`) goes through every element of an array and `[N]`
+//! picks one. A comparison is true if any value the path leads to satisfies it.
+
+use regex_lite::Regex;
+use serde_json::Value;
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// a parsed expression, along with the text it was parsed from
+#[derive(Clone, Debug)]
+pub struct Filter {
+    source: String,
+    expression: Expression,
+}
+
+impl Filter {
+    pub fn matches(&self, record: &Value) -> bool {
+        self.expression.evaluate(record)
+    }
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+
+        let expression = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!(
+                "unexpected {token} after the end of the expression"
+            ));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            expression,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    /// a bare path, true if it leads to anything other than `null` or `false`
+    Truthy(Path),
+    Compare(Path, Comparison, Operand),
+    Matches(Path, Regex),
+}
+
+impl Expression {
+    fn evaluate(&self, record: &Value) -> bool {
+        match self {
+            Self::And(left, right) => left.evaluate(record) && right.evaluate(record),
+            Self::Or(left, right) => left.evaluate(record) || right.evaluate(record),
+            Self::Not(expression) => !expression.evaluate(record),
+            Self::Truthy(path) => path
+                .resolve(record)
+                .into_iter()
+                .any(|value| !matches!(value, Value::Null | Value::Bool(false))),
+            Self::Compare(path, comparison, operand) => {
+                let right = operand.resolve(record);
+
+                path.resolve(record)
+                    .into_iter()
+                    .any(|left| right.iter().any(|right| comparison.evaluate(left, right)))
+            }
+            Self::Matches(path, regex) => {
+                path.resolve(record).into_iter().any(|value| match value {
+                    Value::String(string) => regex.is_match(string),
+                    Value::Number(number) => regex.is_match(&number.to_string()),
+                    Value::Bool(bool) => regex.is_match(&bool.to_string()),
+                    _ => false,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// the left value is equal to an element of the right array
+    In,
+    /// the left array has an element equal to the right value, or the left string contains it
+    Contains,
+}
+
+impl Comparison {
+    fn evaluate(&self, left: &Value, right: &Value) -> bool {
+        match self {
+            Self::Equal => equal(left, right),
+            Self::Less => compare(left, right) == Some(Ordering::Less),
+            Self::LessOrEqual => {
+                matches!(compare(left, right), Some(Ordering::Less | Ordering::Equal))
+            }
+            Self::Greater => compare(left, right) == Some(Ordering::Greater),
+            Self::GreaterOrEqual => matches!(
+                compare(left, right),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Self::In => match right {
+                Value::Array(elements) => elements.iter().any(|element| equal(left, element)),
+                right => equal(left, right),
+            },
+            Self::Contains => match (left, right) {
+                (Value::Array(elements), right) => {
+                    elements.iter().any(|element| equal(element, right))
+                }
+                (Value::String(string), Value::String(substring)) => string.contains(substring),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// numbers and numeric strings compare as numbers (QBO sends ids as strings), other strings
+/// compare lexicographically, which orders ISO dates correctly
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    fn number(value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => {
+            match (left.trim().parse::<f64>(), right.trim().parse::<f64>()) {
+                (Ok(left), Ok(right)) => left.partial_cmp(&right),
+                _ => Some(left.cmp(right)),
+            }
+        }
+        (Value::Number(_), _) | (_, Value::Number(_)) => number(left)?.partial_cmp(&number(right)?),
+        (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> bool {
+    left == right || compare(left, right) == Some(Ordering::Equal)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    Each,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Path(Vec<Segment>);
+
+impl Path {
+    fn parse(path: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid path {path:?}");
+
+        let trimmed = path.strip_prefix('$').unwrap_or(path);
+        let trimmed = trimmed.strip_prefix('.').unwrap_or(trimmed);
+
+        let mut segments = Vec::new();
+        for part in trimmed.split('.') {
+            let (field, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if !field.is_empty() {
+                segments.push(Segment::Field(field.to_string()));
+            } else if rest.is_empty() {
+                return Err(invalid());
+            }
+
+            while !rest.is_empty() {
+                let end = rest.find(']').ok_or_else(invalid)?;
+                segments.push(match &rest[1..end] {
+                    "" | "*" => Segment::Each,
+                    index => Segment::Index(index.parse().map_err(|_| invalid())?),
+                });
+
+                rest = &rest[end + 1..];
+                if !rest.is_empty() && !rest.starts_with('[') {
+                    return Err(invalid());
+                }
+            }
+        }
+
+        Ok(Self(segments))
+    }
+
+    fn resolve<'a>(&self, record: &'a Value) -> Vec<&'a Value> {
+        let mut values = vec![record];
+
+        for segment in &self.0 {
+            values = values
+                .into_iter()
+                .flat_map(|value| match segment {
+                    Segment::Field(field) => value.get(field).into_iter().collect(),
+                    Segment::Index(index) => value.get(index).into_iter().collect(),
+                    Segment::Each => match value {
+                        Value::Array(elements) => elements.iter().collect(),
+                        _ => Vec::new(),
+                    },
+                })
+                .collect();
+        }
+
+        values
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Literal(Value),
+    Path(Path),
+}
+
+impl Operand {
+    fn resolve<'a>(&'a self, record: &'a Value) -> Vec<&'a Value> {
+        match self {
+            Self::Literal(value) => vec![value],
+            Self::Path(path) => path.resolve(record),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Path(String),
+    Literal(Value),
+    Regex(String),
+    Operator(&'static str),
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "`{path}`"),
+            Self::Literal(value) => write!(f, "`{value}`"),
+            Self::Regex(regex) => write!(f, "`/{regex}/`"),
+            Self::Operator(operator) => write!(f, "`{operator}`"),
+            Self::Open => f.write_str("`(`"),
+            Self::Close => f.write_str("`)`"),
+            Self::OpenList => f.write_str("`[`"),
+            Self::CloseList => f.write_str("`]`"),
+            Self::Comma => f.write_str("`,`"),
+        }
+    }
+}
+
+/// operators made of symbols, longest first so `<=` is not read as `<`
+const SYMBOLS: [&str; 12] = [
+    "==", "!=", "<=", ">=", "=~", "!~", "&&", "||", "<", ">", "!", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let previous = tokens.last();
+
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Comma,
+                });
+            }
+            '[' => {
+                chars.next();
+                tokens.push(Token::OpenList);
+            }
+            ']' => {
+                chars.next();
+                tokens.push(Token::CloseList);
+            }
+            '\'' | '"' => {
+                chars.next();
+                tokens.push(Token::Literal(Value::String(quoted(&mut chars, c)?)));
+            }
+            '/' if matches!(previous, Some(Token::Operator("=~" | "!~"))) => {
+                chars.next();
+                let mut regex = quoted(&mut chars, '/')?;
+                if chars.next_if(|&(_, c)| c == 'i').is_some() {
+                    regex.insert_str(0, "(?i)");
+                }
+                tokens.push(Token::Regex(regex));
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && source[start + 1..].starts_with(|c: char| c.is_ascii_digit())) =>
+            {
+                let mut end = start + c.len_utf8();
+                chars.next();
+                while let Some((index, c)) = chars
+                    .next_if(|&(_, c)| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+'))
+                {
+                    end = index + c.len_utf8();
+                }
+
+                let number = &source[start..end];
+                let value = serde_json::from_str(number)
+                    .map_err(|_| format!("invalid number {number:?}"))?;
+                tokens.push(Token::Literal(value));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                // `]` only belongs to the path if it closes one of the path's `[`
+                let (mut end, mut depth) = (start, 0);
+                while let Some((index, c)) = chars.next_if(|&(_, c)| {
+                    c.is_alphanumeric()
+                        || matches!(c, '_' | '$' | '.' | '[' | '*')
+                        || (c == ']' && depth > 0)
+                }) {
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    end = index + c.len_utf8();
+                }
+
+                let word = &source[start..end];
+                tokens.push(match word {
+                    "and" => Token::Operator("&&"),
+                    "or" => Token::Operator("||"),
+                    "not" => Token::Operator("!"),
+                    "in" => Token::Operator("in"),
+                    "contains" => Token::Operator("contains"),
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    path => Token::Path(path.to_string()),
+                });
+            }
+            _ => {
+                let symbol = SYMBOLS
+                    .into_iter()
+                    .find(|symbol| source[start..].starts_with(symbol))
+                    .ok_or_else(|| format!("unexpected {c:?} at position {start}"))?;
+
+                for _ in 0..symbol.len() {
+                    chars.next();
+                }
+                // a single `=` is accepted as `==`, like in QBO queries
+                tokens.push(Token::Operator(if symbol == "=" { "==" } else { symbol }));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// the text up to an unescaped `end`, with the opening quote already consumed
+fn quoted(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    end: char,
+) -> Result<String, String> {
+    let mut text = String::new();
+
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // regexes keep their escapes, except for the delimiter
+                Some((_, c)) if end == '/' && c != '/' => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                Some((_, c)) => text.push(c),
+                None => break,
+            },
+            c if c == end => return Ok(text),
+            c => text.push(c),
+        }
+    }
+
+    Err(format!("missing closing {end}"))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.and()?;
+        while self.eat(&Token::Operator("||")) {
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.unary()?;
+        while self.eat(&Token::Operator("&&")) {
+            expression = Expression::And(Box::new(expression), Box::new(self.unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.eat(&Token::Operator("!")) {
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+
+        if self.eat(&Token::Open) {
+            let expression = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(expression),
+                Some(token) => Err(format!("expected `)`, found {token}")),
+                None => Err("missing `)`".to_string()),
+            };
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expression, String> {
+        let path = match self.next() {
+            Some(Token::Path(path)) => Path::parse(&path)?,
+            Some(token) => return Err(format!("expected a field, found {token}")),
+            None => return Err("expected a field, found the end of the expression".to_string()),
+        };
+
+        let operator = match self.peek() {
+            Some(Token::Operator(operator)) if !matches!(*operator, "&&" | "||" | "!") => *operator,
+            _ => return Ok(Expression::Truthy(path)),
+        };
+        self.position += 1;
+
+        if matches!(operator, "=~" | "!~") {
+            let pattern = match self.next() {
+                Some(Token::Regex(pattern) | Token::Literal(Value::String(pattern))) => pattern,
+                Some(token) => {
+                    return Err(format!(
+                        "expected a regex after `{operator}`, found {token}"
+                    ))
+                }
+                None => return Err(format!("expected a regex after `{operator}`")),
+            };
+            let regex = Regex::new(&pattern).map_err(|err| format!("invalid regex: {err}"))?;
+
+            let expression = Expression::Matches(path, regex);
+            return Ok(if operator == "!~" {
+                Expression::Not(Box::new(expression))
+            } else {
+                expression
+            });
+        }
+
+        let comparison = match operator {
+            "==" | "!=" => Comparison::Equal,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            "in" => Comparison::In,
+            "contains" => Comparison::Contains,
+            operator => unreachable!("{operator} is handled above"),
+        };
+
+        let expression = Expression::Compare(path, comparison, self.operand(operator)?);
+        Ok(if operator == "!=" {
+            Expression::Not(Box::new(expression))
+        } else {
+            expression
+        })
+    }
+
+    fn operand(&mut self, operator: &str) -> Result<Operand, String> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(Operand::Literal(value)),
+            Some(Token::Path(path)) => Ok(Operand::Path(Path::parse(&path)?)),
+            Some(Token::OpenList) => {
+                let mut elements = Vec::new();
+
+                if self.eat(&Token::CloseList) {
+                    return Ok(Operand::Literal(Value::Array(elements)));
+                }
+
+                loop {
+                    match self.next() {
+                        Some(Token::Literal(value)) => elements.push(value),
+                        Some(token) => {
+                            return Err(format!("expected a value in list, found {token}"))
+                        }
+                        None => return Err("missing `]`".to_string()),
+                    }
+
+                    match self.next() {
+                        Some(Token::Comma) => {}
+                        Some(Token::CloseList) => break,
+                        Some(token) => return Err(format!("expected `,` or `]`, found {token}")),
+                        None => return Err("missing `]`".to_string()),
+                    }
+                }
+
+                Ok(Operand::Literal(Value::Array(elements)))
+            }
+            Some(token) => Err(format!(
+                "expected a value after `{operator}`, found {token}"
+            )),
+            None => Err(format!("expected a value after `{operator}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn customer() -> Value {
+        json!({
+            "Id": "58",
+            "DisplayName": "Amy's Bird Sanctuary",
+            "Active": true,
+            "Balance": 239.0,
+            "PrimaryEmailAddr": { "Address": "Birds@Example.com" },
+            "MetaData": { "LastUpdatedTime": "2026-03-04T10:00:00-08:00" },
+            "Line": [
+                { "Amount": 100, "SalesItemLineDetail": { "ItemRef": { "name": "Gardening" } } },
+                { "Amount": 139, "SalesItemLineDetail": { "ItemRef": { "name": "Pest Control" } } },
+            ],
+        })
+    }
+
+    fn matches(filter: &str) -> bool {
+        filter
+            .parse::<Filter>()
+            .unwrap_or_else(|err| panic!("{filter}: {err}"))
+            .matches(&customer())
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(matches("Balance > 0"));
+        assert!(matches("Balance >= 239"));
+        assert!(!matches("Balance < 239"));
+        assert!(matches("Id = 58"));
+        assert!(matches("Id == '58'"));
+        assert!(matches("Id != '59'"));
+        // numeric strings compare as numbers, not lexicographically
+        assert!(matches("Id > '9'"));
+        assert!(matches("MetaData.LastUpdatedTime >= '2026-01-01'"));
+    }
+
+    #[test]
+    fn boolean_logic() {
+        assert!(matches("Active"));
+        assert!(!matches("not Active"));
+        assert!(!matches("Inactive"));
+        assert!(matches("Balance > 1000 || Active && Id in ['57', '58']"));
+        assert!(!matches("(Balance > 1000 || Active) && Id in ['57', '59']"));
+        assert!(matches("!(Balance > 1000) and Active == true"));
+    }
+
+    #[test]
+    fn arrays_match_any_element() {
+        assert!(matches(
+            "Line[].SalesItemLineDetail.ItemRef.name contains 'Garden'"
+        ));
+        assert!(matches("Line[*].Amount > 120"));
+        assert!(!matches("Line[0].Amount > 120"));
+        assert!(matches("Line[1].Amount > 120"));
+        assert!(!matches("Line[2].Amount > 0"));
+    }
+
+    #[test]
+    fn regexes() {
+        assert!(matches(r"PrimaryEmailAddr.Address =~ /@example\.com$/i"));
+        assert!(!matches(r"PrimaryEmailAddr.Address =~ /@example\.com$/"));
+        assert!(matches(r"DisplayName !~ /^Bob/"));
+        assert!(matches("DisplayName =~ 'Bird'"));
+    }
+
+    #[test]
+    fn fields_compare_with_fields() {
+        let record = json!({ "TotalAmt": 10, "Balance": 10, "DueDate": "2026-01-01", "TxnDate": "2026-02-01" });
+        let filter = |filter: &str| filter.parse::<Filter>().unwrap().matches(&record);
+
+        assert!(filter("Balance == TotalAmt"));
+        assert!(filter("DueDate < TxnDate"));
+    }
+
+    #[test]
+    fn errors() {
+        for filter in [
+            "",
+            "Balance >",
+            "Balance > 0 &&",
+            "(Balance > 0",
+            "Balance > 0)",
+            "Id in ['58'",
+            "DisplayName =~ /(/",
+            "Line[x].Amount > 0",
+            "Name == 'unterminated",
+            "Balance # 0",
+        ] {
+            assert!(filter.parse::<Filter>().is_err(), "{filter:?} parsed");
+        }
+    }
+
+    #[test]
+    fn displays_its_source() {
+        let filter: Filter = "Balance > 0".parse().unwrap();
+        assert_eq!(filter.to_string(), "Balance > 0");
+    }
+}
@@ -22,6 +22,22 @@ fn deserialize<S: AsRef<OsStr> + ?Sized, T: DeserializeOwned>(path: &S) -> Resul
     })
 }
 
+/// reads a JSON/TOML/YAML file given by the user, using its extension to pick the format
+#[cfg(any(feature = "cmd-batch", feature = "cmd-update"))]
+pub fn read_data_file<T>(path: &Path) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    match path.extension().and_then(OsStr::to_str) {
+        Some("json") => deserialize(path),
+        #[cfg(feature = "toml")]
+        Some("toml") => deserialize(path),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => deserialize(path),
+        _ => Err(Error::Deserialize(Some("unsupported file extension"))),
+    }
+}
+
 /// TODO: fix this once `const VAR: [type; _] = ["...", "..."];` is stable
 #[cfg(feature = "toml")]
 pub const SUPPORTED_CONFIG_TYPES: [&str; 3] = ["yaml", "json", "toml"];
@@ -29,7 +45,7 @@ pub const SUPPORTED_CONFIG_TYPES: [&str; 3] = ["yaml", "json", "toml"];
 pub const SUPPORTED_CONFIG_TYPES: [&str; 2] = ["yaml", "json"];
 
 /// # Example
-/// ```
+/// ```ignore
 /// let files = fs::get_possible_files("foo/bar");
 ///    assert_eq!(files, "foo/bar.{json,toml,yaml}".to_string());
 ///    // AKA
@@ -100,7 +116,7 @@ pub mod util {
 
     /// Returns a path with a new extension component appended to the end.
     /// # Example
-    /// ```
+    /// ```ignore
     /// use util::append;
     /// use std::path::{Path, PathBuf};
     ///
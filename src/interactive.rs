@@ -0,0 +1,387 @@
+//! Read-eval-print loop accepting the same commands as the command line.
+
+use crate::{
+    args::{Args, OutputFormat},
+    config,
+    entity::Entity,
+};
+
+use clap::{CommandFactory, Parser};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+    Context, Editor, Helper,
+};
+
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
+
+const BUILTINS: [&str; 5] = ["help", "set", "login", "exit", "quit"];
+const VARIABLES: [&str; 5] = ["format", "pretty", "quiet", "dry-run", "profile"];
+
+/// options whose value is a field name of the entity on the line
+const FIELD_OPTIONS: [&str; 4] = ["--fields", "--order-by", "--set", "--where-field"];
+
+/// defaults applied to every command of the session, unless the command overrides them
+#[derive(Clone, Debug, Default)]
+struct Session {
+    format: Option<OutputFormat>,
+    pretty: bool,
+    quiet: bool,
+    dry_run: bool,
+}
+
+pub fn main(args: impl Iterator<Item = OsString>) {
+    let mut session = Session::default();
+
+    // global options given next to `-i` become the session's defaults
+    let args: Vec<_> = args.skip(1).collect();
+    for arg in &args {
+        match arg.to_str() {
+            Some("-q" | "--quiet") => session.quiet = true,
+            Some("--dry-run") => session.dry_run = true,
+            _ => log::warn!("ignoring argument {arg:?} in interactive mode"),
+        }
+    }
+
+    println!(
+        "qbtools version {}, running interactively; type `help` for a list of commands",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    if let Err(err) = config::get_authorized_config(session.quiet) {
+        eprintln!("error: failed to authorize: {err:?}");
+        return;
+    }
+
+    let mut editor: Editor<QbHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("error: failed to start line editor: {err}");
+            return;
+        }
+    };
+    editor.set_helper(Some(QbHelper));
+
+    let history = history_path();
+    if let Err(err) = editor.load_history(&history) {
+        log::debug!("no history loaded from {}: {err}", history.display());
+    }
+
+    loop {
+        let line = match editor.readline("qbtools> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("error: {err}");
+                break;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(err) = editor.add_history_entry(line.as_str()) {
+            log::warn!("failed to add line to history: {err}");
+        }
+
+        let Some(words) = shlex::split(&line) else {
+            eprintln!("error: unbalanced quotes");
+            continue;
+        };
+        // eg. a line with only a comment, or only `""`
+        let Some(first) = words.first() else {
+            continue;
+        };
+
+        match first.as_str() {
+            "exit" | "quit" => break,
+            "help" => print_help(),
+            "set" => set(&mut session, &words[1..]),
+            "login" => {
+                config::forget_authorization();
+                if let Err(err) = config::get_authorized_config(session.quiet) {
+                    eprintln!("error: failed to authorize: {err:?}");
+                }
+            }
+            _ => execute(&session, words),
+        }
+    }
+
+    if let Err(err) = editor.save_history(&history) {
+        log::warn!("failed to save history to {}: {err}", history.display());
+    }
+}
+
+fn history_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(".qbtools_history")
+}
+
+fn print_help() {
+    if let Err(err) = Args::command().print_help() {
+        eprintln!("error: {err}");
+    }
+
+    println!();
+    println!("Interactive commands:");
+    println!("  set [VARIABLE [VALUE]]  show or set format, pretty, quiet, dry-run or profile");
+    println!("  login                   authorize again");
+    println!("  exit, quit              leave the session");
+}
+
+fn set(session: &mut Session, words: &[String]) {
+    fn on_off(value: Option<&str>) -> Option<bool> {
+        match value {
+            Some("on" | "true" | "yes") | None => Some(true),
+            Some("off" | "false" | "no") => Some(false),
+            Some(value) => {
+                eprintln!("error: expected on or off, got {value:?}");
+                None
+            }
+        }
+    }
+
+    let value = words.get(1).map(String::as_str);
+
+    match words.first().map(String::as_str) {
+        None => {
+            println!(
+                "format  = {}",
+                session
+                    .format
+                    .as_ref()
+                    .map_or("(command default)", OutputFormat::as_str)
+            );
+            println!("pretty  = {}", session.pretty);
+            println!("quiet   = {}", session.quiet);
+            println!("dry-run = {}", session.dry_run);
+            println!(
+                "profile = {}",
+                config::profile().unwrap_or_else(|| "(default)".to_string())
+            );
+        }
+        Some("format") => match value.map(OutputFormat::from_str) {
+            None => session.format = None,
+            Some(Ok(format)) => session.format = Some(format),
+            Some(Err(err)) => eprintln!("error: {err}"),
+        },
+        Some("pretty") => session.pretty = on_off(value).unwrap_or(session.pretty),
+        Some("quiet") => session.quiet = on_off(value).unwrap_or(session.quiet),
+        Some("dry-run") => session.dry_run = on_off(value).unwrap_or(session.dry_run),
+        Some("profile") => {
+            config::set_profile(value.filter(|profile| *profile != "default"));
+            if let Err(err) = config::get_authorized_config(session.quiet) {
+                eprintln!("error: failed to authorize: {err:?}");
+            }
+        }
+        Some(variable) => eprintln!("error: unknown variable {variable:?}"),
+    }
+}
+
+/// parses `words` like the command line, with the session's defaults filled in, and runs it
+fn execute(session: &Session, words: Vec<String>) {
+    let root = Args::command();
+    let subcommand_index = words
+        .iter()
+        .position(|word| root.find_subcommand(word).is_some());
+
+    let mut argv = vec!["qbtools".to_string()];
+
+    if session.quiet && !words.iter().any(|word| word == "-q" || word == "--quiet") {
+        argv.push("--quiet".to_string());
+    }
+    if session.dry_run && !words.iter().any(|word| word == "--dry-run") {
+        argv.push("--dry-run".to_string());
+    }
+
+    match subcommand_index {
+        Some(index) => {
+            let subcommand = root
+                .find_subcommand(&words[index])
+                .expect("index to point at a subcommand");
+            let has_argument = |id: &str| {
+                subcommand
+                    .get_arguments()
+                    .any(|argument| argument.get_id() == id)
+            };
+
+            argv.extend_from_slice(&words[..=index]);
+
+            if let Some(format) = &session.format {
+                if has_argument("format")
+                    && !words.iter().any(|word| {
+                        word == "-f" || word == "--format" || word.starts_with("--format=")
+                    })
+                {
+                    argv.push("--format".to_string());
+                    argv.push(format.as_str().to_string());
+                }
+            }
+            if session.pretty && has_argument("pretty") && !words.iter().any(|w| w == "--pretty") {
+                argv.push("--pretty".to_string());
+            }
+
+            argv.extend_from_slice(&words[index + 1..]);
+        }
+        None => argv.extend(words),
+    }
+
+    let args = match Args::try_parse_from(argv) {
+        Ok(args) => args,
+        Err(err) => {
+            // also prints --help and --version output
+            let _ = err.print();
+            return;
+        }
+    };
+
+    // `--profile` only applies to this command; the session's profile is changed with `set`
+    let profile = config::profile();
+    let switches_profile = args.profile.is_some();
+
+    if let Err(message) = crate::run(args) {
+        eprintln!("error: {message}");
+    }
+
+    if switches_profile {
+        config::set_profile(profile.as_deref());
+    }
+}
+
+struct QbHelper;
+
+impl QbHelper {
+    fn candidates(words: &[&str], word: &str) -> Vec<String> {
+        let root = Args::command();
+        let previous = words.last().copied();
+
+        if words.is_empty() {
+            return root
+                .get_subcommands()
+                .map(|subcommand| subcommand.get_name().to_string())
+                .chain(BUILTINS.iter().map(|builtin| builtin.to_string()))
+                .collect();
+        }
+
+        if words[0] == "set" {
+            return match (words.len(), previous) {
+                (1, _) => VARIABLES.iter().map(|v| v.to_string()).collect(),
+                (2, Some("format")) => formats(),
+                (2, Some("pretty" | "quiet" | "dry-run")) => vec!["on".into(), "off".into()],
+                _ => Vec::new(),
+            };
+        }
+
+        if let Some("-f" | "--format") = previous {
+            return formats();
+        }
+
+        if previous.is_some_and(|previous| FIELD_OPTIONS.contains(&previous)) {
+            return match words.iter().find_map(|word| entity_of(word)) {
+                Some(entity) => entity.fields().iter().map(|f| f.to_string()).collect(),
+                None => Vec::new(),
+            };
+        }
+
+        // find the (sub)command being typed, eg. `export` in `export customers`
+        let mut command = &root;
+        for word in words {
+            if let Some(subcommand) = command.find_subcommand(word) {
+                command = subcommand;
+            }
+        }
+
+        if word.starts_with('-') {
+            return command
+                .get_arguments()
+                .chain(root.get_arguments())
+                .filter_map(|argument| argument.get_long())
+                .map(|long| format!("--{long}"))
+                .collect();
+        }
+
+        let mut candidates: Vec<String> = command
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect();
+
+        for positional in command.get_positionals() {
+            match positional.get_id().as_str() {
+                "entity" => candidates.extend(
+                    Entity::ALL
+                        .iter()
+                        .map(|entity| entity.as_str().to_lowercase()),
+                ),
+                #[cfg(feature = "cmd-report")]
+                "report" => candidates.extend(
+                    crate::args::ReportName::ALL
+                        .iter()
+                        .map(|report| report.as_str().to_string()),
+                ),
+                _ => {}
+            }
+        }
+
+        candidates
+    }
+}
+
+fn formats() -> Vec<String> {
+    OutputFormat::ALL
+        .iter()
+        .map(|format| format.as_str().to_string())
+        .collect()
+}
+
+/// the entity a word on the line refers to, eg. `customer` or the `customers` export
+fn entity_of(word: &str) -> Option<Entity> {
+    Entity::from_str(word)
+        .or_else(|_| Entity::from_str(word.strip_suffix('s').unwrap_or(word)))
+        .ok()
+}
+
+impl Completer for QbHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before
+            .rfind(char::is_whitespace)
+            .map_or(0, |index| index + 1);
+        let word = &before[start..];
+        let words: Vec<&str> = before[..start].split_whitespace().collect();
+
+        let candidates = Self::candidates(&words, word)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for QbHelper {
+    type Hint = String;
+}
+
+impl Highlighter for QbHelper {}
+
+impl Validator for QbHelper {}
+
+impl Helper for QbHelper {}
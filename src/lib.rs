@@ -1,16 +1,70 @@
+//! qbtools as a library: the QuickBooks Online API behind [`QboClient`], typed queries, typed
+//! entities ([`model`]) and exports that can be iterated over.
+//!
+//! ```ignore
+//! use qbtools::{api::Client, entity::Entity, ExportOptions};
+//!
+//! // reads `qb-api-cfg-accounting.*`, refreshing (and saving) the access token if needed
+//! let client = Client::connect(Some("accounting"), false)?;
+//!
+//! for customer in qbtools::export(&client, Entity::Customer, ExportOptions::new()) {
+//!     println!("{}", customer?["DisplayName"]);
+//! }
+//! ```
+//!
+//! Nothing under [`api`], [`client`], [`entity`], [`export`], [`model`] and [`query`] (or
+//! re-exported here) prints to stdout or exits the process; errors are returned. These are the
+//! public API, and follow semver: see `CHANGELOG.md`. `main_cmdline` and `main_interactive` are
+//! the command line, which does print and exit.
+
+pub mod api;
 mod args;
+mod cassette;
+pub mod client;
 mod commands;
 mod config;
+pub mod entity;
+pub mod export;
+mod filter;
 mod fs;
+#[cfg(feature = "interactive")]
+mod interactive;
+mod local_server;
+#[cfg(feature = "mock-server")]
+pub mod mock;
+pub mod model;
+pub mod query;
+
+pub use client::QboClient;
+pub use config::{Config, ConfigError};
+pub use export::{export, ExportOptions};
+pub use quickbooks_ureq;
 
 #[cfg(feature = "cmd-export")]
 use commands::export::ExportArgs;
-#[cfg(feature = "cmd-export")]
+#[cfg(any(feature = "cmdline", feature = "interactive"))]
 use std::ffi::OsString;
 
 #[cfg(feature = "cmd-get")]
 use commands::get::GetArgs;
 
+#[cfg(feature = "cmd-batch")]
+use commands::batch::BatchArgs;
+#[cfg(feature = "cmd-query")]
+use commands::query::QueryArgs;
+#[cfg(feature = "cmd-remove")]
+use commands::remove::RemoveArgs;
+#[cfg(feature = "cmd-report")]
+use commands::report::ReportArgs;
+#[cfg(feature = "tui")]
+use commands::tui::TuiArgs;
+#[cfg(feature = "cmd-update")]
+use commands::update::UpdateArgs;
+#[cfg(feature = "cmd-webhook")]
+use commands::webhook::{SendArgs, ServeArgs};
+#[cfg(feature = "cmd-pdf")]
+use entity::Entity;
+
 mod wrapper {
     #![allow(unused_imports)]
     use super::*;
@@ -25,6 +79,8 @@ use wrapper::*;
 
 use quickbooks_ureq::{config::QuickbooksConfig, constants::base_url};
 
+#[cfg(any(feature = "cmdline", feature = "interactive"))]
+use once_cell::unsync::OnceCell;
 use std::process::exit;
 
 const BASE_CONFIG_PATH: &str = "qb-api-cfg";
@@ -35,59 +91,291 @@ const QB_BASE_URL: &str = base_url::PRODUCTION;
 const QB_BASE_URL: &str = base_url::SANDBOX;
 
 #[cfg(feature = "interactive")]
-pub fn main_interactive(_args: impl Iterator<Item = OsString>) {
-    println!(
-        "qbtools version {}, running interactively",
-        env!("CARGO_PKG_VERSION")
-    );
+pub fn main_interactive(args: impl Iterator<Item = OsString>) {
+    interactive::main(args);
+}
+
+/// answers a shell asking for completions (see `qbtools completions`) and exits; does nothing
+/// otherwise, so it has to run before anything is printed
+#[cfg(feature = "cmd-completions")]
+pub fn complete() {
+    commands::completions::complete();
 }
 
 #[cfg(feature = "cmdline")]
 pub fn main_cmdline(args: impl Iterator<Item = OsString>) {
     let args = Args::parse_from(args);
 
-    match args.command {
+    run(args).die_with(|message| (1, message));
+}
+
+/// runs a parsed command, returning a description of the error if it failed
+#[cfg(any(feature = "cmdline", feature = "interactive"))]
+fn run(args: Args) -> Result<(), String> {
+    if let Some(profile) = &args.profile {
+        config::set_profile(Some(profile.as_str()));
+    }
+
+    if let Some(directory) = &args.mirror {
+        client::set_mirror(Some(directory.clone()));
+    }
+
+    if let Some(directory) = &args.record {
+        cassette::record(directory)
+            .map_err(|err| format!("failed to record to {}: {err:?}", directory.display()))?;
+    }
+    if let Some(directory) = &args.replay {
+        cassette::replay(directory)
+            .map_err(|err| format!("failed to replay {}: {err:?}", directory.display()))?;
+    }
+
+    // connecting authorizes against QuickBooks, so only commands that send requests do it
+    let (quiet, dry_run) = (args.quiet, args.dry_run);
+    let connected = OnceCell::new();
+    #[allow(unused)]
+    let connect = || {
+        connected
+            .get_or_try_init(|| client::connect(quiet, dry_run))
+            .map(Box::as_ref)
+            .map_err(|err| format!("failed to connect: {err:?}"))
+    };
+
+    let result = match args.command {
         #[cfg(feature = "cmd-export")]
-        Command::Export {
-            command,
-            format,
-            output_path,
-            pretty,
-        } => {
+        Command::Export { command, output } => {
             let args = ExportArgs {
-                format,
-                output_path,
-                pretty,
+                output,
                 quiet: args.quiet,
                 verbose: args.verbose,
             };
 
             match command {
-                ExportCommands::Customers(c_args) => commands::export::customers(&args, &c_args)
-                    .die_with(|err| (1, format!("failed to export customers: {err:?}"))),
-                ExportCommands::Items => commands::export::items(&args)
-                    .die_with(|err| (1, format!("failed to export items: {err:?}"))),
+                ExportCommands::Customers(c_args) => {
+                    commands::export::customers(connect()?, &args, &c_args)
+                        .map_err(|err| format!("failed to export customers: {err:?}"))
+                }
+                ExportCommands::Items(i_args) => {
+                    commands::export::items(connect()?, &args, &i_args)
+                        .map_err(|err| format!("failed to export items: {err:?}"))
+                }
             }
         }
         #[cfg(feature = "cmd-get")]
         Command::Get {
-            format,
+            output,
             id,
-            output_path,
             command,
         } => {
             let get_args = GetArgs {
-                format,
+                output,
+                quiet: args.quiet,
                 id,
-                output_path,
+            };
+
+            match command {
+                GetCommands::Customer => commands::get::customer(connect()?, &get_args)
+                    .map_err(|err| format!("failed to get customer: {err:?}")),
+                GetCommands::Item => commands::get::item(connect()?, &get_args)
+                    .map_err(|err| format!("failed to get item: {err:?}")),
+            }
+        }
+        #[cfg(feature = "cmd-update")]
+        Command::Update {
+            output,
+            entity,
+            id,
+            set,
+            patch,
+            retry_on_conflict,
+        } => {
+            let update_args = UpdateArgs {
+                output,
                 quiet: args.quiet,
+                dry_run: args.dry_run,
+                entity,
+                id,
+                set,
+                patch,
+                retry_on_conflict,
             };
 
+            commands::update::sparse(connect()?, &update_args).map_err(|err| {
+                format!(
+                    "failed to update {} {}: {err:?}",
+                    update_args.entity, update_args.id
+                )
+            })
+        }
+        #[cfg(feature = "cmd-remove")]
+        Command::Remove {
+            output,
+            entity,
+            ids,
+            void,
+            yes,
+            backup_dir,
+        } => {
+            let remove_args = RemoveArgs {
+                output,
+                quiet: args.quiet,
+                dry_run: args.dry_run,
+                entity,
+                ids,
+                void,
+                yes,
+                backup_dir,
+            };
+
+            commands::remove::remove(connect()?, &remove_args)
+                .map_err(|err| format!("failed to remove {entity}: {err:?}"))
+        }
+        #[cfg(feature = "cmd-batch")]
+        Command::Batch { output, input } => {
+            let batch_args = BatchArgs {
+                output,
+                quiet: args.quiet,
+                dry_run: args.dry_run,
+                input,
+            };
+
+            commands::batch::run(connect()?, &batch_args)
+                .map_err(|err| format!("failed to run batch: {err:?}"))
+        }
+        #[cfg(feature = "cmd-report")]
+        Command::Report {
+            output,
+            report,
+            from,
+            to,
+            accounting_method,
+            summarize_by,
+            flat,
+        } => {
+            let report_args = ReportArgs {
+                output,
+                quiet: args.quiet,
+                report,
+                from,
+                to,
+                accounting_method,
+                summarize_by,
+                flat,
+            };
+
+            commands::report::export(connect()?, &report_args)
+                .map_err(|err| format!("failed to export {} report: {err:?}", report.as_str()))
+        }
+        #[cfg(feature = "cmd-query")]
+        Command::Query {
+            output,
+            query,
+            page_size,
+        } => {
+            let query_args = QueryArgs {
+                output,
+                quiet: args.quiet,
+                query,
+                page_size,
+            };
+
+            commands::query::run(connect()?, &query_args)
+                .map_err(|err| format!("failed to run query: {err:?}"))
+        }
+        #[cfg(feature = "cmd-pdf")]
+        Command::Pdf { command } => {
+            let client = connect()?;
+            let quiet = args.quiet;
+
             match command {
-                GetCommands::Customer => commands::get::customer(&get_args)
-                    .die_with(|err| (1, format!("failed to get customer: {err:?}"))),
-                GetCommands::Item => todo!(),
+                PdfCommands::Invoice(pdf_args) => {
+                    commands::pdf::one(client, Entity::Invoice, &pdf_args, quiet)
+                }
+                PdfCommands::Invoices(pdf_args) => {
+                    commands::pdf::many(client, Entity::Invoice, &pdf_args, quiet)
+                }
+                PdfCommands::Estimate(pdf_args) => {
+                    commands::pdf::one(client, Entity::Estimate, &pdf_args, quiet)
+                }
+                PdfCommands::Estimates(pdf_args) => {
+                    commands::pdf::many(client, Entity::Estimate, &pdf_args, quiet)
+                }
+                PdfCommands::SalesReceipt(pdf_args) => {
+                    commands::pdf::one(client, Entity::SalesReceipt, &pdf_args, quiet)
+                }
+                PdfCommands::SalesReceipts(pdf_args) => {
+                    commands::pdf::many(client, Entity::SalesReceipt, &pdf_args, quiet)
+                }
             }
+            .map_err(|err| format!("failed to download PDFs: {err:?}"))
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui => commands::tui::browse(connect()?, &TuiArgs { quiet: args.quiet })
+            .map_err(|err| format!("failed to run tui: {err:?}")),
+        #[cfg(feature = "mock-server")]
+        Command::MockServer {
+            port,
+            fixtures,
+            token_lifetime,
+            throttle,
+        } => {
+            let options = mock::Options {
+                fixtures,
+                token_lifetime,
+                requests_per_minute: throttle,
+            };
+
+            mock::run(("127.0.0.1", port), options)
+                .map_err(|err| format!("failed to run mock server: {err:?}"))
+        }
+        #[cfg(feature = "cmd-completions")]
+        Command::Completions { shell } => commands::completions::print(&shell)
+            .map_err(|err| format!("failed to print {shell} completions: {err:?}")),
+        #[cfg(feature = "cmd-man")]
+        Command::Man { out_dir } => commands::man::generate(&out_dir, args.quiet)
+            .map_err(|err| format!("failed to write man pages: {err:?}")),
+        #[cfg(feature = "cmd-webhook")]
+        Command::Webhook { command } => match command {
+            WebhookCommands::Serve {
+                bind,
+                port,
+                verifier_token,
+                exec,
+            } => {
+                let serve_args = ServeArgs {
+                    quiet: args.quiet,
+                    bind,
+                    port,
+                    verifier_token,
+                    exec,
+                };
+
+                commands::webhook::serve(&serve_args)
+                    .map_err(|err| format!("failed to serve webhook: {err:?}"))
+            }
+            WebhookCommands::Send {
+                url,
+                verifier_token,
+                payload,
+            } => {
+                let send_args = SendArgs {
+                    url,
+                    verifier_token,
+                    payload,
+                };
+
+                commands::webhook::send(&send_args)
+                    .map_err(|err| format!("failed to send notification: {err:?}"))
+            }
+        },
+    };
+
+    // the requests `--dry-run` did not send are its output, whichever command planned them
+    if let Some(client) = connected.get() {
+        if client.is_dry_run() {
+            commands::print_planned_requests(client.as_ref())
+                .map_err(|err| format!("failed to print planned requests: {err:?}"))?;
         }
     }
+
+    result
 }
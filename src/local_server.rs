@@ -0,0 +1,328 @@
+//! Just enough HTTP/1.1 to stand in for QuickBooks on a local port, for the mock server and for
+//! recording and replaying traffic.
+//!
+//! Every connection gets a single response and is closed. Requests with a body over 1 MiB, a line
+//! over 8 KiB or more than 100 headers are turned away without being read.
+
+use serde_json::json;
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+/// the largest body read, anything bigger is answered with `413`
+const MAX_BODY_SIZE: u64 = 1024 * 1024;
+/// the longest request line or header line, with its line break
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+pub(crate) struct Request {
+    pub method: String,
+    /// percent-decoded, without the query string
+    pub path: String,
+    /// percent-decoded, in the order they were sent
+    pub query: Vec<(String, String)>,
+    /// with lowercase names
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub(crate) struct Response {
+    pub status: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            body: body.to_string().into_bytes(),
+        }
+    }
+}
+
+/// answers every connection to `listener` with `handle`, until the process exits
+pub(crate) fn serve<F>(listener: TcpListener, name: &str, mut handle: F)
+where
+    F: FnMut(&Request) -> Response,
+{
+    for stream in listener.incoming() {
+        let result = stream.and_then(|mut stream| {
+            stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+            let response = match read_request(&mut stream) {
+                Ok(Some(request)) => handle(&request),
+                Ok(None) => return Ok(()),
+                Err(RequestError::Rejected(status, error)) => {
+                    log::warn!("{name}: turned away a request: {error}");
+                    Response::json(status, &json!({ "error": error }))
+                }
+                Err(RequestError::Io(err)) => return Err(err),
+            };
+            write_response(&mut stream, &response)
+        });
+
+        if let Err(err) = result {
+            log::warn!("{name}: {err}");
+        }
+    }
+}
+
+#[derive(Debug)]
+enum RequestError {
+    Io(io::Error),
+    /// answered with the status instead of being handled
+    Rejected(u16, &'static str),
+}
+
+impl From<io::Error> for RequestError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+fn read_request(stream: impl Read) -> Result<Option<Request>, RequestError> {
+    let mut reader = BufReader::new(stream);
+
+    let Some(request_line) = read_line(&mut reader, 414, "request line too long")? else {
+        return Ok(None);
+    };
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(RequestError::Rejected(400, "invalid request line"));
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader, 431, "header line too long")?.unwrap_or_default();
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(RequestError::Rejected(431, "too many headers"));
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = match headers.iter().find(|(name, _)| name == "content-length") {
+        Some((_, value)) => value
+            .parse::<u64>()
+            .map_err(|_| RequestError::Rejected(400, "invalid Content-Length"))?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_SIZE {
+        return Err(RequestError::Rejected(413, "body larger than 1 MiB"));
+    }
+
+    let mut body = Vec::new();
+    reader.take(content_length).read_to_end(&mut body)?;
+    if body.len() as u64 != content_length {
+        return Err(RequestError::Io(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before the whole body was sent",
+        )));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: percent_decode(path),
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// the next line without its line break, None at the end of the stream, or `status` if it is
+/// longer than [`MAX_LINE_LENGTH`]
+fn read_line(
+    reader: &mut impl BufRead,
+    status: u16,
+    error: &'static str,
+) -> Result<Option<String>, RequestError> {
+    let mut line = String::new();
+    if reader.take(MAX_LINE_LENGTH).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() as u64 == MAX_LINE_LENGTH {
+        return Err(RequestError::Rejected(status, error));
+    }
+
+    Ok(Some(line.trim_end().to_string()))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// decodes `%XX` escapes and `+` (as a space)
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[index], escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(request: &[u8]) -> Result<Option<Request>, RequestError> {
+        read_request(request)
+    }
+
+    fn rejected(request: &[u8]) -> u16 {
+        match read(request) {
+            Err(RequestError::Rejected(status, _)) => status,
+            Err(RequestError::Io(err)) => panic!("not rejected: {err}"),
+            Ok(_) => panic!("not rejected"),
+        }
+    }
+
+    #[test]
+    fn reads_a_request() {
+        let request = read(
+            b"POST /v3/company/1/query?query=select%20*+from%20Customer&minorversion=75 HTTP/1.1\r\n\
+              Content-Type: text/plain\r\n\
+              Content-Length: 5\r\n\
+              \r\n\
+              hello and more",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v3/company/1/query");
+        assert_eq!(
+            request.query,
+            [
+                ("query".to_string(), "select * from Customer".to_string()),
+                ("minorversion".to_string(), "75".to_string()),
+            ]
+        );
+        assert_eq!(request.header("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(request.body, b"hello");
+    }
+
+    #[test]
+    fn reads_nothing_from_a_closed_connection() {
+        assert!(read(b"").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        assert_eq!(rejected(b"GET\r\n\r\n"), 400);
+        assert_eq!(
+            rejected(b"POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n"),
+            400
+        );
+    }
+
+    #[test]
+    fn rejects_bodies_over_the_limit_without_reading_them() {
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+
+        assert_eq!(rejected(request.as_bytes()), 413);
+    }
+
+    #[test]
+    fn rejects_long_lines_and_too_many_headers() {
+        let long = "a".repeat(MAX_LINE_LENGTH as usize);
+        assert_eq!(
+            rejected(format!("GET /{long} HTTP/1.1\r\n\r\n").as_bytes()),
+            414
+        );
+        assert_eq!(
+            rejected(format!("GET / HTTP/1.1\r\nX-Long: {long}\r\n\r\n").as_bytes()),
+            431
+        );
+
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS + 1);
+        assert_eq!(
+            rejected(format!("GET / HTTP/1.1\r\n{headers}\r\n").as_bytes()),
+            431
+        );
+
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS);
+        assert!(read(format!("GET / HTTP/1.1\r\n{headers}\r\n").as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn fails_on_a_truncated_body() {
+        let result = read(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort");
+
+        assert!(
+            matches!(result, Err(RequestError::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof)
+        );
+    }
+}
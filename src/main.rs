@@ -5,11 +5,14 @@ use std::ffi::OsStr;
 use std::ffi::OsString;
 
 fn main() {
+    #[cfg(feature = "cmd-completions")]
+    qbtools::complete();
+
     env_logger::init();
     #[allow(unused_mut)]
     let mut args: Vec<OsString> = std::env::args_os().collect();
 
-    // TODO: add to --help output
+    // `-i` is described in the `--help` output of `Args`
     #[cfg(feature = "interactive")]
     let interactive = if let Some(index) = args.iter().position(|arg| arg == OsStr::new("-i")) {
         args.remove(index);
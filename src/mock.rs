@@ -0,0 +1,345 @@
+//! A QuickBooks Online API served from memory, for running qbtools without Intuit's sandbox.
+//!
+//! [`MockServer::start`] serves fixture entities on a local port, in the background, so tests can
+//! point a config at [`MockServer::base_url`]; `qbtools mock-server` does the same in the
+//! foreground. It implements:
+//! - `query` (`WHERE`, `ORDERBY`, `STARTPOSITION`, `MAXRESULTS` and `COUNT(*)`), leaving out
+//!   inactive entities unless the query mentions `Active`, like QuickBooks
+//! - reading, creating, updating (sparse or not, checking the `SyncToken`), deleting and voiding
+//! - `batch` and `reports/<name>` (from a `reports/<name>.json` fixture, if there is one)
+//! - `<entity>/<id>/pdf`, with a placeholder PDF
+//! - OAuth: access tokens that expire after a number of requests, and a token endpoint
+//!   (`/oauth2/v1/tokens/bearer`) that hands out new ones
+//! - throttling, with the same `429` QuickBooks answers with
+//!
+//! Requests are answered by a [`MemoryClient`], so errors are `Fault` bodies with the codes
+//! QuickBooks uses, eg. `610` for an entity that does not exist and `5010` for a stale `SyncToken`.
+
+use crate::{
+    api::{self, Error, Method},
+    client::{
+        memory::{fault, Entities},
+        MemoryClient, QboClient,
+    },
+    local_server::{self, percent_decode, Request, Response},
+};
+
+use serde_json::{json, Value};
+
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+const REFRESH_TOKEN: &str = "mock-refresh-token";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    /// directory of `<Entity>.json` files (each an array of entities) and `reports/<name>.json`
+    /// files; the built-in fixtures are used if None
+    pub fixtures: Option<PathBuf>,
+    /// after this many requests, the access token expires and has to be refreshed
+    pub token_lifetime: Option<u32>,
+    /// requests answered per minute before the rest are throttled
+    pub requests_per_minute: Option<u32>,
+}
+
+/// A mock server running in the background until it is dropped (or the process exits).
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    /// starts serving on `address`, eg. `127.0.0.1:0` for any free port
+    pub fn start(address: impl ToSocketAddrs, options: Options) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::new(&options)?));
+
+        let server_state = Arc::clone(&state);
+        thread::spawn(move || serve(listener, server_state));
+
+        Ok(Self { address, state })
+    }
+
+    /// what to use instead of `https://quickbooks.api.intuit.com`
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// a config file that points qbtools at this server
+    pub fn config(&self) -> Value {
+        let state = lock(&self.state);
+
+        json!({
+            "company_id": state.company_id,
+            "access_token": state.access_token,
+            "refresh_token": REFRESH_TOKEN,
+            "token_type": "bearer",
+            "base_url": self.base_url(),
+        })
+    }
+
+    /// every stored entity of `entity`, eg. to check what a command changed
+    pub fn entities(&self, entity: &str) -> Vec<Value> {
+        lock(&self.state).client.entities(entity)
+    }
+
+    /// how many requests the server has answered
+    pub fn request_count(&self) -> usize {
+        lock(&self.state).request_count
+    }
+}
+
+/// serves on `address` until the process is stopped, printing the config to use it with
+pub fn run(address: impl ToSocketAddrs, options: Options) -> io::Result<()> {
+    let server = MockServer::start(address, options)?;
+
+    log::info!("serving a mock QuickBooks API on {}", server.base_url());
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&server.config()).unwrap_or_default()
+    );
+
+    loop {
+        thread::park();
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    // a request that panicked leaves the state as it was before it
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct State {
+    company_id: String,
+    access_token: String,
+    token_generation: u32,
+    /// requests left before the access token expires
+    token_requests_left: Option<u32>,
+    token_lifetime: Option<u32>,
+
+    requests_per_minute: Option<u32>,
+    minute: (Instant, u32),
+    request_count: usize,
+
+    client: MemoryClient,
+}
+
+impl State {
+    fn new(options: &Options) -> io::Result<Self> {
+        let client = match &options.fixtures {
+            Some(directory) => MemoryClient::from_directory(directory)?,
+            None => MemoryClient::new(default_fixtures()),
+        };
+
+        Ok(Self {
+            company_id: "1234567890".to_string(),
+            access_token: "mock-access-token-0".to_string(),
+            token_generation: 0,
+            token_requests_left: options.token_lifetime,
+            token_lifetime: options.token_lifetime,
+            requests_per_minute: options.requests_per_minute,
+            minute: (Instant::now(), 0),
+            request_count: 0,
+            client,
+        })
+    }
+}
+
+fn default_fixtures() -> Entities {
+    let meta_data = json!({
+        "CreateTime": "2026-01-05T09:30:00-08:00",
+        "LastUpdatedTime": "2026-02-10T14:00:00-08:00",
+    });
+
+    BTreeMap::from([
+        (
+            "CompanyInfo".to_string(),
+            vec![json!({
+                "Id": "1",
+                "SyncToken": "0",
+                "CompanyName": "Mock Company",
+                "LegalName": "Mock Company LLC",
+                "CompanyAddr": { "Line1": "123 Main St.", "City": "Mountain View", "CountrySubDivisionCode": "CA", "PostalCode": "94043" },
+                "LegalAddr": { "Line1": "123 Main St.", "City": "Mountain View", "CountrySubDivisionCode": "CA", "PostalCode": "94043" },
+            })],
+        ),
+        (
+            "Customer".to_string(),
+            vec![
+                json!({ "Id": "1", "SyncToken": "0", "DisplayName": "Amy's Bird Sanctuary", "GivenName": "Amy", "PrimaryEmailAddr": { "Address": "birds@example.com" }, "BillAddr": { "Line1": "4581 Finch St.", "City": "Bayshore", "CountrySubDivisionCode": "CA", "PostalCode": "94326" }, "Balance": 239.0, "Active": true, "MetaData": meta_data }),
+                json!({ "Id": "2", "SyncToken": "1", "DisplayName": "Bill's Windsurf Shop", "PrimaryEmailAddr": { "Address": "surf@example.com" }, "Balance": 85.0, "Active": true, "MetaData": meta_data }),
+                json!({ "Id": "3", "SyncToken": "0", "DisplayName": "O'Brien Consulting", "Balance": 0, "Active": false, "MetaData": meta_data }),
+            ],
+        ),
+        (
+            "Item".to_string(),
+            vec![
+                json!({ "Id": "1", "SyncToken": "0", "Name": "Gardening", "Type": "Service", "UnitPrice": 25, "Active": true, "MetaData": meta_data }),
+                json!({ "Id": "2", "SyncToken": "0", "Name": "Rock Fountain", "Type": "Inventory", "UnitPrice": 275, "QtyOnHand": 2, "Active": true, "MetaData": meta_data }),
+            ],
+        ),
+        (
+            "Invoice".to_string(),
+            vec![
+                json!({ "Id": "101", "SyncToken": "0", "DocNumber": "1001", "TxnDate": "2026-02-01", "CustomerRef": { "value": "1", "name": "Amy's Bird Sanctuary" }, "Line": [{ "Amount": 239.0, "DetailType": "SalesItemLineDetail", "SalesItemLineDetail": { "ItemRef": { "value": "1", "name": "Gardening" } } }], "TotalAmt": 239.0, "Balance": 239.0, "MetaData": meta_data }),
+                json!({ "Id": "102", "SyncToken": "0", "DocNumber": "1002", "TxnDate": "2026-02-03", "CustomerRef": { "value": "2", "name": "Bill's Windsurf Shop" }, "Line": [{ "Amount": 85.0, "DetailType": "SalesItemLineDetail", "SalesItemLineDetail": { "ItemRef": { "value": "1", "name": "Gardening" } } }], "TotalAmt": 85.0, "Balance": 85.0, "MetaData": meta_data }),
+            ],
+        ),
+        (
+            "Vendor".to_string(),
+            vec![
+                json!({ "Id": "1", "SyncToken": "0", "DisplayName": "Norton Lumber", "Balance": 0, "Active": true, "MetaData": meta_data }),
+            ],
+        ),
+    ])
+}
+
+fn serve(listener: TcpListener, state: Arc<Mutex<State>>) {
+    local_server::serve(listener, "mock server", |request| {
+        match handle(&mut lock(&state), request) {
+            Ok(response) => response,
+            Err(Error::Status(status, body)) => Response::json(status, &body),
+            Err(error) => {
+                log::error!("mock server: {error:?}");
+                Response::json(500, &json!({ "error": format!("{error:?}") }))
+            }
+        }
+    });
+}
+
+fn handle(state: &mut State, request: &Request) -> Result<Response, Error> {
+    state.request_count += 1;
+    log::debug!("mock server: {} {}", request.method, request.path);
+
+    if request.path == "/oauth2/v1/tokens/bearer" {
+        return refresh_token(state, request).map(|body| Response::json(200, &body));
+    }
+
+    if let Some(limit) = state.requests_per_minute {
+        let (started, count) = &mut state.minute;
+        if started.elapsed() >= Duration::from_secs(60) {
+            *started = Instant::now();
+            *count = 0;
+        }
+        *count += 1;
+
+        if *count > limit {
+            return Err(fault(
+                429,
+                "SERVICE",
+                "003001",
+                "message=ThrottleExceeded; errorCode=003001; statusCode=429",
+                "The request limit was reached.",
+            ));
+        }
+    }
+
+    authenticate(state, request)?;
+
+    let prefix = format!("/v3/company/{}/", state.company_id);
+    let not_found = || {
+        let detail = format!("{} {}", request.method, request.path);
+        fault(404, "SERVICE", "610", "Not Found", &detail)
+    };
+    let Some(endpoint) = request.path.strip_prefix(&prefix) else {
+        return Err(not_found());
+    };
+    let method = match request.method.as_str() {
+        "GET" => Method::Get,
+        "POST" => Method::Post,
+        _ => return Err(not_found()),
+    };
+
+    let body = if request.body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice(&request.body) {
+            Ok(body) => Some(body),
+            Err(err) => {
+                return Err(fault(
+                    400,
+                    "ValidationFault",
+                    "2020",
+                    "Required param missing, need to supply the required value for the API",
+                    &format!("invalid JSON body: {err}"),
+                ))
+            }
+        }
+    };
+
+    let request = api::Request {
+        method,
+        endpoint: endpoint.to_string(),
+        query: request.query.iter().cloned().collect(),
+        body,
+    };
+
+    if request.endpoint.ends_with("/pdf") {
+        return Ok(Response {
+            status: 200,
+            content_type: "application/pdf".to_string(),
+            body: state.client.call_bytes(&request)?,
+        });
+    }
+
+    state
+        .client
+        .call(&request)
+        .map(|body| Response::json(200, &body))
+}
+
+fn refresh_token(state: &mut State, request: &Request) -> Result<Value, Error> {
+    let form = String::from_utf8_lossy(&request.body);
+    let refresh_token = form
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "refresh_token")
+        .map(|(_, value)| percent_decode(value));
+
+    if refresh_token.as_deref() != Some(REFRESH_TOKEN) {
+        return Err(Error::Status(400, json!({ "error": "invalid_grant" })));
+    }
+
+    state.token_generation += 1;
+    state.access_token = format!("mock-access-token-{}", state.token_generation);
+    state.token_requests_left = state.token_lifetime;
+
+    Ok(json!({
+        "access_token": state.access_token,
+        "refresh_token": REFRESH_TOKEN,
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "x_refresh_token_expires_in": 8_726_400,
+    }))
+}
+
+/// fails unless the request has the current access token (and it has not expired)
+fn authenticate(state: &mut State, request: &Request) -> Result<(), Error> {
+    let expected = format!("Bearer {}", state.access_token);
+    let expired = state.token_requests_left == Some(0);
+
+    if request.header("authorization") == Some(expected.as_str()) && !expired {
+        if let Some(left) = &mut state.token_requests_left {
+            *left -= 1;
+        }
+        return Ok(());
+    }
+
+    Err(fault(
+        401,
+        "AUTHENTICATION",
+        "3200",
+        "message=AuthenticationFailed; errorCode=003200; statusCode=401",
+        "Token expired or invalid",
+    ))
+}
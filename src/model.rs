@@ -0,0 +1,379 @@
+//! Typed entities, for code that would rather not index into `serde_json::Value`.
+//!
+//! ```ignore
+//! use qbtools::{export::export_as, model::Customer, ExportOptions};
+//!
+//! for customer in export_as::<Customer, _>(&client, ExportOptions::new()) {
+//!     let customer = customer?;
+//!     println!("{}: {:?}", customer.id, customer.display_name);
+//! }
+//! ```
+//!
+//! Only the common fields are typed. Every other field QuickBooks sends is kept in `other`, and
+//! written back out with the rest, so nothing is lost going through a model. Amounts are kept as
+//! the numbers QuickBooks sent (see [`Number::as_f64`]), so they are written out unchanged.
+//!
+//! An entity that does not have the shape of its model (eg. an `Id` that is not a string) is a
+//! [`DecodeError`].
+
+use crate::entity::{Entity, QbEntity};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+use std::fmt;
+
+/// An entity with a typed model.
+pub trait Model: QbEntity + Serialize + DeserializeOwned {
+    fn id(&self) -> &str;
+}
+
+/// An entity that does not have the shape of its model, or a response without the part that was
+/// asked for (eg. the `QueryResponse` of a query).
+#[derive(Debug)]
+pub struct DecodeError {
+    /// None for a response that is not an entity
+    pub entity: Option<Entity>,
+    /// the `Id` of the entity, if it has one
+    pub id: Option<String>,
+    pub error: serde_json::Error,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.entity, &self.id) {
+            (Some(entity), Some(id)) => write!(f, "unexpected {entity} {id}: {}", self.error),
+            (Some(entity), None) => write!(f, "unexpected {entity}: {}", self.error),
+            (None, _) => write!(f, "unexpected response: {}", self.error),
+        }
+    }
+}
+
+impl DecodeError {
+    /// a response without `field`
+    pub(crate) fn missing_field(field: &'static str) -> Self {
+        Self {
+            entity: None,
+            id: None,
+            error: serde::de::Error::missing_field(field),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// `value` as a `T`, eg. `from_value::<Customer>(record)`
+pub fn from_value<T: Model>(value: Value) -> Result<T, DecodeError> {
+    let id = value.get("Id").and_then(Value::as_str).map(str::to_string);
+
+    serde_json::from_value(value).map_err(|error| DecodeError {
+        entity: Some(T::ENTITY),
+        id,
+        error,
+    })
+}
+
+/// every value of `values` as a `T`, failing on the first one that does not fit
+pub fn from_values<T: Model>(values: Vec<Value>) -> Result<Vec<T>, DecodeError> {
+    values.into_iter().map(from_value).collect()
+}
+
+/// A reference to another entity, eg. the `CustomerRef` of an invoice.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Reference {
+    /// the `Id` of the entity
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MetaData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_updated_time: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Address {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line2: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// the state or province, eg. `CA`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_sub_division_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailAddress {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PhoneNumber {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_form_number: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A line of a transaction; the `<DetailType>` object (eg. `SalesItemLineDetail`) is in `other`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Line {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_num: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail_type: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Customer {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fully_qualified_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_email_addr: Option<EmailAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_phone: Option<PhoneNumber>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bill_addr: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ship_addr: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Vendor {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_email_addr: Option<EmailAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_phone: Option<PhoneNumber>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bill_addr: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acct_num: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Item {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fully_qualified_name: Option<String>,
+    /// eg. `Service`, `Inventory` or `NonInventory`
+    #[serde(default, rename = "Type", skip_serializing_if = "Option::is_none")]
+    pub item_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_price: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purchase_cost: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qty_on_hand: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taxable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub income_account_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expense_account_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Invoice {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line: Vec<Line>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_amt: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bill_email: Option<EmailAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bill_addr: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ship_addr: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Estimate {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    /// eg. `Pending`, `Accepted` or `Closed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line: Vec<Line>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_amt: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bill_email: Option<EmailAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SalesReceipt {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line: Vec<Line>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_amt: Option<Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_method_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deposit_to_account_ref: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_data: Option<MetaData>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+macro_rules! models {
+    ($($model:ident),* $(,)?) => {
+        $(
+            impl QbEntity for $model {
+                const ENTITY: Entity = Entity::$model;
+            }
+
+            impl Model for $model {
+                fn id(&self) -> &str {
+                    &self.id
+                }
+            }
+        )*
+    };
+}
+
+models!(Customer, Estimate, Invoice, Item, SalesReceipt, Vendor);
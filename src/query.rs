@@ -0,0 +1,549 @@
+//! Building queries without interpolating strings by hand.
+//!
+//! ```ignore
+//! use qbtools::{model::Customer, query::{field, Query}};
+//!
+//! let query = Query::select::<Customer>()
+//!     .filter(field("Balance").gt(0))
+//!     .filter(field("DisplayName").like("O'Brien%"))
+//!     .order_by("DisplayName")
+//!     .limit(500)
+//!     .build()?;
+//!
+//! client.query(&query.sql())?;
+//! ```
+//!
+//! Values are quoted and escaped, and field names are checked against `Entity::fields`.
+
+use crate::entity::{Entity, QbEntity};
+
+use quickbooks_ureq::config::QueryConfig;
+
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// the entity has no field with this name
+    UnknownField { entity: Entity, field: String },
+    /// `IN` with no values matches nothing, and is rejected by QBO
+    EmptyIn(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownField { entity, field } => write!(f, "{entity} has no field {field:?}"),
+            Self::EmptyIn(field) => write!(f, "no values given for `{field} IN (...)`"),
+        }
+    }
+}
+
+/// the comparisons QBO queries support (there is no `!=`, `OR` or `NOT`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `%` matches any number of characters
+    Like,
+    In,
+}
+
+impl Operator {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Like => "LIKE",
+            Self::In => "IN",
+        }
+    }
+}
+
+impl FromStr for Operator {
+    type Err = &'static str;
+
+    /// the symbol or a name, eg. `>=` or `ge`
+    fn from_str(operator: &str) -> Result<Self, Self::Err> {
+        match operator.to_lowercase().as_str() {
+            "=" | "==" | "eq" => Ok(Self::Eq),
+            "<" | "lt" => Ok(Self::Lt),
+            "<=" | "le" => Ok(Self::Le),
+            ">" | "gt" => Ok(Self::Gt),
+            ">=" | "ge" => Ok(Self::Ge),
+            "like" => Ok(Self::Like),
+            "in" => Ok(Self::In),
+            _ => Err("Could not parse operator (expected eq, lt, le, gt, ge, like or in)"),
+        }
+    }
+}
+
+/// a value in a condition; everything but booleans is sent as a quoted string
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    String(String),
+    Bool(bool),
+}
+
+impl Literal {
+    fn to_sql(&self) -> String {
+        match self {
+            Self::String(string) => quote(string),
+            Self::Bool(bool) => bool.to_string(),
+        }
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(string: &str) -> Self {
+        Self::String(string.to_string())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(string: String) -> Self {
+        Self::String(string)
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(bool: bool) -> Self {
+        Self::Bool(bool)
+    }
+}
+
+macro_rules! literal_from_number {
+    ($($number:ty),*) => {
+        $(
+            impl From<$number> for Literal {
+                fn from(number: $number) -> Self {
+                    Self::String(number.to_string())
+                }
+            }
+        )*
+    };
+}
+
+literal_from_number!(i32, i64, u32, u64, f32, f64);
+
+/// `'value'`, with backslashes and single quotes escaped, eg. `'O\'Brien'`
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// a field to compare, eg. `field("Balance").gt(0)`
+pub fn field(name: impl Into<String>) -> Field {
+    Field(name.into())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field(String);
+
+impl Field {
+    pub fn compare(self, operator: Operator, value: impl Into<Literal>) -> Condition {
+        Condition::Compare {
+            field: self.0,
+            operator,
+            values: vec![value.into()],
+        }
+    }
+
+    pub fn eq(self, value: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Eq, value)
+    }
+
+    pub fn lt(self, value: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Lt, value)
+    }
+
+    pub fn le(self, value: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Le, value)
+    }
+
+    pub fn gt(self, value: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Gt, value)
+    }
+
+    pub fn ge(self, value: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Ge, value)
+    }
+
+    pub fn like(self, pattern: impl Into<Literal>) -> Condition {
+        self.compare(Operator::Like, pattern)
+    }
+
+    pub fn is_in<I>(self, values: I) -> Condition
+    where
+        I: IntoIterator,
+        I::Item: Into<Literal>,
+    {
+        Condition::Compare {
+            field: self.0,
+            operator: Operator::In,
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Compare {
+        field: String,
+        operator: Operator,
+        values: Vec<Literal>,
+    },
+    /// used as-is, without validation or escaping (eg. `--where`)
+    Raw(String),
+}
+
+impl Condition {
+    pub fn raw(condition: impl Into<String>) -> Self {
+        Self::Raw(condition.into())
+    }
+
+    fn to_sql(&self, entity: Entity) -> Result<String, Error> {
+        let (field, operator, values) = match self {
+            Self::Raw(condition) => return Ok(condition.clone()),
+            Self::Compare {
+                field,
+                operator,
+                values,
+            } => (field, operator, values),
+        };
+
+        if !entity.fields().contains(&field.as_str()) {
+            return Err(Error::UnknownField {
+                entity,
+                field: field.clone(),
+            });
+        }
+
+        let values: Vec<String> = values.iter().map(Literal::to_sql).collect();
+
+        Ok(match operator {
+            Operator::In if values.is_empty() => return Err(Error::EmptyIn(field.clone())),
+            Operator::In => format!("{field} IN ({})", values.join(", ")),
+            operator => format!("{field} {} {}", operator.as_str(), values.join(", ")),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+impl Order {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+impl FromStr for Order {
+    type Err = &'static str;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err> {
+        match order.to_lowercase().as_str() {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err("Could not parse order (expected asc or desc)"),
+        }
+    }
+}
+
+/// `SELECT * FROM <entity>`, with every condition joined by `AND`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    entity: Entity,
+    conditions: Vec<Condition>,
+    order_by: Vec<(String, Order)>,
+    start_position: Option<u32>,
+    max_results: Option<u32>,
+}
+
+impl Query {
+    pub fn select<T: QbEntity>() -> Self {
+        Self::from_entity(T::ENTITY)
+    }
+
+    pub fn from_entity(entity: Entity) -> Self {
+        Self {
+            entity,
+            conditions: Vec::new(),
+            order_by: Vec::new(),
+            start_position: None,
+            max_results: None,
+        }
+    }
+
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn order_by(self, field: impl Into<String>) -> Self {
+        self.order_by_with(field, Order::Asc)
+    }
+
+    pub fn order_by_desc(self, field: impl Into<String>) -> Self {
+        self.order_by_with(field, Order::Desc)
+    }
+
+    pub fn order_by_with(mut self, field: impl Into<String>, order: Order) -> Self {
+        self.order_by.push((field.into(), order));
+        self
+    }
+
+    /// the (1-based) position of the first result
+    pub fn start_position(mut self, start_position: u32) -> Self {
+        self.start_position = Some(start_position);
+        self
+    }
+
+    /// QBO returns at most 1000 results per query
+    pub fn limit(mut self, max_results: u32) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// validates the field names and renders the query
+    pub fn build(&self) -> Result<BuiltQuery, Error> {
+        let conditions = self
+            .conditions
+            .iter()
+            .map(|condition| condition.to_sql(self.entity))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut order_by = Vec::with_capacity(self.order_by.len());
+        for (field, order) in &self.order_by {
+            if !self.entity.fields().contains(&field.as_str()) {
+                return Err(Error::UnknownField {
+                    entity: self.entity,
+                    field: field.clone(),
+                });
+            }
+            order_by.push(format!("{field} {}", order.as_str()));
+        }
+
+        Ok(BuiltQuery {
+            entity: self.entity,
+            conditions: (!conditions.is_empty()).then(|| conditions.join(" AND ")),
+            order_by: (!order_by.is_empty()).then(|| order_by.join(", ")),
+            start_position: self.start_position,
+            max_results: self.max_results,
+        })
+    }
+}
+
+/// a validated query, as a `QueryConfig` or a full statement
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuiltQuery {
+    entity: Entity,
+    conditions: Option<String>,
+    order_by: Option<String>,
+    start_position: Option<u32>,
+    max_results: Option<u32>,
+}
+
+impl BuiltQuery {
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// the conditions, which go after `WHERE`
+    pub fn where_clause(&self) -> Option<&str> {
+        self.conditions.as_deref()
+    }
+
+    /// the fields to order by, which go after `ORDERBY`
+    pub fn order_by_clause(&self) -> Option<&str> {
+        self.order_by.as_deref()
+    }
+
+    /// whether the query has its own `STARTPOSITION` or `MAXRESULTS`
+    pub fn is_paged(&self) -> bool {
+        self.start_position.is_some() || self.max_results.is_some()
+    }
+
+    /// `QueryConfig` has no ordering, so it is only included in [`Self::sql`]
+    pub fn config(&self) -> QueryConfig<'_> {
+        QueryConfig {
+            r#where: self.where_clause(),
+            start_position: self.start_position,
+            max_results: self.max_results,
+        }
+    }
+
+    /// eg. `SELECT * FROM Customer WHERE Balance > '0' ORDERBY DisplayName ASC MAXRESULTS 500`
+    pub fn sql(&self) -> String {
+        let mut sql = format!("SELECT * FROM {}", self.entity);
+
+        if let Some(conditions) = &self.conditions {
+            sql = format!("{sql} WHERE {conditions}");
+        }
+        if let Some(order_by) = &self.order_by {
+            sql = format!("{sql} ORDERBY {order_by}");
+        }
+        if let Some(start_position) = self.start_position {
+            sql = format!("{sql} STARTPOSITION {start_position}");
+        }
+        if let Some(max_results) = self.max_results {
+            sql = format!("{sql} MAXRESULTS {max_results}");
+        }
+
+        sql
+    }
+}
+
+impl fmt::Display for BuiltQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.sql())
+    }
+}
+
+/// `YYYY-MM-DD`
+pub fn is_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+/// `YYYY-MM-DD`, optionally followed by `THH:MM:SS` and a `Z` or `+HH:MM`/`-HH:MM` offset
+pub fn is_date_time(date_time: &str) -> bool {
+    fn is_time(time: &str) -> bool {
+        let bytes = time.as_bytes();
+
+        bytes.len() == 8
+            && bytes.iter().enumerate().all(|(index, byte)| match index {
+                2 | 5 => *byte == b':',
+                _ => byte.is_ascii_digit(),
+            })
+    }
+
+    let Some((date, time)) = date_time.split_once('T') else {
+        return is_date(date_time);
+    };
+    if !is_date(date) || time.len() < 8 || !time.is_char_boundary(8) {
+        return false;
+    }
+
+    let (time, offset) = time.split_at(8);
+    is_time(time)
+        && match offset.strip_prefix(['+', '-']) {
+            Some(offset) => offset.len() == 5 && is_time(&format!("{offset}:00")),
+            None => offset.is_empty() || offset == "Z",
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Customer;
+
+    #[test]
+    fn values_are_quoted_and_escaped() {
+        assert_eq!(quote("Amy"), "'Amy'");
+        assert_eq!(quote("O'Brien"), r"'O\'Brien'");
+        assert_eq!(quote(r"a\' OR 1=1"), r"'a\\\' OR 1=1'");
+    }
+
+    #[test]
+    fn builds_sql() {
+        let query = Query::select::<Customer>()
+            .filter(field("Balance").gt(0))
+            .filter(field("DisplayName").like("O'Brien%"))
+            .filter(field("Active").eq(true))
+            .filter(field("Id").is_in(["1", "2"]))
+            .order_by("DisplayName")
+            .order_by_desc("Balance")
+            .start_position(1001)
+            .limit(500)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r"SELECT * FROM Customer WHERE Balance > '0' AND DisplayName LIKE 'O\'Brien%' AND Active = true AND Id IN ('1', '2') ORDERBY DisplayName ASC, Balance DESC STARTPOSITION 1001 MAXRESULTS 500"
+        );
+        assert!(query.is_paged());
+        assert_eq!(query.config().r#where, query.where_clause());
+    }
+
+    #[test]
+    fn raw_conditions_are_used_as_is() {
+        let query = Query::from_entity(Entity::Customer)
+            .filter(Condition::raw("Notes = 'anything'"))
+            .build()
+            .unwrap();
+
+        assert_eq!(query.where_clause(), Some("Notes = 'anything'"));
+        assert!(!query.is_paged());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let error = Query::select::<Customer>()
+            .filter(field("Balance; DROP").eq(1))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            Error::UnknownField {
+                entity: Entity::Customer,
+                field: "Balance; DROP".to_string(),
+            }
+        );
+
+        assert!(Query::select::<Customer>()
+            .order_by("Nope")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn empty_in_is_rejected() {
+        let error = Query::select::<Customer>()
+            .filter(field("Id").is_in(Vec::<String>::new()))
+            .build()
+            .unwrap_err();
+
+        assert_eq!(error, Error::EmptyIn("Id".to_string()));
+    }
+
+    #[test]
+    fn operators_parse_as_symbols_and_names() {
+        assert_eq!(">=".parse(), Ok(Operator::Ge));
+        assert_eq!("GE".parse(), Ok(Operator::Ge));
+        assert_eq!("==".parse(), Ok(Operator::Eq));
+        assert_eq!("like".parse(), Ok(Operator::Like));
+        assert!("!=".parse::<Operator>().is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_date("2026-01-31"));
+        assert!(!is_date("2026-1-31"));
+        assert!(!is_date("31/01/2026"));
+
+        assert!(is_date_time("2026-01-31"));
+        assert!(is_date_time("2026-01-31T10:00:00"));
+        assert!(is_date_time("2026-01-31T10:00:00Z"));
+        assert!(is_date_time("2026-01-31T10:00:00-08:00"));
+        assert!(!is_date_time("2026-01-31T10:00"));
+        assert!(!is_date_time("2026-01-31T10:00:00-0800"));
+        assert!(!is_date_time("2026-01-31T10:00:00é"));
+    }
+}
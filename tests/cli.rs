@@ -0,0 +1,264 @@
+//! End-to-end tests of the `qbtools` binary against [`MockServer`], through the config it
+//! generates, so nothing is sent to Intuit.
+#![cfg(feature = "mock-server")]
+
+use qbtools::mock::{MockServer, Options};
+
+use serde_json::{json, Value};
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// a mock server, and a directory with a config that points qbtools at it
+struct Session {
+    server: MockServer,
+    directory: PathBuf,
+}
+
+impl Session {
+    fn start(name: &str, options: Options) -> Self {
+        let server = MockServer::start("127.0.0.1:0", options).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("qbtools-cli-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("qb-api-cfg.json"),
+            server.config().to_string(),
+        )
+        .unwrap();
+
+        Self { server, directory }
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_qbtools"))
+            .arg("--quiet")
+            .args(args)
+            .current_dir(&self.directory)
+            .output()
+            .unwrap()
+    }
+
+    /// the JSON `args` prints, failing if the command does
+    fn json(&self, args: &[&str]) -> Value {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "qbtools {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    fn config(&self) -> Value {
+        serde_json::from_str(&fs::read_to_string(self.directory.join("qb-api-cfg.json")).unwrap())
+            .unwrap()
+    }
+
+    fn customer(&self, id: &str) -> Value {
+        self.server
+            .entities("Customer")
+            .into_iter()
+            .find(|customer| customer["Id"] == id)
+            .unwrap()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+#[test]
+fn exports_active_customers() {
+    let session = Session::start("export", Options::default());
+
+    assert_eq!(
+        session.json(&["export", "--fields", "Id,DisplayName", "customers"]),
+        json!([
+            { "Id": "1", "DisplayName": "Amy's Bird Sanctuary" },
+            { "Id": "2", "DisplayName": "Bill's Windsurf Shop" },
+        ])
+    );
+}
+
+#[test]
+#[cfg(feature = "cmd-get")]
+fn gets_a_customer_as_an_object() {
+    let session = Session::start("get", Options::default());
+
+    assert_eq!(
+        session.json(&["get", "--id", "2", "--fields", "Id,DisplayName", "customer"]),
+        json!({ "Id": "2", "DisplayName": "Bill's Windsurf Shop" })
+    );
+}
+
+#[test]
+fn queries_every_page() {
+    let fixtures =
+        std::env::temp_dir().join(format!("qbtools-cli-{}-fixtures", std::process::id()));
+    fs::create_dir_all(&fixtures).unwrap();
+
+    let address = json!({ "Line1": "1 Page St.", "City": "Mountain View" });
+    let company_info = json!([{
+        "Id": "1",
+        "CompanyName": "Paged",
+        "LegalName": "Paged LLC",
+        "CompanyAddr": address,
+        "LegalAddr": address,
+    }]);
+    let customers: Vec<Value> = (1..=5)
+        .map(|id| json!({ "Id": id.to_string(), "SyncToken": "0", "Active": true }))
+        .collect();
+    for (entity, records) in [
+        ("CompanyInfo", company_info),
+        ("Customer", customers.into()),
+    ] {
+        fs::write(fixtures.join(format!("{entity}.json")), records.to_string()).unwrap();
+    }
+
+    let session = Session::start(
+        "query",
+        Options {
+            fixtures: Some(fixtures.clone()),
+            ..Options::default()
+        },
+    );
+    let requests_before = session.server.request_count();
+
+    let records = session.json(&[
+        "query",
+        "SELECT * FROM Customer",
+        "--page-size",
+        "2",
+        "--fields",
+        "Id",
+    ]);
+    fs::remove_dir_all(&fixtures).unwrap();
+
+    assert_eq!(
+        records,
+        json!([{ "Id": "1" }, { "Id": "2" }, { "Id": "3" }, { "Id": "4" }, { "Id": "5" }])
+    );
+    // the company info read when authorizing, then pages of 2, 2 and 1 records
+    assert_eq!(session.server.request_count() - requests_before, 4);
+}
+
+#[test]
+fn updates_a_customer() {
+    let session = Session::start("update", Options::default());
+
+    let updated = session.json(&[
+        "update",
+        "customer",
+        "--id",
+        "1",
+        "--set",
+        "DisplayName=Amy's Birds",
+        "--fields",
+        "Id,SyncToken,DisplayName",
+    ]);
+
+    assert_eq!(
+        updated,
+        json!({ "Id": "1", "SyncToken": "1", "DisplayName": "Amy's Birds" })
+    );
+    assert_eq!(session.customer("1")["DisplayName"], "Amy's Birds");
+}
+
+#[test]
+fn removes_a_customer_after_backing_it_up() {
+    let session = Session::start("remove", Options::default());
+
+    let removed = session.json(&[
+        "remove",
+        "customer",
+        "--id",
+        "2",
+        "--yes",
+        "--backup-dir",
+        "backups",
+        "--fields",
+        "Id,Active",
+    ]);
+
+    assert_eq!(removed, json!([{ "Id": "2", "Active": false }]));
+    assert_eq!(session.customer("2")["Active"], false);
+
+    let backups: Vec<_> = fs::read_dir(session.directory.join("backups"))
+        .unwrap()
+        .collect();
+    assert_eq!(backups.len(), 1);
+}
+
+#[test]
+fn runs_a_batch_and_reports_faults() {
+    let session = Session::start("batch", Options::default());
+    let operations = json!([
+        { "operation": "create", "entity": "Customer", "body": { "DisplayName": "Cool Cars" } },
+        { "operation": "query", "query": "SELECT * FROM Customer WHERE Id = '1'" },
+        { "operation": "update", "entity": "Customer", "body": { "Id": "2", "SyncToken": "0", "sparse": true, "DisplayName": "Stale" } },
+    ]);
+    fs::write(
+        session.directory.join("operations.json"),
+        operations.to_string(),
+    )
+    .unwrap();
+
+    let output = session.run(&["batch", "--input", "operations.json"]);
+    assert!(!output.status.success(), "a fault should fail the batch");
+
+    let results: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results[0]["result"]["DisplayName"], "Cool Cars");
+    assert_eq!(results[1]["result"]["Customer"][0]["Id"], "1");
+    assert_eq!(results[2]["fault"]["Error"][0]["code"], "5010");
+    assert_eq!(session.customer("2")["DisplayName"], "Bill's Windsurf Shop");
+}
+
+#[test]
+fn refreshes_an_expired_access_token() {
+    let session = Session::start(
+        "token",
+        Options {
+            token_lifetime: Some(2),
+            ..Options::default()
+        },
+    );
+    let access_token = session.config()["access_token"].clone();
+
+    // authorizing and the export use up the token, so the next run has to refresh it
+    for _ in 0..2 {
+        let customers = session.json(&["export", "--fields", "Id", "customers"]);
+        assert_eq!(customers, json!([{ "Id": "1" }, { "Id": "2" }]));
+    }
+
+    let config = session.config();
+    assert_ne!(config["access_token"], access_token);
+    assert_eq!(config["refresh_token"], "mock-refresh-token");
+}
+
+#[test]
+fn fails_when_throttled() {
+    let session = Session::start(
+        "throttle",
+        Options {
+            requests_per_minute: Some(1),
+            ..Options::default()
+        },
+    );
+
+    // authorizing is the one request answered
+    let output = session.run(&["export", "customers"]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("429"), "{stderr}");
+}